@@ -0,0 +1,45 @@
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "auction")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub item_id: Uuid,
+    pub reserve: i64,
+    pub increment: i64,
+    pub sealed: bool,
+    pub ends_at: DateTimeWithTimeZone,
+    pub purchase_id: Option<i64>,
+    pub winning_bid: Option<i64>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub settled_by: Option<String>,
+    pub settled_at: Option<DateTimeWithTimeZone>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(has_many = "super::bid::Entity")]
+    Bid,
+    #[sea_orm(
+        belongs_to = "super::items::Entity",
+        from = "Column::ItemId",
+        to = "super::items::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Items,
+}
+
+impl Related<super::bid::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Bid.def()
+    }
+}
+
+impl Related<super::items::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Items.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
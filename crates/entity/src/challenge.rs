@@ -1,4 +1,7 @@
-use crate::{enums::ChallengeCategory, geography::Point};
+use crate::{
+    enums::{ChallengeCategory, UnlockRule},
+    geography::Point,
+};
 use sea_orm::entity::prelude::*;
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
@@ -25,12 +28,21 @@ pub struct Model {
     #[sea_orm(column_type = "Text", nullable, unique)]
     pub code: Option<String>,
     pub open_from: DateTimeWithTimeZone,
+    pub unlock_rule: UnlockRule,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
     #[sea_orm(has_many = "super::challenge_card::Entity")]
     ChallengeCard,
+    #[sea_orm(has_many = "super::challenge_hint::Entity")]
+    ChallengeHint,
+    #[sea_orm(has_one = "super::challenge_photo::Entity")]
+    ChallengePhoto,
+    #[sea_orm(has_one = "super::challenge_qr::Entity")]
+    ChallengeQr,
+    #[sea_orm(has_one = "super::challenge_question::Entity")]
+    ChallengeQuestion,
     #[sea_orm(has_many = "super::daily_challenge::Entity")]
     DailyChallenge,
     #[sea_orm(has_many = "super::tap_events::Entity")]
@@ -43,6 +55,30 @@ impl Related<super::challenge_card::Entity> for Entity {
     }
 }
 
+impl Related<super::challenge_hint::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ChallengeHint.def()
+    }
+}
+
+impl Related<super::challenge_photo::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ChallengePhoto.def()
+    }
+}
+
+impl Related<super::challenge_qr::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ChallengeQr.def()
+    }
+}
+
+impl Related<super::challenge_question::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ChallengeQuestion.def()
+    }
+}
+
 impl Related<super::daily_challenge::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::DailyChallenge.def()
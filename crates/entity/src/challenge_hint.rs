@@ -0,0 +1,33 @@
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "challenge_hint")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub challenge_id: Uuid,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub position: i32,
+    #[sea_orm(column_type = "Text")]
+    pub body: String,
+    pub cost: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::challenge::Entity",
+        from = "Column::ChallengeId",
+        to = "super::challenge::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Challenge,
+}
+
+impl Related<super::challenge::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Challenge.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
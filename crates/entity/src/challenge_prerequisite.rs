@@ -0,0 +1,38 @@
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "challenge_prerequisite")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub challenge_id: Uuid,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub requires_id: Uuid,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::challenge::Entity",
+        from = "Column::ChallengeId",
+        to = "super::challenge::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Challenge,
+    #[sea_orm(
+        belongs_to = "super::challenge::Entity",
+        from = "Column::RequiresId",
+        to = "super::challenge::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Requires,
+}
+
+impl Related<super::challenge::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Challenge.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
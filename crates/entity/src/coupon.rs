@@ -0,0 +1,36 @@
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "coupon")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false, column_type = "Text")]
+    pub code: String,
+    pub percent_off: Option<i32>,
+    pub amount_off: Option<i64>,
+    pub max_redemptions: Option<i32>,
+    pub max_per_user: Option<i32>,
+    pub expires_at: Option<DateTimeWithTimeZone>,
+    pub created_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(has_many = "super::coupon_item::Entity")]
+    CouponItem,
+    #[sea_orm(has_many = "super::coupon_redemption::Entity")]
+    CouponRedemption,
+}
+
+impl Related<super::coupon_item::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::CouponItem.def()
+    }
+}
+
+impl Related<super::coupon_redemption::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::CouponRedemption.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,79 @@
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "coupon_redemption")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    #[sea_orm(column_type = "Text")]
+    pub code: String,
+    pub user_id: Uuid,
+    pub item_id: Uuid,
+    pub purchase_id: Option<i64>,
+    pub quantity: i64,
+    pub list_cost: i64,
+    pub unit_cost: i64,
+    pub redeemed_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::coupon::Entity",
+        from = "Column::Code",
+        to = "super::coupon::Column::Code",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Coupon,
+    #[sea_orm(
+        belongs_to = "super::items::Entity",
+        from = "Column::ItemId",
+        to = "super::items::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Items,
+    #[sea_orm(
+        belongs_to = "super::purchases::Entity",
+        from = "Column::PurchaseId",
+        to = "super::purchases::Column::PurchaseId",
+        on_update = "NoAction",
+        on_delete = "SetNull"
+    )]
+    Purchases,
+    #[sea_orm(
+        belongs_to = "super::users::Entity",
+        from = "Column::UserId",
+        to = "super::users::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Users,
+}
+
+impl Related<super::coupon::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Coupon.def()
+    }
+}
+
+impl Related<super::items::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Items.def()
+    }
+}
+
+impl Related<super::purchases::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Purchases.def()
+    }
+}
+
+impl Related<super::users::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Users.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
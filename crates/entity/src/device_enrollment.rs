@@ -0,0 +1,42 @@
+use sea_orm::entity::prelude::*;
+
+use super::enums::EnrollmentStatus;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "device_enrollment")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub user_id: Uuid,
+    #[sea_orm(column_type = "Text")]
+    pub public_key: String,
+    /// Display only — client-influenced, nothing keys off it.
+    #[sea_orm(column_type = "Text", nullable)]
+    pub label: Option<String>,
+    pub status: EnrollmentStatus,
+    pub requested_at: DateTimeWithTimeZone,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub decided_by: Option<String>,
+    pub decided_at: Option<DateTimeWithTimeZone>,
+    pub enrolled_at: Option<DateTimeWithTimeZone>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::users::Entity",
+        from = "Column::UserId",
+        to = "super::users::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Users,
+}
+
+impl Related<super::users::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Users.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
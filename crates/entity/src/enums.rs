@@ -52,3 +52,71 @@ pub enum OptionKind {
     #[sea_orm(string_value = "text")]
     Text,
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(255))")]
+pub enum TapChannel {
+    #[sea_orm(string_value = "nfc")]
+    Nfc,
+    #[sea_orm(string_value = "answer")]
+    Answer,
+    #[sea_orm(string_value = "photo")]
+    Photo,
+    #[sea_orm(string_value = "qr")]
+    Qr,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(255))")]
+pub enum AnswerMatch {
+    #[sea_orm(string_value = "exact")]
+    Exact,
+    #[sea_orm(string_value = "case_insensitive")]
+    CaseInsensitive,
+    #[sea_orm(string_value = "any_of")]
+    AnyOf,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(255))")]
+pub enum SubmissionStatus {
+    #[sea_orm(string_value = "pending")]
+    Pending,
+    #[sea_orm(string_value = "approved")]
+    Approved,
+    #[sea_orm(string_value = "rejected")]
+    Rejected,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(255))")]
+pub enum EnrollmentStatus {
+    #[sea_orm(string_value = "pending")]
+    Pending,
+    #[sea_orm(string_value = "approved")]
+    Approved,
+    #[sea_orm(string_value = "denied")]
+    Denied,
+    #[sea_orm(string_value = "enrolled")]
+    Enrolled,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(255))")]
+pub enum UnlockRule {
+    #[sea_orm(string_value = "all")]
+    All,
+    #[sea_orm(string_value = "any")]
+    Any,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(255))")]
+pub enum TicketState {
+    #[sea_orm(string_value = "entered")]
+    Entered,
+    #[sea_orm(string_value = "won")]
+    Won,
+    #[sea_orm(string_value = "lost")]
+    Lost,
+}
@@ -0,0 +1,44 @@
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "item_pickup_slot")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub item_id: Uuid,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub slot_id: Uuid,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::items::Entity",
+        from = "Column::ItemId",
+        to = "super::items::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Items,
+    #[sea_orm(
+        belongs_to = "super::pickup_slot::Entity",
+        from = "Column::SlotId",
+        to = "super::pickup_slot::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    PickupSlot,
+}
+
+impl Related<super::items::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Items.def()
+    }
+}
+
+impl Related<super::pickup_slot::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PickupSlot.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
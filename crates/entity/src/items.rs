@@ -17,12 +17,30 @@ pub struct Model {
     #[sea_orm(column_type = "Text", nullable)]
     pub icon_shade: Option<String>,
     pub quantity_available: i64,
+    pub max_per_order: i64,
+    pub max_per_user: Option<i64>,
+    pub window_limit: Option<i64>,
+    pub window_hours: Option<i32>,
+    pub available_from: Option<DateTimeWithTimeZone>,
+    pub available_until: Option<DateTimeWithTimeZone>,
+    pub raffle: bool,
+    pub refund_window_hours: Option<i32>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_one = "super::auction::Entity")]
+    Auction,
     #[sea_orm(has_many = "super::purchases::Entity")]
     Purchases,
+    #[sea_orm(has_one = "super::raffle::Entity")]
+    Raffle,
+}
+
+impl Related<super::auction::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Auction.def()
+    }
 }
 
 impl Related<super::purchases::Entity> for Entity {
@@ -31,4 +49,10 @@ impl Related<super::purchases::Entity> for Entity {
     }
 }
 
+impl Related<super::raffle::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Raffle.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
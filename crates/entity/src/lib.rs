@@ -1,17 +1,37 @@
 pub mod prelude;
 
 pub mod asset;
+pub mod auction;
+pub mod bid;
 pub mod challenge;
 pub mod challenge_card;
+pub mod challenge_hint;
+pub mod challenge_photo;
+pub mod challenge_prerequisite;
+pub mod challenge_qr;
+pub mod challenge_question;
+pub mod coupon;
+pub mod coupon_item;
+pub mod coupon_redemption;
 pub mod daily_challenge;
+pub mod device_enrollment;
 pub mod devices;
 pub mod enums;
 pub mod failed_taps;
 pub mod geography;
+pub mod hint_unlock;
 pub mod item_option;
+pub mod item_pickup_slot;
 pub mod items;
+pub mod pass_registration;
+pub mod pickup_location;
+pub mod pickup_slot;
 pub mod purchase_option;
 pub mod purchases;
+pub mod raffle;
+pub mod refund;
+pub mod submission;
 pub mod tap_events;
 pub mod users;
+pub mod waitlist;
 pub mod wallet_pass;
@@ -0,0 +1,33 @@
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "pass_registration")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false, column_type = "Text")]
+    pub device_library_id: String,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub user_id: Uuid,
+    #[sea_orm(column_type = "Text")]
+    pub push_token: String,
+    pub created_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::wallet_pass::Entity",
+        from = "Column::UserId",
+        to = "super::wallet_pass::Column::UserId",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    WalletPass,
+}
+
+impl Related<super::wallet_pass::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::WalletPass.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,26 @@
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "pickup_location")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    #[sea_orm(column_type = "Text")]
+    pub name: String,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub directions: Option<String>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(has_many = "super::pickup_slot::Entity")]
+    PickupSlot,
+}
+
+impl Related<super::pickup_slot::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PickupSlot.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,48 @@
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "pickup_slot")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    pub location_id: Uuid,
+    pub starts_at: DateTimeWithTimeZone,
+    pub ends_at: DateTimeWithTimeZone,
+    pub capacity: Option<i32>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(has_many = "super::item_pickup_slot::Entity")]
+    ItemPickupSlot,
+    #[sea_orm(
+        belongs_to = "super::pickup_location::Entity",
+        from = "Column::LocationId",
+        to = "super::pickup_location::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    PickupLocation,
+    #[sea_orm(has_many = "super::purchases::Entity")]
+    Purchases,
+}
+
+impl Related<super::item_pickup_slot::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ItemPickupSlot.def()
+    }
+}
+
+impl Related<super::pickup_location::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PickupLocation.def()
+    }
+}
+
+impl Related<super::purchases::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Purchases.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -1,15 +1,38 @@
 pub use super::asset::Entity as Asset;
+pub use super::auction::Entity as Auction;
+pub use super::bid::Entity as Bid;
 pub use super::challenge::Entity as Challenge;
 pub use super::challenge_card::Entity as ChallengeCard;
+pub use super::challenge_hint::Entity as ChallengeHint;
+pub use super::challenge_photo::Entity as ChallengePhoto;
+pub use super::challenge_prerequisite::Entity as ChallengePrerequisite;
+pub use super::challenge_qr::Entity as ChallengeQr;
+pub use super::challenge_question::Entity as ChallengeQuestion;
+pub use super::coupon::Entity as Coupon;
+pub use super::coupon_item::Entity as CouponItem;
+pub use super::coupon_redemption::Entity as CouponRedemption;
 pub use super::daily_challenge::Entity as DailyChallenge;
+pub use super::device_enrollment::Entity as DeviceEnrollment;
 pub use super::devices::Entity as Devices;
-pub use super::enums::{ChallengeCategory, Dorm, OptionKind};
+pub use super::enums::{
+    AnswerMatch, ChallengeCategory, Dorm, EnrollmentStatus, OptionKind, SubmissionStatus,
+    TapChannel, TicketState, UnlockRule,
+};
 pub use super::failed_taps::Entity as FailedTaps;
 pub use super::geography::Point;
+pub use super::hint_unlock::Entity as HintUnlock;
 pub use super::item_option::Entity as ItemOption;
+pub use super::item_pickup_slot::Entity as ItemPickupSlot;
 pub use super::items::Entity as Items;
+pub use super::pass_registration::Entity as PassRegistration;
+pub use super::pickup_location::Entity as PickupLocation;
+pub use super::pickup_slot::Entity as PickupSlot;
 pub use super::purchase_option::Entity as PurchaseOption;
 pub use super::purchases::Entity as Purchases;
+pub use super::raffle::Entity as Raffle;
+pub use super::refund::Entity as Refund;
+pub use super::submission::Entity as Submission;
 pub use super::tap_events::Entity as TapEvents;
 pub use super::users::Entity as Users;
+pub use super::waitlist::Entity as Waitlist;
 pub use super::wallet_pass::Entity as WalletPass;
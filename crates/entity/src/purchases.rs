@@ -1,5 +1,7 @@
 use sea_orm::entity::prelude::*;
 
+use super::enums::TicketState;
+
 #[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
 #[sea_orm(table_name = "purchases")]
 pub struct Model {
@@ -10,6 +12,12 @@ pub struct Model {
     pub quantity: i64,
     pub unit_cost: i64,
     pub received_item_date: Option<Date>,
+    pub purchased_at: Option<DateTimeWithTimeZone>,
+    pub ticket: Option<TicketState>,
+    pub pickup_slot_id: Option<Uuid>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub delivered_by: Option<String>,
+    pub delivered_at: Option<DateTimeWithTimeZone>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -22,6 +30,14 @@ pub enum Relation {
         on_delete = "NoAction"
     )]
     Items,
+    #[sea_orm(
+        belongs_to = "super::pickup_slot::Entity",
+        from = "Column::PickupSlotId",
+        to = "super::pickup_slot::Column::Id",
+        on_update = "NoAction",
+        on_delete = "SetNull"
+    )]
+    PickupSlot,
     #[sea_orm(
         belongs_to = "super::users::Entity",
         from = "Column::UserId",
@@ -38,6 +54,12 @@ impl Related<super::items::Entity> for Entity {
     }
 }
 
+impl Related<super::pickup_slot::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PickupSlot.def()
+    }
+}
+
 impl Related<super::users::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Users.def()
@@ -0,0 +1,56 @@
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "refund")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub purchase_id: i64,
+    pub item_id: Uuid,
+    pub user_id: Uuid,
+    pub quantity: i64,
+    pub unit_cost: i64,
+    pub amount: i64,
+    #[sea_orm(column_type = "Text")]
+    pub reason: String,
+    pub delivered: bool,
+    pub returned: bool,
+    pub damaged: bool,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub refunded_by: Option<String>,
+    pub refunded_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::items::Entity",
+        from = "Column::ItemId",
+        to = "super::items::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Items,
+    #[sea_orm(
+        belongs_to = "super::users::Entity",
+        from = "Column::UserId",
+        to = "super::users::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Users,
+}
+
+impl Related<super::items::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Items.def()
+    }
+}
+
+impl Related<super::users::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Users.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
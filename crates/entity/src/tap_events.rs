@@ -1,4 +1,4 @@
-use crate::geography::Point;
+use crate::{enums::TapChannel, geography::Point};
 use sea_orm::entity::prelude::*;
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
@@ -7,10 +7,14 @@ pub struct Model {
     #[sea_orm(primary_key)]
     pub id: i64,
     pub challenge_id: Uuid,
-    #[sea_orm(column_type = "Text", unique_key = "tap_events_card_id_counter_key")]
-    pub card_id: String,
-    #[sea_orm(unique_key = "tap_events_card_id_counter_key")]
-    pub counter: i64,
+    #[sea_orm(
+        column_type = "Text",
+        nullable,
+        unique_key = "tap_events_card_id_counter_key"
+    )]
+    pub card_id: Option<String>,
+    #[sea_orm(nullable, unique_key = "tap_events_card_id_counter_key")]
+    pub counter: Option<i64>,
     pub time: i64,
     #[sea_orm(
         column_type = "custom(\"geography(Point, 4326)\")",
@@ -22,6 +26,7 @@ pub struct Model {
     #[sea_orm(column_type = "Float", nullable)]
     pub accuracy: Option<f32>,
     pub user_id: Uuid,
+    pub channel: TapChannel,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -17,10 +17,18 @@ pub struct Model {
     #[sea_orm(column_type = "Text")]
     pub signature: String,
     pub created_at: DateTimeWithTimeZone,
+    #[sea_orm(column_type = "Text")]
+    pub auth_token: String,
+    pub updated_at: DateTimeWithTimeZone,
+    pub shown_scottycoins: Option<i64>,
+    pub shown_thistlestones: Option<i64>,
+    pub revoked_at: Option<DateTimeWithTimeZone>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::pass_registration::Entity")]
+    PassRegistration,
     #[sea_orm(
         belongs_to = "super::users::Entity",
         from = "Column::UserId",
@@ -31,6 +39,12 @@ pub enum Relation {
     Users,
 }
 
+impl Related<super::pass_registration::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PassRegistration.def()
+    }
+}
+
 impl Related<super::users::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Users.def()
@@ -24,6 +24,24 @@ mod m20260817_000100_secret_challenges;
 mod m20260817_000200_seed_secret_challenges;
 mod m20260818_000100_purchase_unit_cost;
 mod m20260821_000100_gemstone_corrections;
+mod m20260822_000100_challenge_hints;
+mod m20260823_000100_answer_challenges;
+mod m20260824_000100_photo_submissions;
+mod m20260825_000100_qr_challenges;
+mod m20260826_000100_challenge_prerequisites;
+mod m20260827_000100_purchase_limits;
+mod m20260828_000100_item_sale_windows;
+mod m20260829_000100_raffles;
+mod m20260830_000100_auctions;
+mod m20260831_000100_waitlists;
+mod m20260901_000100_pickups;
+mod m20260902_000100_delivery_audit;
+mod m20260903_000100_refunds;
+mod m20260904_000100_coupons;
+mod m20260905_000100_pass_registrations;
+mod m20260906_000100_pass_balances;
+mod m20260907_000100_pass_revocation;
+mod m20260908_000100_device_enrollments;
 
 pub struct Migrator;
 
@@ -55,6 +73,24 @@ impl MigratorTrait for Migrator {
             Box::new(m20260817_000200_seed_secret_challenges::Migration),
             Box::new(m20260818_000100_purchase_unit_cost::Migration),
             Box::new(m20260821_000100_gemstone_corrections::Migration),
+            Box::new(m20260822_000100_challenge_hints::Migration),
+            Box::new(m20260823_000100_answer_challenges::Migration),
+            Box::new(m20260824_000100_photo_submissions::Migration),
+            Box::new(m20260825_000100_qr_challenges::Migration),
+            Box::new(m20260826_000100_challenge_prerequisites::Migration),
+            Box::new(m20260827_000100_purchase_limits::Migration),
+            Box::new(m20260828_000100_item_sale_windows::Migration),
+            Box::new(m20260829_000100_raffles::Migration),
+            Box::new(m20260830_000100_auctions::Migration),
+            Box::new(m20260831_000100_waitlists::Migration),
+            Box::new(m20260901_000100_pickups::Migration),
+            Box::new(m20260902_000100_delivery_audit::Migration),
+            Box::new(m20260903_000100_refunds::Migration),
+            Box::new(m20260904_000100_coupons::Migration),
+            Box::new(m20260905_000100_pass_registrations::Migration),
+            Box::new(m20260906_000100_pass_balances::Migration),
+            Box::new(m20260907_000100_pass_revocation::Migration),
+            Box::new(m20260908_000100_device_enrollments::Migration),
         ]
     }
 }
@@ -0,0 +1,63 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"
+                CREATE TABLE "challenge_hint" (
+                    "challenge_id" UUID    NOT NULL
+                        CONSTRAINT "challenge_hint_challenge_id_fkey"
+                        REFERENCES "challenge" ("id") ON DELETE CASCADE,
+                    "position"     INTEGER NOT NULL
+                        CONSTRAINT "challenge_hint_position_check"
+                        CHECK ("position" >= 1),
+                    "body"         TEXT    NOT NULL
+                        CONSTRAINT "challenge_hint_body_check"
+                        CHECK (length(btrim("body")) > 0),
+                    "cost"         BIGINT  NOT NULL
+                        CONSTRAINT "challenge_hint_cost_check"
+                        CHECK ("cost" >= 0),
+                    CONSTRAINT "challenge_hint_pkey"
+                        PRIMARY KEY ("challenge_id", "position")
+                );
+
+                CREATE TABLE "hint_unlock" (
+                    "user_id"      UUID    NOT NULL
+                        CONSTRAINT "hint_unlock_user_id_fkey"
+                        REFERENCES "users" ("id") ON DELETE CASCADE,
+                    "challenge_id" UUID    NOT NULL
+                        CONSTRAINT "hint_unlock_challenge_id_fkey"
+                        REFERENCES "challenge" ("id") ON DELETE CASCADE,
+                    "position"     INTEGER NOT NULL,
+                    "cost"         BIGINT  NOT NULL
+                        CONSTRAINT "hint_unlock_cost_check"
+                        CHECK ("cost" >= 0),
+                    "created_at"   TIMESTAMPTZ NOT NULL DEFAULT now(),
+                    CONSTRAINT "hint_unlock_pkey"
+                        PRIMARY KEY ("user_id", "challenge_id", "position")
+                );
+                "#,
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"
+                DROP TABLE "hint_unlock";
+                DROP TABLE "challenge_hint";
+                "#,
+            )
+            .await?;
+        Ok(())
+    }
+}
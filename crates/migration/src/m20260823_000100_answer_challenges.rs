@@ -0,0 +1,83 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const UP: &str = r#"
+    ALTER TABLE "tap_events"
+        ALTER COLUMN "card_id" DROP NOT NULL,
+        ALTER COLUMN "counter" DROP NOT NULL,
+        ADD COLUMN "channel" VARCHAR(255) NOT NULL DEFAULT 'nfc'
+            CONSTRAINT "tap_events_channel_check"
+            CHECK ("channel" IN ('nfc', 'answer')),
+        ADD CONSTRAINT "tap_events_nfc_card_check"
+            CHECK (
+                "channel" <> 'nfc'
+                OR ("card_id" IS NOT NULL AND "counter" IS NOT NULL)
+            );
+
+    CREATE TABLE "challenge_question" (
+        "challenge_id" UUID NOT NULL
+            CONSTRAINT "challenge_question_pkey" PRIMARY KEY
+            CONSTRAINT "challenge_question_challenge_id_fkey"
+            REFERENCES "challenge" ("id") ON DELETE CASCADE,
+        "prompt"       TEXT NOT NULL
+            CONSTRAINT "challenge_question_prompt_check"
+            CHECK (length(btrim("prompt")) > 0),
+        "matching"     VARCHAR(255) NOT NULL DEFAULT 'case_insensitive'
+            CONSTRAINT "challenge_question_matching_check"
+            CHECK ("matching" IN ('exact', 'case_insensitive', 'any_of')),
+        "answers"      JSONB NOT NULL
+            CONSTRAINT "challenge_question_answers_check"
+            CHECK (
+                jsonb_typeof("answers") = 'array'
+                AND jsonb_array_length("answers") > 0
+            ),
+        CONSTRAINT "challenge_question_single_answer_check"
+            CHECK ("matching" = 'any_of' OR jsonb_array_length("answers") = 1)
+    );
+
+    CREATE TABLE "answer_attempt" (
+        "id"           BIGSERIAL NOT NULL PRIMARY KEY,
+        "user_id"      UUID NOT NULL
+            CONSTRAINT "answer_attempt_user_id_fkey"
+            REFERENCES "users" ("id") ON DELETE CASCADE,
+        "challenge_id" UUID NOT NULL
+            CONSTRAINT "answer_attempt_challenge_id_fkey"
+            REFERENCES "challenge" ("id") ON DELETE CASCADE,
+        "answer"       TEXT NOT NULL
+            CONSTRAINT "answer_attempt_answer_check"
+            CHECK (char_length("answer") <= 200),
+        "correct"      BOOLEAN NOT NULL,
+        "created_at"   TIMESTAMPTZ NOT NULL DEFAULT now()
+    );
+
+    CREATE INDEX "answer_attempt_user_id_challenge_id_created_at_idx"
+        ON "answer_attempt" ("user_id", "challenge_id", "created_at");
+"#;
+
+const DOWN: &str = r#"
+    DROP TABLE "answer_attempt";
+    DROP TABLE "challenge_question";
+
+    DELETE FROM "tap_events" WHERE "channel" <> 'nfc';
+
+    ALTER TABLE "tap_events"
+        DROP CONSTRAINT "tap_events_nfc_card_check",
+        DROP COLUMN "channel",
+        ALTER COLUMN "card_id" SET NOT NULL,
+        ALTER COLUMN "counter" SET NOT NULL;
+"#;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.get_connection().execute_unprepared(UP).await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.get_connection().execute_unprepared(DOWN).await?;
+        Ok(())
+    }
+}
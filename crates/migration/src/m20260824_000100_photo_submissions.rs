@@ -0,0 +1,82 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const UP: &str = r#"
+    ALTER TABLE "tap_events"
+        DROP CONSTRAINT "tap_events_channel_check";
+
+    ALTER TABLE "tap_events"
+        ADD CONSTRAINT "tap_events_channel_check"
+        CHECK ("channel" IN ('nfc', 'answer', 'photo'));
+
+    CREATE TABLE "challenge_photo" (
+        "challenge_id" UUID NOT NULL
+            CONSTRAINT "challenge_photo_pkey" PRIMARY KEY
+            CONSTRAINT "challenge_photo_challenge_id_fkey"
+            REFERENCES "challenge" ("id") ON DELETE CASCADE,
+        "prompt"       TEXT NOT NULL
+            CONSTRAINT "challenge_photo_prompt_check"
+            CHECK (length(btrim("prompt")) > 0)
+    );
+
+    CREATE TABLE "submission" (
+        "id"           BIGSERIAL NOT NULL PRIMARY KEY,
+        "user_id"      UUID NOT NULL
+            CONSTRAINT "submission_user_id_fkey"
+            REFERENCES "users" ("id") ON DELETE CASCADE,
+        "challenge_id" UUID NOT NULL
+            CONSTRAINT "submission_challenge_id_fkey"
+            REFERENCES "challenge" ("id") ON DELETE CASCADE,
+        "asset_key"    TEXT NULL,
+        "status"       VARCHAR(255) NOT NULL DEFAULT 'pending'
+            CONSTRAINT "submission_status_check"
+            CHECK ("status" IN ('pending', 'approved', 'rejected')),
+        "reason"       TEXT NULL
+            CONSTRAINT "submission_reason_check"
+            CHECK (
+                "reason" IS NULL
+                OR char_length(btrim("reason")) BETWEEN 1 AND 200
+            ),
+        "reviewed_by"  TEXT NULL,
+        "reviewed_at"  TIMESTAMPTZ NULL,
+        "created_at"   TIMESTAMPTZ NOT NULL DEFAULT now(),
+        CONSTRAINT "submission_rejected_reason_check"
+            CHECK ("status" <> 'rejected' OR "reason" IS NOT NULL)
+    );
+
+    CREATE UNIQUE INDEX "submission_user_id_challenge_id_pending_key"
+        ON "submission" ("user_id", "challenge_id")
+        WHERE "status" = 'pending';
+
+    CREATE INDEX "submission_status_created_at_idx"
+        ON "submission" ("status", "created_at");
+"#;
+
+const DOWN: &str = r#"
+    DROP TABLE "submission";
+    DROP TABLE "challenge_photo";
+
+    DELETE FROM "tap_events" WHERE "channel" = 'photo';
+
+    ALTER TABLE "tap_events"
+        DROP CONSTRAINT "tap_events_channel_check";
+
+    ALTER TABLE "tap_events"
+        ADD CONSTRAINT "tap_events_channel_check"
+        CHECK ("channel" IN ('nfc', 'answer'));
+"#;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.get_connection().execute_unprepared(UP).await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.get_connection().execute_unprepared(DOWN).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,89 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const UP: &str = r#"
+    ALTER TABLE "tap_events"
+        DROP CONSTRAINT "tap_events_channel_check";
+
+    ALTER TABLE "tap_events"
+        ADD CONSTRAINT "tap_events_channel_check"
+        CHECK ("channel" IN ('nfc', 'answer', 'photo', 'qr'));
+
+    CREATE TABLE "challenge_qr" (
+        "challenge_id" UUID NOT NULL
+            CONSTRAINT "challenge_qr_pkey" PRIMARY KEY
+            CONSTRAINT "challenge_qr_challenge_id_fkey"
+            REFERENCES "challenge" ("id") ON DELETE CASCADE,
+        "period"       INTEGER NOT NULL DEFAULT 30
+            CONSTRAINT "challenge_qr_period_check"
+            CHECK ("period" BETWEEN 10 AND 3600)
+    );
+
+    ALTER TABLE "failed_taps"
+        DROP CONSTRAINT "failed_taps_reason_check",
+        ADD CONSTRAINT "failed_taps_reason_check" CHECK ("reason" IN (
+            'tap_body_invalid',
+            'tap_url_malformed',
+            'tap_signature',
+            'card_unassigned',
+            'card_retired',
+            'card_locked',
+            'challenge_row_missing',
+            'tap_out_of_range',
+            'tap_replayed',
+            'location_too_coarse',
+            'no_location_fix',
+            'qr_unassigned',
+            'qr_expired'
+        ));
+"#;
+
+const DOWN: &str = r#"
+    DELETE FROM "failed_taps"
+    WHERE "reason" IN (
+        'location_too_coarse',
+        'no_location_fix',
+        'qr_unassigned',
+        'qr_expired'
+    );
+
+    ALTER TABLE "failed_taps"
+        DROP CONSTRAINT "failed_taps_reason_check",
+        ADD CONSTRAINT "failed_taps_reason_check" CHECK ("reason" IN (
+            'tap_body_invalid',
+            'tap_url_malformed',
+            'tap_signature',
+            'card_unassigned',
+            'card_retired',
+            'card_locked',
+            'challenge_row_missing',
+            'tap_out_of_range',
+            'tap_replayed'
+        ));
+
+    DROP TABLE "challenge_qr";
+
+    DELETE FROM "tap_events" WHERE "channel" = 'qr';
+
+    ALTER TABLE "tap_events"
+        DROP CONSTRAINT "tap_events_channel_check";
+
+    ALTER TABLE "tap_events"
+        ADD CONSTRAINT "tap_events_channel_check"
+        CHECK ("channel" IN ('nfc', 'answer', 'photo'));
+"#;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.get_connection().execute_unprepared(UP).await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.get_connection().execute_unprepared(DOWN).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,85 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const UP: &str = r#"
+    ALTER TABLE "challenge"
+        ADD COLUMN "unlock_rule" VARCHAR(255) NOT NULL DEFAULT 'all'
+            CONSTRAINT "challenge_unlock_rule_check"
+            CHECK ("unlock_rule" IN ('all', 'any'));
+
+    CREATE TABLE "challenge_prerequisite" (
+        "challenge_id" UUID NOT NULL
+            CONSTRAINT "challenge_prerequisite_challenge_id_fkey"
+            REFERENCES "challenge" ("id") ON DELETE CASCADE,
+        "requires_id"  UUID NOT NULL
+            CONSTRAINT "challenge_prerequisite_requires_id_fkey"
+            REFERENCES "challenge" ("id") ON DELETE CASCADE,
+        CONSTRAINT "challenge_prerequisite_pkey"
+            PRIMARY KEY ("challenge_id", "requires_id"),
+        CONSTRAINT "challenge_prerequisite_self_check"
+            CHECK ("challenge_id" <> "requires_id")
+    );
+
+    CREATE INDEX "challenge_prerequisite_requires_id_idx"
+        ON "challenge_prerequisite" ("requires_id");
+
+    ALTER TABLE "failed_taps"
+        DROP CONSTRAINT "failed_taps_reason_check",
+        ADD CONSTRAINT "failed_taps_reason_check" CHECK ("reason" IN (
+            'tap_body_invalid',
+            'tap_url_malformed',
+            'tap_signature',
+            'card_unassigned',
+            'card_retired',
+            'card_locked',
+            'challenge_row_missing',
+            'tap_out_of_range',
+            'tap_replayed',
+            'location_too_coarse',
+            'no_location_fix',
+            'qr_unassigned',
+            'qr_expired',
+            'prerequisites_missing'
+        ));
+"#;
+
+const DOWN: &str = r#"
+    DELETE FROM "failed_taps" WHERE "reason" = 'prerequisites_missing';
+
+    ALTER TABLE "failed_taps"
+        DROP CONSTRAINT "failed_taps_reason_check",
+        ADD CONSTRAINT "failed_taps_reason_check" CHECK ("reason" IN (
+            'tap_body_invalid',
+            'tap_url_malformed',
+            'tap_signature',
+            'card_unassigned',
+            'card_retired',
+            'card_locked',
+            'challenge_row_missing',
+            'tap_out_of_range',
+            'tap_replayed',
+            'location_too_coarse',
+            'no_location_fix',
+            'qr_unassigned',
+            'qr_expired'
+        ));
+
+    DROP TABLE "challenge_prerequisite";
+
+    ALTER TABLE "challenge" DROP COLUMN "unlock_rule";
+"#;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.get_connection().execute_unprepared(UP).await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.get_connection().execute_unprepared(DOWN).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,61 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+// The defaults keep every existing item at one per person.
+const UP: &str = r#"
+    ALTER TABLE "items"
+        ADD COLUMN "max_per_order" BIGINT NOT NULL DEFAULT 1
+            CONSTRAINT "items_max_per_order_check"
+            CHECK ("max_per_order" >= 1),
+        ADD COLUMN "max_per_user"  BIGINT DEFAULT 1
+            CONSTRAINT "items_max_per_user_check"
+            CHECK ("max_per_user" >= 1),
+        ADD COLUMN "window_limit"  BIGINT
+            CONSTRAINT "items_window_limit_check"
+            CHECK ("window_limit" >= 1),
+        ADD COLUMN "window_hours"  INTEGER
+            CONSTRAINT "items_window_hours_check"
+            CHECK ("window_hours" >= 1),
+        ADD CONSTRAINT "items_window_pair_check"
+            CHECK (("window_limit" IS NULL) = ("window_hours" IS NULL));
+
+    -- Orders from before this column have no known purchase time and stay
+    -- NULL, so windowed limits, refund windows and date filters skip them
+    -- rather than treating them all as bought on the day this ran.
+    ALTER TABLE "purchases"
+        ADD COLUMN "purchased_at" TIMESTAMPTZ NULL;
+
+    ALTER TABLE "purchases"
+        ALTER COLUMN "purchased_at" SET DEFAULT now();
+
+    CREATE INDEX "purchases_user_item_idx"
+        ON "purchases" ("user_id", "item_id", "purchased_at");
+"#;
+
+const DOWN: &str = r#"
+    DROP INDEX "purchases_user_item_idx";
+
+    ALTER TABLE "purchases" DROP COLUMN "purchased_at";
+
+    ALTER TABLE "items"
+        DROP CONSTRAINT "items_window_pair_check",
+        DROP COLUMN "window_hours",
+        DROP COLUMN "window_limit",
+        DROP COLUMN "max_per_user",
+        DROP COLUMN "max_per_order";
+"#;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.get_connection().execute_unprepared(UP).await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.get_connection().execute_unprepared(DOWN).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,32 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const UP: &str = r#"
+    ALTER TABLE "items"
+        ADD COLUMN "available_from"  TIMESTAMPTZ,
+        ADD COLUMN "available_until" TIMESTAMPTZ,
+        ADD CONSTRAINT "items_available_window_check"
+            CHECK ("available_from" < "available_until");
+"#;
+
+const DOWN: &str = r#"
+    ALTER TABLE "items"
+        DROP CONSTRAINT "items_available_window_check",
+        DROP COLUMN "available_until",
+        DROP COLUMN "available_from";
+"#;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.get_connection().execute_unprepared(UP).await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.get_connection().execute_unprepared(DOWN).await?;
+        Ok(())
+    }
+}
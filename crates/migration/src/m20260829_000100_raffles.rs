@@ -0,0 +1,58 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const UP: &str = r#"
+    ALTER TABLE "items"
+        ADD COLUMN "raffle" BOOLEAN NOT NULL DEFAULT false;
+
+    ALTER TABLE "purchases"
+        ADD COLUMN "ticket" VARCHAR(255)
+            CONSTRAINT "purchases_ticket_check"
+            CHECK ("ticket" IN ('entered', 'won', 'lost'));
+
+    CREATE TABLE "raffle" (
+        "item_id"      UUID NOT NULL
+            CONSTRAINT "raffle_pkey" PRIMARY KEY
+            CONSTRAINT "raffle_item_id_fkey"
+            REFERENCES "items" ("id") ON DELETE CASCADE,
+        "commitment"   TEXT NOT NULL
+            CONSTRAINT "raffle_commitment_check"
+            CHECK ("commitment" ~ '^[0-9a-f]{64}$'),
+        "seed"         TEXT,
+        "winners"      INTEGER
+            CONSTRAINT "raffle_winners_check"
+            CHECK ("winners" >= 1),
+        "committed_by" TEXT NOT NULL,
+        "committed_at" TIMESTAMPTZ NOT NULL DEFAULT now(),
+        "drawn_by"     TEXT,
+        "drawn_at"     TIMESTAMPTZ,
+        CONSTRAINT "raffle_drawn_check" CHECK (
+            ("drawn_at" IS NULL) = ("seed" IS NULL)
+            AND ("drawn_at" IS NULL) = ("winners" IS NULL)
+            AND ("drawn_at" IS NULL) = ("drawn_by" IS NULL)
+        )
+    );
+"#;
+
+const DOWN: &str = r#"
+    DROP TABLE "raffle";
+
+    ALTER TABLE "purchases" DROP COLUMN "ticket";
+
+    ALTER TABLE "items" DROP COLUMN "raffle";
+"#;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.get_connection().execute_unprepared(UP).await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.get_connection().execute_unprepared(DOWN).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,76 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const UP: &str = r#"
+    CREATE TABLE "auction" (
+        "item_id"     UUID NOT NULL
+            CONSTRAINT "auction_pkey" PRIMARY KEY
+            CONSTRAINT "auction_item_id_fkey"
+            REFERENCES "items" ("id") ON DELETE CASCADE,
+        "reserve"     BIGINT NOT NULL DEFAULT 1
+            CONSTRAINT "auction_reserve_check"
+            CHECK ("reserve" >= 1),
+        "increment"   BIGINT NOT NULL DEFAULT 1
+            CONSTRAINT "auction_increment_check"
+            CHECK ("increment" >= 1),
+        "sealed"      BOOLEAN NOT NULL DEFAULT false,
+        "ends_at"     TIMESTAMPTZ NOT NULL,
+        "purchase_id" BIGINT
+            CONSTRAINT "auction_purchase_id_fkey"
+            REFERENCES "purchases" ("purchase_id") ON DELETE SET NULL,
+        "settled_by"  TEXT,
+        "settled_at"  TIMESTAMPTZ,
+        CONSTRAINT "auction_settled_check"
+            CHECK (("settled_at" IS NULL) = ("settled_by" IS NULL))
+    );
+
+    CREATE TABLE "bid" (
+        "id"          BIGSERIAL NOT NULL
+            CONSTRAINT "bid_pkey" PRIMARY KEY,
+        "item_id"     UUID NOT NULL
+            CONSTRAINT "bid_item_id_fkey"
+            REFERENCES "auction" ("item_id") ON DELETE CASCADE,
+        "user_id"     UUID NOT NULL
+            CONSTRAINT "bid_user_id_fkey"
+            REFERENCES "users" ("id") ON DELETE CASCADE,
+        "amount"      BIGINT NOT NULL
+            CONSTRAINT "bid_amount_check"
+            CHECK ("amount" >= 1),
+        "created_at"  TIMESTAMPTZ NOT NULL DEFAULT now(),
+        "released_at" TIMESTAMPTZ
+    );
+
+    -- Unreleased bids are the escrow counted against a player's balance.
+    CREATE INDEX "bid_held_idx"
+        ON "bid" ("user_id")
+        WHERE "released_at" IS NULL;
+
+    CREATE INDEX "bid_item_id_idx"
+        ON "bid" ("item_id", "amount" DESC);
+
+    ALTER TABLE "auction"
+        ADD COLUMN "winning_bid" BIGINT
+            CONSTRAINT "auction_winning_bid_fkey"
+            REFERENCES "bid" ("id") ON DELETE SET NULL;
+"#;
+
+const DOWN: &str = r#"
+    ALTER TABLE "auction" DROP COLUMN "winning_bid";
+    DROP TABLE "bid";
+    DROP TABLE "auction";
+"#;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.get_connection().execute_unprepared(UP).await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.get_connection().execute_unprepared(DOWN).await?;
+        Ok(())
+    }
+}
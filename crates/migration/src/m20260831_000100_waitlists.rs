@@ -0,0 +1,59 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const UP: &str = r#"
+    CREATE TABLE "waitlist" (
+        "id"             BIGSERIAL NOT NULL
+            CONSTRAINT "waitlist_pkey" PRIMARY KEY,
+        "item_id"        UUID NOT NULL
+            CONSTRAINT "waitlist_item_id_fkey"
+            REFERENCES "items" ("id") ON DELETE CASCADE,
+        "user_id"        UUID NOT NULL
+            CONSTRAINT "waitlist_user_id_fkey"
+            REFERENCES "users" ("id") ON DELETE CASCADE,
+        -- Options are rewritten wholesale on edit, so a choice is pinned by
+        -- position and value the same way purchase_option is.
+        "position"       INTEGER,
+        "choice"         TEXT,
+        "quantity"       BIGINT NOT NULL DEFAULT 1
+            CONSTRAINT "waitlist_quantity_check"
+            CHECK ("quantity" >= 1),
+        "joined_at"      TIMESTAMPTZ NOT NULL DEFAULT now(),
+        "reserved_at"    TIMESTAMPTZ,
+        "reserved_until" TIMESTAMPTZ,
+        CONSTRAINT "waitlist_choice_check"
+            CHECK (("position" IS NULL) = ("choice" IS NULL)),
+        CONSTRAINT "waitlist_reserved_check"
+            CHECK (("reserved_at" IS NULL) = ("reserved_until" IS NULL))
+    );
+
+    CREATE UNIQUE INDEX "waitlist_user_choice_idx"
+        ON "waitlist" (
+            "item_id",
+            "user_id",
+            COALESCE("position", -1),
+            COALESCE("choice", '')
+        );
+
+    CREATE INDEX "waitlist_queue_idx"
+        ON "waitlist" ("item_id", "joined_at", "id");
+"#;
+
+const DOWN: &str = r#"
+    DROP TABLE "waitlist";
+"#;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.get_connection().execute_unprepared(UP).await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.get_connection().execute_unprepared(DOWN).await?;
+        Ok(())
+    }
+}
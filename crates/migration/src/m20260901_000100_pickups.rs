@@ -0,0 +1,74 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const UP: &str = r#"
+    CREATE TABLE "pickup_location" (
+        "id"         UUID NOT NULL DEFAULT gen_random_uuid()
+            CONSTRAINT "pickup_location_pkey" PRIMARY KEY,
+        "name"       TEXT NOT NULL,
+        "directions" TEXT
+    );
+
+    CREATE TABLE "pickup_slot" (
+        "id"          UUID NOT NULL DEFAULT gen_random_uuid()
+            CONSTRAINT "pickup_slot_pkey" PRIMARY KEY,
+        "location_id" UUID NOT NULL
+            CONSTRAINT "pickup_slot_location_id_fkey"
+            REFERENCES "pickup_location" ("id") ON DELETE CASCADE,
+        "starts_at"   TIMESTAMPTZ NOT NULL,
+        "ends_at"     TIMESTAMPTZ NOT NULL,
+        -- Orders the slot can take; NULL for no limit.
+        "capacity"    INTEGER
+            CONSTRAINT "pickup_slot_capacity_check"
+            CHECK ("capacity" IS NULL OR "capacity" >= 0),
+        CONSTRAINT "pickup_slot_window_check"
+            CHECK ("ends_at" > "starts_at")
+    );
+
+    CREATE INDEX "pickup_slot_starts_at_idx"
+        ON "pickup_slot" ("starts_at");
+
+    CREATE TABLE "item_pickup_slot" (
+        "item_id" UUID NOT NULL
+            CONSTRAINT "item_pickup_slot_item_id_fkey"
+            REFERENCES "items" ("id") ON DELETE CASCADE,
+        "slot_id" UUID NOT NULL
+            CONSTRAINT "item_pickup_slot_slot_id_fkey"
+            REFERENCES "pickup_slot" ("id") ON DELETE CASCADE,
+        CONSTRAINT "item_pickup_slot_pkey" PRIMARY KEY ("item_id", "slot_id")
+    );
+
+    CREATE INDEX "item_pickup_slot_slot_id_idx"
+        ON "item_pickup_slot" ("slot_id");
+
+    ALTER TABLE "purchases"
+        ADD COLUMN "pickup_slot_id" UUID
+            CONSTRAINT "purchases_pickup_slot_id_fkey"
+            REFERENCES "pickup_slot" ("id") ON DELETE SET NULL;
+
+    CREATE INDEX "purchases_pickup_slot_id_idx"
+        ON "purchases" ("pickup_slot_id")
+        WHERE "pickup_slot_id" IS NOT NULL;
+"#;
+
+const DOWN: &str = r#"
+    ALTER TABLE "purchases" DROP COLUMN "pickup_slot_id";
+    DROP TABLE "item_pickup_slot";
+    DROP TABLE "pickup_slot";
+    DROP TABLE "pickup_location";
+"#;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.get_connection().execute_unprepared(UP).await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.get_connection().execute_unprepared(DOWN).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,34 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const UP: &str = r#"
+    ALTER TABLE "purchases"
+        ADD COLUMN "delivered_by" TEXT,
+        ADD COLUMN "delivered_at" TIMESTAMPTZ;
+
+    -- Older deliveries only ever recorded the day.
+    UPDATE "purchases"
+    SET "delivered_at" = "received_item_date"::TIMESTAMPTZ
+    WHERE "received_item_date" IS NOT NULL;
+"#;
+
+const DOWN: &str = r#"
+    ALTER TABLE "purchases"
+        DROP COLUMN "delivered_at",
+        DROP COLUMN "delivered_by";
+"#;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.get_connection().execute_unprepared(UP).await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.get_connection().execute_unprepared(DOWN).await?;
+        Ok(())
+    }
+}
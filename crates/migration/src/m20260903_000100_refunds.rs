@@ -0,0 +1,64 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const UP: &str = r#"
+    ALTER TABLE "items"
+        ADD COLUMN "refund_window_hours" INTEGER
+            CONSTRAINT "items_refund_window_hours_check"
+            CHECK ("refund_window_hours" IS NULL OR "refund_window_hours" >= 0);
+
+    -- Purchases shrink or disappear when refunded, so the audit row keeps
+    -- its own copy of what was paid.
+    CREATE TABLE "refund" (
+        "id"           BIGSERIAL NOT NULL
+            CONSTRAINT "refund_pkey" PRIMARY KEY,
+        "purchase_id"  BIGINT NOT NULL,
+        "item_id"      UUID NOT NULL
+            CONSTRAINT "refund_item_id_fkey"
+            REFERENCES "items" ("id") ON DELETE CASCADE,
+        "user_id"      UUID NOT NULL
+            CONSTRAINT "refund_user_id_fkey"
+            REFERENCES "users" ("id") ON DELETE CASCADE,
+        "quantity"     BIGINT NOT NULL
+            CONSTRAINT "refund_quantity_check"
+            CHECK ("quantity" >= 1),
+        "unit_cost"    BIGINT NOT NULL,
+        "amount"       BIGINT NOT NULL,
+        "reason"       TEXT NOT NULL
+            CONSTRAINT "refund_reason_check"
+            CHECK (length(btrim("reason")) > 0),
+        "delivered"    BOOLEAN NOT NULL DEFAULT false,
+        "returned"     BOOLEAN NOT NULL DEFAULT false,
+        "damaged"      BOOLEAN NOT NULL DEFAULT false,
+        "refunded_by"  TEXT,
+        "refunded_at"  TIMESTAMPTZ NOT NULL DEFAULT now(),
+        CONSTRAINT "refund_amount_check"
+            CHECK ("amount" >= 0 AND "amount" <= "quantity" * "unit_cost")
+    );
+
+    CREATE INDEX "refund_item_id_idx"
+        ON "refund" ("item_id", "refunded_at" DESC);
+
+    CREATE INDEX "refund_user_id_idx"
+        ON "refund" ("user_id");
+"#;
+
+const DOWN: &str = r#"
+    DROP TABLE "refund";
+    ALTER TABLE "items" DROP COLUMN "refund_window_hours";
+"#;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.get_connection().execute_unprepared(UP).await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.get_connection().execute_unprepared(DOWN).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,92 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const UP: &str = r#"
+    CREATE TABLE "coupon" (
+        "code"            TEXT NOT NULL
+            CONSTRAINT "coupon_pkey" PRIMARY KEY
+            CONSTRAINT "coupon_code_check"
+            CHECK ("code" = upper(btrim("code")) AND length("code") > 0),
+        "percent_off"     INTEGER
+            CONSTRAINT "coupon_percent_off_check"
+            CHECK ("percent_off" BETWEEN 1 AND 100),
+        "amount_off"      BIGINT
+            CONSTRAINT "coupon_amount_off_check"
+            CHECK ("amount_off" >= 1),
+        "max_redemptions" INTEGER
+            CONSTRAINT "coupon_max_redemptions_check"
+            CHECK ("max_redemptions" >= 1),
+        "max_per_user"    INTEGER
+            CONSTRAINT "coupon_max_per_user_check"
+            CHECK ("max_per_user" >= 1),
+        "expires_at"      TIMESTAMPTZ,
+        "created_at"      TIMESTAMPTZ NOT NULL DEFAULT now(),
+        CONSTRAINT "coupon_discount_check"
+            CHECK (("percent_off" IS NULL) <> ("amount_off" IS NULL))
+    );
+
+    -- A coupon with no rows here applies to every item.
+    CREATE TABLE "coupon_item" (
+        "code"    TEXT NOT NULL
+            CONSTRAINT "coupon_item_code_fkey"
+            REFERENCES "coupon" ("code") ON DELETE CASCADE ON UPDATE CASCADE,
+        "item_id" UUID NOT NULL
+            CONSTRAINT "coupon_item_item_id_fkey"
+            REFERENCES "items" ("id") ON DELETE CASCADE,
+        CONSTRAINT "coupon_item_pkey" PRIMARY KEY ("code", "item_id")
+    );
+
+    -- One row per discounted purchase line. Rows outlive refunds so a
+    -- refunded redemption still counts against the caps.
+    CREATE TABLE "coupon_redemption" (
+        "id"          BIGSERIAL NOT NULL
+            CONSTRAINT "coupon_redemption_pkey" PRIMARY KEY,
+        "code"        TEXT NOT NULL
+            CONSTRAINT "coupon_redemption_code_fkey"
+            REFERENCES "coupon" ("code") ON DELETE CASCADE ON UPDATE CASCADE,
+        "user_id"     UUID NOT NULL
+            CONSTRAINT "coupon_redemption_user_id_fkey"
+            REFERENCES "users" ("id") ON DELETE CASCADE,
+        "item_id"     UUID NOT NULL
+            CONSTRAINT "coupon_redemption_item_id_fkey"
+            REFERENCES "items" ("id") ON DELETE CASCADE,
+        "purchase_id" BIGINT
+            CONSTRAINT "coupon_redemption_purchase_id_fkey"
+            REFERENCES "purchases" ("purchase_id") ON DELETE SET NULL,
+        "quantity"    BIGINT NOT NULL
+            CONSTRAINT "coupon_redemption_quantity_check"
+            CHECK ("quantity" >= 1),
+        "list_cost"   BIGINT NOT NULL,
+        "unit_cost"   BIGINT NOT NULL,
+        "redeemed_at" TIMESTAMPTZ NOT NULL DEFAULT now(),
+        CONSTRAINT "coupon_redemption_cost_check"
+            CHECK ("unit_cost" >= 0 AND "unit_cost" <= "list_cost")
+    );
+
+    CREATE INDEX "coupon_redemption_code_idx"
+        ON "coupon_redemption" ("code", "user_id");
+
+    CREATE INDEX "coupon_redemption_item_id_idx"
+        ON "coupon_redemption" ("item_id");
+"#;
+
+const DOWN: &str = r#"
+    DROP TABLE "coupon_redemption";
+    DROP TABLE "coupon_item";
+    DROP TABLE "coupon";
+"#;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.get_connection().execute_unprepared(UP).await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.get_connection().execute_unprepared(DOWN).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,48 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+// Wallet presents `auth_token` on every web-service call; `updated_at`
+// backs the update tags devices poll with.
+const UP: &str = r#"
+    ALTER TABLE "wallet_pass"
+        ADD COLUMN "auth_token" TEXT NOT NULL
+            DEFAULT replace(gen_random_uuid()::text, '-', '') || replace(gen_random_uuid()::text, '-', ''),
+        ADD COLUMN "updated_at" TIMESTAMPTZ NOT NULL DEFAULT now();
+
+    UPDATE "wallet_pass" SET "updated_at" = "created_at";
+
+    CREATE TABLE "pass_registration" (
+        "device_library_id" TEXT NOT NULL,
+        "user_id"           UUID NOT NULL
+            CONSTRAINT "pass_registration_user_id_fkey"
+            REFERENCES "wallet_pass" ("user_id") ON DELETE CASCADE,
+        "push_token"        TEXT NOT NULL,
+        "created_at"        TIMESTAMPTZ NOT NULL DEFAULT now(),
+        CONSTRAINT "pass_registration_pkey" PRIMARY KEY ("device_library_id", "user_id")
+    );
+
+    CREATE INDEX "pass_registration_user_id_idx"
+        ON "pass_registration" ("user_id");
+"#;
+
+const DOWN: &str = r#"
+    DROP TABLE "pass_registration";
+    ALTER TABLE "wallet_pass"
+        DROP COLUMN "updated_at",
+        DROP COLUMN "auth_token";
+"#;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.get_connection().execute_unprepared(UP).await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.get_connection().execute_unprepared(DOWN).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,31 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+// The balances last printed on the pass, so the next build can say how
+// much they moved by.
+const UP: &str = r#"
+    ALTER TABLE "wallet_pass"
+        ADD COLUMN "shown_scottycoins"   BIGINT,
+        ADD COLUMN "shown_thistlestones" BIGINT;
+"#;
+
+const DOWN: &str = r#"
+    ALTER TABLE "wallet_pass"
+        DROP COLUMN "shown_thistlestones",
+        DROP COLUMN "shown_scottycoins";
+"#;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.get_connection().execute_unprepared(UP).await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.get_connection().execute_unprepared(DOWN).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,29 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+// Set when the device that signed the pass is revoked; cleared when the
+// holder re-signs from another device.
+const UP: &str = r#"
+    ALTER TABLE "wallet_pass"
+        ADD COLUMN "revoked_at" TIMESTAMPTZ;
+"#;
+
+const DOWN: &str = r#"
+    ALTER TABLE "wallet_pass"
+        DROP COLUMN "revoked_at";
+"#;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.get_connection().execute_unprepared(UP).await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.get_connection().execute_unprepared(DOWN).await?;
+        Ok(())
+    }
+}
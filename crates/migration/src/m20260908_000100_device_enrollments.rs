@@ -0,0 +1,57 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+// One row per enrollment attempt. Past the device cap a row waits as
+// `pending` until an enrolled device or staff decides it; an approved row
+// turns `enrolled` when that key next signs in.
+const UP: &str = r#"
+    CREATE TABLE "device_enrollment" (
+        "id"           BIGSERIAL PRIMARY KEY,
+        "user_id"      UUID NOT NULL
+            CONSTRAINT "device_enrollment_user_id_fkey"
+            REFERENCES "users" ("id") ON DELETE CASCADE,
+        "public_key"   TEXT NOT NULL
+            CONSTRAINT "device_enrollment_public_key_canonical"
+            CHECK ("public_key" ~ '^04[0-9a-f]{128}$'),
+        "label"        TEXT
+            CONSTRAINT "device_enrollment_label_length"
+            CHECK (char_length("label") <= 64),
+        "status"       VARCHAR(255) NOT NULL
+            CONSTRAINT "device_enrollment_status_check"
+            CHECK ("status" IN ('pending', 'approved', 'denied', 'enrolled')),
+        "requested_at" TIMESTAMPTZ NOT NULL DEFAULT now(),
+        "decided_by"   TEXT,
+        "decided_at"   TIMESTAMPTZ,
+        "enrolled_at"  TIMESTAMPTZ,
+        CONSTRAINT "device_enrollment_decided"
+            CHECK (("status" IN ('approved', 'denied')) <= ("decided_at" IS NOT NULL)),
+        CONSTRAINT "device_enrollment_enrolled"
+            CHECK (("status" = 'enrolled') = ("enrolled_at" IS NOT NULL))
+    );
+
+    CREATE INDEX "device_enrollment_user_id_idx"
+        ON "device_enrollment" ("user_id", "requested_at");
+
+    CREATE UNIQUE INDEX "device_enrollment_open_key"
+        ON "device_enrollment" ("user_id", "public_key")
+        WHERE "status" IN ('pending', 'approved');
+"#;
+
+const DOWN: &str = r#"
+    DROP TABLE "device_enrollment";
+"#;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.get_connection().execute_unprepared(UP).await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.get_connection().execute_unprepared(DOWN).await?;
+        Ok(())
+    }
+}
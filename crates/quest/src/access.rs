@@ -122,8 +122,17 @@ static GRANTS: &[Grant] = &[
             ("users", Level::Edit),
             ("challenge", Level::Full),
             ("challenge_card", Level::Full),
+            ("challenge_hint", Level::Full),
+            ("challenge_question", Level::Full),
+            ("challenge_photo", Level::Full),
+            ("challenge_qr", Level::Full),
+            ("challenge_prerequisite", Level::Full),
+            ("answer_attempt", Level::Read),
+            ("submission", Level::Edit),
             ("daily_challenge", Level::Edit),
+            ("hint_unlock", Level::Read),
             ("devices", Level::Read),
+            ("device_enrollment", Level::Edit),
             ("tap_events", Level::Read),
             ("failed_taps", Level::Read),
             ("items", Level::Read),
@@ -148,6 +157,17 @@ static GRANTS: &[Grant] = &[
             ("item_option", Level::Full),
             ("purchases", Level::Full),
             ("purchase_option", Level::Full),
+            ("raffle", Level::Full),
+            ("auction", Level::Full),
+            ("bid", Level::Read),
+            ("waitlist", Level::Full),
+            ("pickup_location", Level::Full),
+            ("pickup_slot", Level::Full),
+            ("item_pickup_slot", Level::Full),
+            ("refund", Level::Read),
+            ("coupon", Level::Full),
+            ("coupon_item", Level::Full),
+            ("coupon_redemption", Level::Read),
             ("users", Level::Read),
         ]),
     },
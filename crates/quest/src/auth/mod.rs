@@ -84,6 +84,7 @@ pub enum AuthError {
     Forbidden(&'static str),
     NotFound(&'static str),
     Conflict(&'static str),
+    TooManyRequests(&'static str),
     Upstream(&'static str),
 }
 
@@ -96,6 +97,7 @@ impl AuthError {
             AuthError::Forbidden(e) => (StatusCode::FORBIDDEN, *e),
             AuthError::NotFound(e) => (StatusCode::NOT_FOUND, *e),
             AuthError::Conflict(e) => (StatusCode::CONFLICT, *e),
+            AuthError::TooManyRequests(e) => (StatusCode::TOO_MANY_REQUESTS, *e),
             AuthError::Upstream(e) => (StatusCode::BAD_GATEWAY, *e),
         }
     }
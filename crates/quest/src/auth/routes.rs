@@ -13,11 +13,13 @@ use serde::{Deserialize, Serialize};
 use tower::ServiceBuilder;
 use tower_sessions::Session;
 
-use super::extract::CurrentUser;
+use super::extract::{CurrentDevice, CurrentUser};
 use super::oidc::{Class, GroupClaims, IdClaims, SessionWrapper, validate_return};
 use super::session::{DEVICE_KEY, SESSION_TTL, SessionUser, USER_KEY};
 use super::{Auth, AuthError};
-use crate::devices::Devices;
+use crate::devices::key::{DeviceKey, decode};
+use crate::devices::{Devices, Rotation};
+use crate::passes::{Passes, Signed};
 use crate::users::Users;
 
 const RETURN_KEY: &str = "quest.return";
@@ -72,6 +74,7 @@ fn session_paths() -> utoipa_axum::router::OpenApiRouter<Arc<Auth>> {
     utoipa_axum::router::OpenApiRouter::new()
         .routes(utoipa_axum::routes!(status))
         .routes(utoipa_axum::routes!(logout))
+        .routes(utoipa_axum::routes!(rotate))
 }
 
 fn session_router() -> Router<Arc<Auth>> {
@@ -92,6 +95,7 @@ pub fn unconfigured_router() -> Router {
         .route("/auth/callback", get(unavailable))
         .route("/auth/status", get(unavailable))
         .route("/auth/logout", post(unavailable))
+        .route("/auth/device/rotate", post(unavailable))
 }
 
 async fn oidc_failed(err: MiddlewareError) -> AuthError {
@@ -428,3 +432,133 @@ pub const ALLOWED_HEADERS: [header::HeaderName; 5] = [
     header::COOKIE,
     crate::devices::proof::PROOF_HEADER,
 ];
+
+#[derive(Deserialize, utoipa::ToSchema)]
+struct RotateBody {
+    /// From `GET /auth/challenge`.
+    nonce: String,
+    public_key: String,
+    /// The current key's signature over
+    /// `quest-device-rotate:<nonce>:<new public key, hex>`.
+    endorsement: String,
+    /// The new key's signature over the same message.
+    possession: String,
+    /// The new key's signature over `Q1.<andrew_id>.<pass_issued_at>`,
+    /// required when a wallet pass is held.
+    pass_issued_at: Option<i64>,
+    pass_signature: Option<String>,
+}
+
+#[derive(Serialize, utoipa::ToSchema)]
+struct Rotated {
+    public_key: String,
+    /// The wallet pass was re-signed by the new key.
+    pass_moved: bool,
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/device/rotate",
+    tag = "auth",
+    request_body = RotateBody,
+    responses(
+        (status = OK, body = Rotated),
+        (status = BAD_REQUEST, body = crate::auth::AuthErrBody),
+        (status = UNAUTHORIZED, body = crate::auth::AuthErrBody),
+        (status = NOT_FOUND, body = crate::auth::AuthErrBody),
+        (status = CONFLICT, body = crate::auth::AuthErrBody),
+        (status = BAD_GATEWAY, body = crate::auth::AuthErrBody),
+    ),
+)]
+async fn rotate(
+    State(auth): State<Arc<Auth>>,
+    Extension(users): Extension<Users>,
+    Extension(devices): Extension<Devices>,
+    Extension(passes): Extension<Passes>,
+    CurrentUser(user): CurrentUser,
+    CurrentDevice(bound): CurrentDevice,
+    session: Session,
+    Json(body): Json<RotateBody>,
+) -> Result<Json<Rotated>, AuthError> {
+    let malformed = || AuthError::BadRequest("signature_malformed");
+    let key =
+        DeviceKey::parse(&body.public_key).ok_or(AuthError::BadRequest("public_key_invalid"))?;
+
+    let pass = match (body.pass_issued_at, body.pass_signature.as_deref()) {
+        (Some(issued_at), Some(signature)) => Some(Signed {
+            issued_at,
+            signature: decode(signature).ok_or_else(malformed)?,
+        }),
+        (None, None) => None,
+        _ => return Err(AuthError::BadRequest("pass_signature_incomplete")),
+    };
+
+    let public_key = key.hex().to_owned();
+    let row = users.row(&user).await?;
+    let pending = devices
+        .rotate(
+            row.id,
+            &bound,
+            Rotation {
+                nonce: body.nonce,
+                key,
+                endorsement: decode(&body.endorsement).ok_or_else(malformed)?,
+                possession: decode(&body.possession).ok_or_else(malformed)?,
+                pass,
+            },
+        )
+        .await?;
+
+    // The session moves to the new key before the rotation commits, so a
+    // failure on either side leaves both on the old key.
+    let store_down = || AuthError::Upstream("session_store_unavailable");
+    let moved = async {
+        session
+            .insert(DEVICE_KEY, &public_key)
+            .await
+            .map_err(|_| store_down())?;
+        session.save().await.map_err(|_| store_down())?;
+
+        let id = session
+            .id()
+            .ok_or(AuthError::Upstream("session_no_id"))?
+            .to_string();
+
+        auth.sessions
+            .bind(&user.andrew_id, &public_key, &id)
+            .await?;
+
+        pending.commit().await
+    }
+    .await;
+
+    let moved = match moved {
+        Ok(moved) => moved,
+        Err(err) => {
+            auth.sessions
+                .release(&user.andrew_id, &public_key)
+                .await
+                .ok();
+            if session.insert(DEVICE_KEY, &bound).await.is_ok() {
+                session.save().await.ok();
+            }
+            return Err(err);
+        }
+    };
+
+    // The rotation committed, so the caller must learn its new key even if
+    // the cleanup below fails: the old key's binding expires on its own, and
+    // Wallet picks up a missed push on its next refresh.
+    if let Err(err) = auth.sessions.release(&user.andrew_id, &bound).await {
+        eprintln!("auth: releasing the rotated-out key failed: {err:?}");
+    }
+
+    if moved && let Err(err) = passes.push(row.id).await {
+        eprintln!("auth: pass push after rotate failed: {err:?}");
+    }
+
+    Ok(Json(Rotated {
+        public_key,
+        pass_moved: moved,
+    }))
+}
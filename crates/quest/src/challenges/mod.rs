@@ -1,21 +1,60 @@
 pub mod routes;
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
-use entity::enums::ChallengeCategory;
-use entity::{challenge, tap_events};
+use entity::enums::{ChallengeCategory, UnlockRule};
+use entity::geography::Point;
+use entity::{
+    challenge, challenge_hint, challenge_photo, challenge_prerequisite, challenge_question,
+    hint_unlock, tap_events, users,
+};
 use sea_orm::prelude::Uuid;
 use sea_orm::{
-    ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder, QuerySelect,
+    ActiveEnum, ActiveModelTrait, ActiveValue, ColumnTrait, ConnectionTrait, DatabaseConnection,
+    DbBackend, DbErr, EntityTrait, FromQueryResult, QueryFilter, QueryOrder, QuerySelect,
+    Statement, TransactionTrait,
 };
 
 use crate::auth::AuthError;
+use crate::tokens::{Scope, balances_of};
+
+const NEARBY: &str = r#"
+SELECT "challenge"."id" AS "id",
+       ST_Distance("challenge"."location", "here"."at") AS "metres"
+FROM "challenge",
+     (SELECT ST_SetSRID(ST_MakePoint($1, $2), 4326)::geography AS "at") AS "here"
+WHERE "challenge"."location" IS NOT NULL
+  AND ($3::text IS NULL OR "challenge"."category" = $3)
+  AND ($4::float8 IS NULL OR ST_DWithin("challenge"."location", "here"."at", $4))
+ORDER BY "metres", "challenge"."name"
+"#;
 
 #[derive(Clone)]
 pub struct Challenges {
     db: DatabaseConnection,
 }
 
+#[derive(Debug)]
+pub struct Hint {
+    pub position: i32,
+    pub cost: i64,
+    pub body: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct Unlocked {
+    pub position: i32,
+    pub body: String,
+    pub cost: i64,
+    pub scottycoins: i64,
+}
+
+#[derive(FromQueryResult)]
+struct Distance {
+    id: Uuid,
+    metres: f64,
+}
+
 impl Challenges {
     pub fn new(db: DatabaseConnection) -> Self {
         Self { db }
@@ -39,6 +78,42 @@ impl Challenges {
             .map_err(db_down)
     }
 
+    /// Placed challenges nearest `at` first, with their distance in metres.
+    pub async fn nearby(
+        &self,
+        category: Option<ChallengeCategory>,
+        at: Point,
+        radius: Option<f64>,
+    ) -> Result<Vec<(challenge::Model, f64)>, AuthError> {
+        let order = Distance::find_by_statement(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            NEARBY,
+            [
+                at.lon.into(),
+                at.lat.into(),
+                category.map(|category| category.to_value()).into(),
+                radius.into(),
+            ],
+        ))
+        .all(&self.db)
+        .await
+        .map_err(db_down)?;
+
+        let mut rows: HashMap<Uuid, challenge::Model> = challenge::Entity::find()
+            .filter(challenge::Column::Id.is_in(order.iter().map(|row| row.id)))
+            .all(&self.db)
+            .await
+            .map_err(db_down)?
+            .into_iter()
+            .map(|row| (row.id, row))
+            .collect();
+
+        Ok(order
+            .into_iter()
+            .filter_map(|row| Some((rows.remove(&row.id)?, row.metres)))
+            .collect())
+    }
+
     pub async fn one(&self, id: Uuid) -> Result<challenge::Model, AuthError> {
         challenge::Entity::find_by_id(id)
             .one(&self.db)
@@ -60,9 +135,362 @@ impl Challenges {
 
         Ok(ids.into_iter().collect())
     }
+
+    pub async fn missing(
+        &self,
+        user: Uuid,
+        challenges: &[challenge::Model],
+    ) -> Result<HashMap<Uuid, Vec<Uuid>>, AuthError> {
+        missing(&self.db, user, challenges).await
+    }
+
+    /// The prompt of every answerable challenge among `challenges`.
+    pub async fn questions(&self, challenges: &[Uuid]) -> Result<HashMap<Uuid, String>, AuthError> {
+        if challenges.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let rows = challenge_question::Entity::find()
+            .filter(challenge_question::Column::ChallengeId.is_in(challenges.iter().copied()))
+            .all(&self.db)
+            .await
+            .map_err(db_down)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.challenge_id, row.prompt))
+            .collect())
+    }
+
+    /// What to photograph for every photo-proof challenge among `challenges`.
+    pub async fn photos(&self, challenges: &[Uuid]) -> Result<HashMap<Uuid, String>, AuthError> {
+        if challenges.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let rows = challenge_photo::Entity::find()
+            .filter(challenge_photo::Column::ChallengeId.is_in(challenges.iter().copied()))
+            .all(&self.db)
+            .await
+            .map_err(db_down)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.challenge_id, row.prompt))
+            .collect())
+    }
+
+    /// Every hint on the given challenges in order, with the body filled in
+    /// only where `user` has bought it.
+    pub async fn hints(
+        &self,
+        user: Uuid,
+        challenges: &[Uuid],
+    ) -> Result<HashMap<Uuid, Vec<Hint>>, AuthError> {
+        if challenges.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let defined = challenge_hint::Entity::find()
+            .filter(challenge_hint::Column::ChallengeId.is_in(challenges.iter().copied()))
+            .order_by_asc(challenge_hint::Column::ChallengeId)
+            .order_by_asc(challenge_hint::Column::Position)
+            .all(&self.db)
+            .await
+            .map_err(db_down)?;
+
+        let bought: HashSet<(Uuid, i32)> = hint_unlock::Entity::find()
+            .filter(hint_unlock::Column::UserId.eq(user))
+            .filter(hint_unlock::Column::ChallengeId.is_in(challenges.iter().copied()))
+            .all(&self.db)
+            .await
+            .map_err(db_down)?
+            .into_iter()
+            .map(|row| (row.challenge_id, row.position))
+            .collect();
+
+        let mut out: HashMap<Uuid, Vec<Hint>> = HashMap::new();
+        for row in defined {
+            let unlocked = bought.contains(&(row.challenge_id, row.position));
+            out.entry(row.challenge_id).or_default().push(Hint {
+                position: row.position,
+                cost: row.cost,
+                body: unlocked.then_some(row.body),
+            });
+        }
+
+        Ok(out)
+    }
+
+    /// Buys the hint at `position`. An uncleared challenge still behind its
+    /// prerequisites is refused before anything is charged: hidden from
+    /// players as it is on the board, and `prerequisites_missing` for staff.
+    pub async fn unlock(
+        &self,
+        user: Uuid,
+        challenge: Uuid,
+        position: i32,
+        reveal: bool,
+    ) -> Result<Unlocked, AuthError> {
+        let txn = self.db.begin().await.map_err(db_down)?;
+
+        // Serialises spends per player so two unlocks can't share one balance.
+        users::Entity::find_by_id(user)
+            .lock_exclusive()
+            .one(&txn)
+            .await
+            .map_err(db_down)?
+            .ok_or(AuthError::NotFound("user_unknown"))?;
+
+        let found = challenge::Entity::find_by_id(challenge)
+            .one(&txn)
+            .await
+            .map_err(db_down)?
+            .ok_or(AuthError::NotFound("challenge_unknown"))?;
+
+        let gated = missing(&txn, user, std::slice::from_ref(&found)).await?;
+        if gated.contains_key(&challenge) && !cleared_in(&txn, user, challenge).await? {
+            txn.rollback().await.ok();
+            return Err(if reveal {
+                AuthError::Conflict("prerequisites_missing")
+            } else {
+                AuthError::NotFound("challenge_unknown")
+            });
+        }
+
+        let defined = challenge_hint::Entity::find()
+            .filter(challenge_hint::Column::ChallengeId.eq(challenge))
+            .order_by_asc(challenge_hint::Column::Position)
+            .all(&txn)
+            .await
+            .map_err(db_down)?;
+
+        let bought: HashSet<i32> = hint_unlock::Entity::find()
+            .filter(hint_unlock::Column::UserId.eq(user))
+            .filter(hint_unlock::Column::ChallengeId.eq(challenge))
+            .all(&txn)
+            .await
+            .map_err(db_down)?
+            .into_iter()
+            .map(|row| row.position)
+            .collect();
+
+        let hint = match purchasable(&defined, &bought, position) {
+            Ok(hint) => hint,
+            Err(err) => {
+                txn.rollback().await.ok();
+                return Err(err);
+            }
+        };
+
+        let balance = balances_of(&txn, user, Scope::Lifetime).await?;
+        if hint.cost > balance.scottycoins {
+            txn.rollback().await.ok();
+            return Err(AuthError::Conflict("insufficient_coins"));
+        }
+
+        hint_unlock::ActiveModel {
+            user_id: ActiveValue::Set(user),
+            challenge_id: ActiveValue::Set(challenge),
+            position: ActiveValue::Set(position),
+            cost: ActiveValue::Set(hint.cost),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await
+        .map_err(db_down)?;
+
+        txn.commit().await.map_err(db_down)?;
+
+        Ok(Unlocked {
+            position,
+            body: hint.body.clone(),
+            cost: hint.cost,
+            scottycoins: balance.scottycoins - hint.cost,
+        })
+    }
+}
+
+async fn cleared_in<C: ConnectionTrait>(
+    conn: &C,
+    user: Uuid,
+    challenge: Uuid,
+) -> Result<bool, AuthError> {
+    let found = tap_events::Entity::find()
+        .filter(tap_events::Column::UserId.eq(user))
+        .filter(tap_events::Column::ChallengeId.eq(challenge))
+        .one(conn)
+        .await
+        .map_err(db_down)?;
+
+    Ok(found.is_some())
+}
+
+/// The hint at `position`, provided it is not bought yet and every hint
+/// before it is.
+fn purchasable<'a>(
+    defined: &'a [challenge_hint::Model],
+    bought: &HashSet<i32>,
+    position: i32,
+) -> Result<&'a challenge_hint::Model, AuthError> {
+    let hint = defined
+        .iter()
+        .find(|row| row.position == position)
+        .ok_or(AuthError::NotFound("hint_unknown"))?;
+
+    if bought.contains(&position) {
+        return Err(AuthError::Conflict("hint_already_unlocked"));
+    }
+
+    if defined
+        .iter()
+        .any(|row| row.position < position && !bought.contains(&row.position))
+    {
+        return Err(AuthError::Conflict("hint_out_of_order"));
+    }
+
+    Ok(hint)
+}
+
+/// The prerequisites `user` still has to clear, keyed by challenge. Open
+/// challenges are left out of the map.
+pub async fn missing<C: ConnectionTrait>(
+    conn: &C,
+    user: Uuid,
+    challenges: &[challenge::Model],
+) -> Result<HashMap<Uuid, Vec<Uuid>>, AuthError> {
+    if challenges.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let edges = challenge_prerequisite::Entity::find()
+        .filter(
+            challenge_prerequisite::Column::ChallengeId
+                .is_in(challenges.iter().map(|challenge| challenge.id)),
+        )
+        .order_by_asc(challenge_prerequisite::Column::RequiresId)
+        .all(conn)
+        .await
+        .map_err(db_down)?;
+
+    if edges.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let cleared: HashSet<Uuid> = tap_events::Entity::find()
+        .select_only()
+        .column(tap_events::Column::ChallengeId)
+        .filter(tap_events::Column::UserId.eq(user))
+        .filter(tap_events::Column::ChallengeId.is_in(edges.iter().map(|edge| edge.requires_id)))
+        .distinct()
+        .into_tuple::<Uuid>()
+        .all(conn)
+        .await
+        .map_err(db_down)?
+        .into_iter()
+        .collect();
+
+    let mut requires: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+    for edge in edges {
+        requires
+            .entry(edge.challenge_id)
+            .or_default()
+            .push(edge.requires_id);
+    }
+
+    Ok(challenges
+        .iter()
+        .filter_map(|challenge| {
+            let requires = requires.get(&challenge.id)?;
+            let unmet = unmet(challenge.unlock_rule, requires, &cleared);
+            (!unmet.is_empty()).then_some((challenge.id, unmet))
+        })
+        .collect())
+}
+
+fn unmet(rule: UnlockRule, requires: &[Uuid], cleared: &HashSet<Uuid>) -> Vec<Uuid> {
+    match rule {
+        UnlockRule::All => requires
+            .iter()
+            .filter(|id| !cleared.contains(id))
+            .copied()
+            .collect(),
+        UnlockRule::Any if requires.iter().any(|id| cleared.contains(id)) => Vec::new(),
+        UnlockRule::Any => requires.to_vec(),
+    }
 }
 
 fn db_down(err: DbErr) -> AuthError {
     eprintln!("challenges: {err}");
     AuthError::Upstream("database_unavailable")
 }
+
+#[cfg(test)]
+mod hint_tests {
+    use super::*;
+
+    fn hints() -> Vec<challenge_hint::Model> {
+        (1..=3)
+            .map(|position| challenge_hint::Model {
+                challenge_id: Uuid::nil(),
+                position,
+                body: format!("hint {position}"),
+                cost: i64::from(position) * 5,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn hints_unlock_in_order() {
+        let defined = hints();
+
+        let first = purchasable(&defined, &HashSet::new(), 1).expect("first hint is open");
+        assert_eq!(first.cost, 5);
+
+        let second = purchasable(&defined, &HashSet::from([1]), 2).expect("second follows");
+        assert_eq!(second.body, "hint 2");
+
+        assert!(matches!(
+            purchasable(&defined, &HashSet::from([1]), 3),
+            Err(AuthError::Conflict("hint_out_of_order"))
+        ));
+    }
+
+    #[test]
+    fn bought_and_unknown_hints_are_refused() {
+        let defined = hints();
+
+        assert!(matches!(
+            purchasable(&defined, &HashSet::from([1]), 1),
+            Err(AuthError::Conflict("hint_already_unlocked"))
+        ));
+        assert!(matches!(
+            purchasable(&defined, &HashSet::new(), 4),
+            Err(AuthError::NotFound("hint_unknown"))
+        ));
+    }
+}
+
+#[cfg(test)]
+mod prerequisite_tests {
+    use super::*;
+
+    const A: Uuid = Uuid::from_u128(1);
+    const B: Uuid = Uuid::from_u128(2);
+
+    #[test]
+    fn all_of_lists_every_uncleared_prerequisite() {
+        assert_eq!(unmet(UnlockRule::All, &[A, B], &HashSet::new()), vec![A, B]);
+        assert_eq!(
+            unmet(UnlockRule::All, &[A, B], &HashSet::from([A])),
+            vec![B]
+        );
+        assert!(unmet(UnlockRule::All, &[A, B], &HashSet::from([A, B])).is_empty());
+    }
+
+    #[test]
+    fn any_of_opens_on_the_first_cleared() {
+        assert_eq!(unmet(UnlockRule::Any, &[A, B], &HashSet::new()), vec![A, B]);
+        assert!(unmet(UnlockRule::Any, &[A, B], &HashSet::from([B])).is_empty());
+    }
+}
@@ -1,9 +1,10 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use axum::extract::{Path, Query, State};
 use axum::{Extension, Json};
 use entity::challenge;
 use entity::enums::ChallengeCategory;
+use entity::geography::Point;
 use sea_orm::ActiveEnum;
 use sea_orm::prelude::Uuid;
 use serde::{Deserialize, Serialize};
@@ -11,7 +12,7 @@ use utoipa::{IntoParams, ToSchema};
 use utoipa_axum::router::OpenApiRouter;
 use utoipa_axum::routes;
 
-use super::Challenges;
+use super::{Challenges, Hint};
 use crate::auth::extract::CurrentUser;
 use crate::auth::{AuthErrBody, AuthError};
 use crate::users::Users;
@@ -20,6 +21,7 @@ pub fn router(challenges: Challenges) -> OpenApiRouter {
     OpenApiRouter::new()
         .routes(routes!(list))
         .routes(routes!(one))
+        .routes(routes!(unlock_hint))
         .with_state(challenges)
 }
 
@@ -29,6 +31,25 @@ pub struct Location {
     lon: f64,
 }
 
+#[derive(Serialize, ToSchema)]
+pub struct HintView {
+    position: i32,
+    cost: i64,
+    unlocked: bool,
+    body: Option<String>,
+}
+
+impl From<Hint> for HintView {
+    fn from(hint: Hint) -> Self {
+        Self {
+            position: hint.position,
+            cost: hint.cost,
+            unlocked: hint.body.is_some(),
+            body: hint.body,
+        }
+    }
+}
+
 #[derive(Serialize, ToSchema)]
 pub struct ChallengeView {
     id: String,
@@ -41,6 +62,12 @@ pub struct ChallengeView {
     open_from: String,
     cleared: bool,
     secret: bool,
+    question: Option<String>,
+    photo: Option<String>,
+    hints: Vec<HintView>,
+    missing: Vec<String>,
+    /// Metres from the `near` point, when one was given.
+    distance: Option<f64>,
 }
 
 impl ChallengeView {
@@ -65,9 +92,39 @@ impl ChallengeView {
             open_from: row.open_from.to_rfc3339(),
             cleared,
             secret: row.secret,
+            question: None,
+            photo: None,
+            hints: Vec::new(),
+            missing: Vec::new(),
+            distance: None,
         }
     }
 
+    pub fn with_question(mut self, prompt: Option<String>) -> Self {
+        self.question = prompt;
+        self
+    }
+
+    pub fn with_photo(mut self, prompt: Option<String>) -> Self {
+        self.photo = prompt;
+        self
+    }
+
+    pub fn with_hints(mut self, hints: Vec<Hint>) -> Self {
+        self.hints = hints.into_iter().map(HintView::from).collect();
+        self
+    }
+
+    pub fn with_missing(mut self, missing: Vec<Uuid>) -> Self {
+        self.missing = missing.iter().map(Uuid::to_string).collect();
+        self
+    }
+
+    fn with_distance(mut self, metres: Option<f64>) -> Self {
+        self.distance = metres;
+        self
+    }
+
     fn from_set(row: challenge::Model, cleared: &HashSet<Uuid>, reveal_secret: bool) -> Self {
         let done = cleared.contains(&row.id);
         Self::new(row, done, reveal_secret)
@@ -78,6 +135,26 @@ impl ChallengeView {
 #[into_params(parameter_in = Query)]
 struct ListQuery {
     category: Option<String>,
+    /// `lat,lon` to sort by distance from; unplaced challenges are left out.
+    near: Option<String>,
+    /// Metres around `near` to search within.
+    radius: Option<f64>,
+    /// Only challenges the player has not cleared yet.
+    uncleared: Option<bool>,
+}
+
+fn near(raw: &str) -> Result<Point, AuthError> {
+    let invalid = AuthError::BadRequest("near_invalid");
+
+    let (lat, lon) = raw.split_once(',').ok_or(invalid)?;
+    let lat: f64 = lat.trim().parse().map_err(|_| invalid)?;
+    let lon: f64 = lon.trim().parse().map_err(|_| invalid)?;
+
+    if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+        return Err(invalid);
+    }
+
+    Ok(Point::new(lon, lat))
 }
 
 #[derive(Serialize, ToSchema)]
@@ -95,6 +172,7 @@ struct Board {
     params(ListQuery),
     responses(
         (status = OK, body = Board),
+        (status = BAD_REQUEST, body = AuthErrBody),
         (status = UNAUTHORIZED, body = AuthErrBody),
         (status = BAD_GATEWAY, body = AuthErrBody),
     ),
@@ -113,15 +191,65 @@ async fn list(
         ),
     };
 
+    let at = query.near.as_deref().map(near).transpose()?;
+    let radius = match query.radius {
+        None => None,
+        Some(_) if at.is_none() => return Err(AuthError::BadRequest("radius_without_near")),
+        Some(metres) if metres.is_finite() && metres > 0.0 => Some(metres),
+        Some(_) => return Err(AuthError::BadRequest("radius_invalid")),
+    };
+
     let reveal_secret = user.staff();
     let row = users.row(&user).await?;
     let cleared = challenges.cleared(row.id).await?;
 
-    let views: Vec<ChallengeView> = challenges
-        .list(category)
-        .await?
+    let (mut found, mut distances) = match at {
+        Some(at) => {
+            let ranked = challenges.nearby(category, at, radius).await?;
+            let distances: HashMap<Uuid, f64> = ranked
+                .iter()
+                .map(|(challenge, metres)| (challenge.id, *metres))
+                .collect();
+            let found: Vec<challenge::Model> =
+                ranked.into_iter().map(|(challenge, _)| challenge).collect();
+            (found, distances)
+        }
+        None => (challenges.list(category).await?, HashMap::new()),
+    };
+
+    if query.uncleared.unwrap_or(false) {
+        found.retain(|challenge| !cleared.contains(&challenge.id));
+    }
+
+    // Players only see a challenge once its prerequisites are met; staff see
+    // the whole graph along with what is still missing.
+    let mut missing = challenges.missing(row.id, &found).await?;
+    if !reveal_secret {
+        found.retain(|challenge| {
+            cleared.contains(&challenge.id) || !missing.contains_key(&challenge.id)
+        });
+    }
+
+    let ids: Vec<Uuid> = found.iter().map(|challenge| challenge.id).collect();
+    let mut hints = challenges.hints(row.id, &ids).await?;
+    let mut questions = challenges.questions(&ids).await?;
+    let mut photos = challenges.photos(&ids).await?;
+
+    let views: Vec<ChallengeView> = found
         .into_iter()
-        .map(|found| ChallengeView::from_set(found, &cleared, reveal_secret))
+        .map(|challenge| {
+            let mine = hints.remove(&challenge.id).unwrap_or_default();
+            let prompt = questions.remove(&challenge.id);
+            let photo = photos.remove(&challenge.id);
+            let gated = missing.remove(&challenge.id).unwrap_or_default();
+            let metres = distances.remove(&challenge.id);
+            ChallengeView::from_set(challenge, &cleared, reveal_secret)
+                .with_question(prompt)
+                .with_photo(photo)
+                .with_hints(mine)
+                .with_missing(gated)
+                .with_distance(metres)
+        })
         .collect();
 
     Ok(Json(Board {
@@ -154,10 +282,107 @@ async fn one(
     let reveal_secret = user.staff();
     let row = users.row(&user).await?;
     let cleared = challenges.cleared(row.id).await?;
+    let found = challenges.one(id).await?;
+    let gated = challenges
+        .missing(row.id, std::slice::from_ref(&found))
+        .await?
+        .remove(&id)
+        .unwrap_or_default();
 
-    Ok(Json(ChallengeView::from_set(
-        challenges.one(id).await?,
-        &cleared,
-        reveal_secret,
-    )))
+    if !gated.is_empty() && !reveal_secret && !cleared.contains(&id) {
+        return Err(AuthError::NotFound("challenge_unknown"));
+    }
+
+    let hints = challenges
+        .hints(row.id, &[id])
+        .await?
+        .remove(&id)
+        .unwrap_or_default();
+    let prompt = challenges.questions(&[id]).await?.remove(&id);
+    let photo = challenges.photos(&[id]).await?.remove(&id);
+
+    Ok(Json(
+        ChallengeView::from_set(found, &cleared, reveal_secret)
+            .with_question(prompt)
+            .with_photo(photo)
+            .with_hints(hints)
+            .with_missing(gated),
+    ))
+}
+
+#[derive(Serialize, ToSchema)]
+struct HintBought {
+    position: i32,
+    body: String,
+    cost: i64,
+    scottycoins: i64,
+}
+
+#[utoipa::path(
+    post,
+    path = "/challenges/{id}/hints/{position}",
+    tag = "challenges",
+    params(
+        ("id" = String, Path, description = "Challenge id"),
+        ("position" = i32, Path, description = "Hint position, starting at 1"),
+    ),
+    responses(
+        (status = OK, body = HintBought),
+        (status = BAD_REQUEST, body = AuthErrBody),
+        (status = NOT_FOUND, body = AuthErrBody),
+        (status = CONFLICT, body = AuthErrBody),
+        (status = FORBIDDEN, body = AuthErrBody),
+        (status = UNAUTHORIZED, body = AuthErrBody),
+        (status = BAD_GATEWAY, body = AuthErrBody),
+    ),
+)]
+async fn unlock_hint(
+    State(challenges): State<Challenges>,
+    Extension(users): Extension<Users>,
+    CurrentUser(user): CurrentUser,
+    Path((id, position)): Path<(String, String)>,
+) -> Result<Json<HintBought>, AuthError> {
+    let id = Uuid::parse_str(&id).map_err(|_| AuthError::BadRequest("challenge_id_invalid"))?;
+    let position = position
+        .parse::<i32>()
+        .map_err(|_| AuthError::BadRequest("hint_position_invalid"))?;
+
+    let row = users.row(&user).await?;
+
+    if !row.player {
+        return Err(AuthError::Forbidden("not_a_player"));
+    }
+
+    let bought = challenges
+        .unlock(row.id, id, position, user.staff())
+        .await?;
+
+    Ok(Json(HintBought {
+        position: bought.position,
+        body: bought.body,
+        cost: bought.cost,
+        scottycoins: bought.scottycoins,
+    }))
+}
+
+#[cfg(test)]
+mod near_tests {
+    use super::*;
+
+    #[test]
+    fn near_reads_lat_then_lon() {
+        let at = near("40.4433, -79.9436").expect("point parses");
+        assert_eq!(at.lat, 40.4433);
+        assert_eq!(at.lon, -79.9436);
+    }
+
+    #[test]
+    fn near_refuses_malformed_and_out_of_range_points() {
+        for raw in ["", "40.44", "north,west", "91,0", "0,181", "NaN,0"] {
+            assert!(
+                matches!(near(raw), Err(AuthError::BadRequest("near_invalid"))),
+                "{raw:?} should be refused"
+            );
+        }
+    }
 }
@@ -53,6 +53,25 @@ pub fn derive_key(master: &[u8; 32], label: &[u8; 2], uid: Option<&[u8; 7]>) ->
     k
 }
 
+/// The tag a kiosk QR code carries for one rotation window. Its key comes
+/// from the master secret under its own label, apart from every card key.
+pub fn qr_tag(master: &[u8; 32], challenge: &[u8; 16], window: u64) -> [u8; 16] {
+    let key = derive_key(master, b"QR", None);
+
+    let mut data = [0u8; 24];
+    data[..16].copy_from_slice(challenge);
+    data[16..].copy_from_slice(&window.to_be_bytes());
+
+    let full = hmac_sha256(&key, &data);
+    let mut tag = [0u8; 16];
+    tag.copy_from_slice(&full[..16]);
+    tag
+}
+
+pub fn verify_qr(master: &[u8; 32], challenge: &[u8; 16], window: u64, tag: &[u8; 16]) -> bool {
+    qr_tag(master, challenge, window).ct_eq(tag).into()
+}
+
 fn derive_k_meta(master: &[u8; 32]) -> [u8; 16] {
     derive_key(master, b"K1", None)
 }
@@ -30,6 +30,27 @@ WHERE "challenge"."open_from" <= $2
       WHERE "tap_events"."user_id" = $1
         AND "tap_events"."challenge_id" = "challenge"."id"
   )
+  AND CASE "challenge"."unlock_rule"
+      WHEN 'any' THEN NOT EXISTS (
+          SELECT 1 FROM "challenge_prerequisite"
+          WHERE "challenge_prerequisite"."challenge_id" = "challenge"."id"
+      ) OR EXISTS (
+          SELECT 1 FROM "challenge_prerequisite"
+          JOIN "tap_events"
+            ON "tap_events"."challenge_id" = "challenge_prerequisite"."requires_id"
+           AND "tap_events"."user_id" = $1
+          WHERE "challenge_prerequisite"."challenge_id" = "challenge"."id"
+      )
+      ELSE NOT EXISTS (
+          SELECT 1 FROM "challenge_prerequisite"
+          WHERE "challenge_prerequisite"."challenge_id" = "challenge"."id"
+            AND NOT EXISTS (
+                SELECT 1 FROM "tap_events"
+                WHERE "tap_events"."user_id" = $1
+                  AND "tap_events"."challenge_id" = "challenge_prerequisite"."requires_id"
+            )
+      )
+  END
 ORDER BY random()
 LIMIT 1
 "#;
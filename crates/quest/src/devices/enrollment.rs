@@ -0,0 +1,344 @@
+use chrono::Utc;
+use entity::enums::EnrollmentStatus;
+use entity::{device_enrollment, devices, users};
+use sea_orm::prelude::Uuid;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, ConnectionTrait, EntityTrait, PaginatorTrait,
+    QueryFilter, QueryOrder, QuerySelect, TransactionTrait,
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use super::key::DeviceKey;
+use super::{Devices, db_down};
+use crate::auth::AuthError;
+
+pub const APPROVE_CONTEXT: &str = "quest-device-approve:";
+
+pub const DENY_CONTEXT: &str = "quest-device-deny:";
+
+const DEFAULT_CAP: u64 = 3;
+
+const HISTORY_CAP: u64 = 100;
+
+/// Devices a user may enroll before each new one needs approval.
+pub fn cap_from_env() -> u64 {
+    let Some(raw) = crate::auth::env_opt("DEVICE_CAP") else {
+        return DEFAULT_CAP;
+    };
+
+    parse_cap(&raw).unwrap_or_else(|| {
+        eprintln!("devices: DEVICE_CAP {raw:?} is not a positive count; using {DEFAULT_CAP}");
+        DEFAULT_CAP
+    })
+}
+
+fn parse_cap(raw: &str) -> Option<u64> {
+    raw.trim().parse::<u64>().ok().filter(|cap| *cap > 0)
+}
+
+/// Whether a key may enroll with `enrolled` devices already held: under
+/// the cap, or past it once its open attempt was approved.
+fn admits(enrolled: u64, cap: u64, open: Option<EnrollmentStatus>) -> bool {
+    enrolled < cap || open == Some(EnrollmentStatus::Approved)
+}
+
+/// What a device signs to approve or deny enrollment `id` of `public_key`.
+fn decision_message(approve: bool, id: i64, public_key: &str) -> String {
+    let context = if approve {
+        APPROVE_CONTEXT
+    } else {
+        DENY_CONTEXT
+    };
+    format!("{context}{id}:{public_key}")
+}
+
+/// Who is deciding a pending enrollment.
+pub enum Decider<'a> {
+    /// One of the user's enrolled devices, signing
+    /// `quest-device-approve:<id>:<new key>` (or `-deny:`) with its key.
+    Device {
+        owner: Uuid,
+        public_key: &'a str,
+        signature: &'a [u8],
+    },
+    Staff(&'a str),
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct EnrollmentView {
+    pub id: i64,
+    pub andrew_id: Option<String>,
+    pub public_key: String,
+    pub label: Option<String>,
+    pub status: &'static str,
+    pub requested_at: String,
+    pub decided_by: Option<String>,
+    pub decided_at: Option<String>,
+    pub enrolled_at: Option<String>,
+}
+
+pub fn status_name(status: EnrollmentStatus) -> &'static str {
+    match status {
+        EnrollmentStatus::Pending => "pending",
+        EnrollmentStatus::Approved => "approved",
+        EnrollmentStatus::Denied => "denied",
+        EnrollmentStatus::Enrolled => "enrolled",
+    }
+}
+
+fn view(row: device_enrollment::Model, andrew_id: Option<String>) -> EnrollmentView {
+    EnrollmentView {
+        id: row.id,
+        andrew_id,
+        public_key: row.public_key,
+        label: row.label,
+        status: status_name(row.status),
+        requested_at: row.requested_at.to_rfc3339(),
+        decided_by: row.decided_by,
+        decided_at: row.decided_at.map(|at| at.to_rfc3339()),
+        enrolled_at: row.enrolled_at.map(|at| at.to_rfc3339()),
+    }
+}
+
+/// Records a new key for `owner` and reports whether it may be enrolled
+/// now: under the cap, or past it with an approval waiting. Otherwise the
+/// attempt is left pending. Runs in the caller's transaction, after the
+/// user row is locked.
+pub(super) async fn admit<C: ConnectionTrait>(
+    conn: &C,
+    owner: Uuid,
+    public_key: &str,
+    label: Option<String>,
+    cap: u64,
+) -> Result<bool, AuthError> {
+    let enrolled = devices::Entity::find()
+        .filter(devices::Column::UserId.eq(owner))
+        .count(conn)
+        .await
+        .map_err(db_down)?;
+
+    let open = device_enrollment::Entity::find()
+        .filter(device_enrollment::Column::UserId.eq(owner))
+        .filter(device_enrollment::Column::PublicKey.eq(public_key))
+        .filter(
+            device_enrollment::Column::Status
+                .is_in([EnrollmentStatus::Pending, EnrollmentStatus::Approved]),
+        )
+        .one(conn)
+        .await
+        .map_err(db_down)?;
+
+    if !admits(enrolled, cap, open.as_ref().map(|row| row.status)) {
+        if open.is_none() {
+            device_enrollment::ActiveModel {
+                user_id: ActiveValue::Set(owner),
+                public_key: ActiveValue::Set(public_key.to_owned()),
+                label: ActiveValue::Set(label),
+                status: ActiveValue::Set(EnrollmentStatus::Pending),
+                ..Default::default()
+            }
+            .insert(conn)
+            .await
+            .map_err(db_down)?;
+        }
+        return Ok(false);
+    }
+
+    let now = Utc::now();
+    match open {
+        Some(row) => {
+            device_enrollment::ActiveModel {
+                id: ActiveValue::Unchanged(row.id),
+                status: ActiveValue::Set(EnrollmentStatus::Enrolled),
+                enrolled_at: ActiveValue::Set(Some(now.into())),
+                ..Default::default()
+            }
+            .update(conn)
+            .await
+            .map_err(db_down)?;
+        }
+        None => record(conn, owner, public_key, label).await?,
+    }
+
+    Ok(true)
+}
+
+/// Logs a key that enrolled without going through the cap, as a rotation
+/// replacing one already counted does.
+pub(super) async fn record<C: ConnectionTrait>(
+    conn: &C,
+    owner: Uuid,
+    public_key: &str,
+    label: Option<String>,
+) -> Result<(), AuthError> {
+    device_enrollment::ActiveModel {
+        user_id: ActiveValue::Set(owner),
+        public_key: ActiveValue::Set(public_key.to_owned()),
+        label: ActiveValue::Set(label),
+        status: ActiveValue::Set(EnrollmentStatus::Enrolled),
+        enrolled_at: ActiveValue::Set(Some(Utc::now().into())),
+        ..Default::default()
+    }
+    .insert(conn)
+    .await
+    .map_err(db_down)?;
+
+    Ok(())
+}
+
+impl Devices {
+    /// `owner`'s enrollment attempts, newest first.
+    pub async fn enrollments(&self, owner: Uuid) -> Result<Vec<EnrollmentView>, AuthError> {
+        let rows = device_enrollment::Entity::find()
+            .filter(device_enrollment::Column::UserId.eq(owner))
+            .order_by_desc(device_enrollment::Column::RequestedAt)
+            .limit(HISTORY_CAP)
+            .all(&self.db)
+            .await
+            .map_err(db_down)?;
+
+        Ok(rows.into_iter().map(|row| view(row, None)).collect())
+    }
+
+    /// Enrollments across every user for the portal, newest first,
+    /// optionally only those still waiting.
+    pub async fn enrollment_queue(
+        &self,
+        andrew_id: Option<&str>,
+        pending: bool,
+    ) -> Result<Vec<EnrollmentView>, AuthError> {
+        let mut query = device_enrollment::Entity::find()
+            .find_also_related(users::Entity)
+            .order_by_desc(device_enrollment::Column::RequestedAt)
+            .limit(HISTORY_CAP);
+
+        if let Some(andrew_id) = andrew_id {
+            query = query.filter(users::Column::AndrewId.eq(andrew_id));
+        }
+        if pending {
+            query = query.filter(device_enrollment::Column::Status.eq(EnrollmentStatus::Pending));
+        }
+
+        let rows = query.all(&self.db).await.map_err(db_down)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(row, user)| view(row, user.map(|user| user.andrew_id)))
+            .collect())
+    }
+
+    /// Approves or denies a pending enrollment. An approved key enrolls
+    /// the next time it signs in.
+    pub async fn decide(
+        &self,
+        id: i64,
+        approve: bool,
+        by: Decider<'_>,
+    ) -> Result<EnrollmentView, AuthError> {
+        let unknown = AuthError::NotFound("enrollment_unknown");
+        let txn = self.db.begin().await.map_err(db_down)?;
+
+        let row = device_enrollment::Entity::find_by_id(id)
+            .lock_exclusive()
+            .one(&txn)
+            .await
+            .map_err(db_down)?
+            .ok_or(unknown)?;
+
+        let decided_by = match by {
+            Decider::Device {
+                owner,
+                public_key,
+                signature,
+            } => {
+                if row.user_id != owner {
+                    return Err(unknown);
+                }
+
+                let message = decision_message(approve, row.id, &row.public_key);
+                let signed = DeviceKey::parse(public_key)
+                    .is_some_and(|key| key.verifies(message.as_bytes(), signature));
+                if !signed {
+                    return Err(AuthError::Unauthorized("enrollment_signature"));
+                }
+
+                format!("device:{}", &public_key[..public_key.len().min(16)])
+            }
+            Decider::Staff(andrew_id) => format!("staff:{andrew_id}"),
+        };
+
+        if row.status != EnrollmentStatus::Pending {
+            txn.rollback().await.ok();
+            return Err(AuthError::Conflict("enrollment_decided"));
+        }
+
+        let status = if approve {
+            EnrollmentStatus::Approved
+        } else {
+            EnrollmentStatus::Denied
+        };
+
+        let decided = device_enrollment::ActiveModel {
+            id: ActiveValue::Unchanged(row.id),
+            status: ActiveValue::Set(status),
+            decided_by: ActiveValue::Set(Some(decided_by)),
+            decided_at: ActiveValue::Set(Some(Utc::now().into())),
+            ..Default::default()
+        }
+        .update(&txn)
+        .await
+        .map_err(db_down)?;
+
+        txn.commit().await.map_err(db_down)?;
+
+        Ok(view(decided, None))
+    }
+}
+
+#[cfg(test)]
+mod admit_tests {
+    use super::*;
+
+    #[test]
+    fn keys_enroll_freely_under_the_cap() {
+        assert!(admits(0, 3, None));
+        assert!(admits(2, 3, Some(EnrollmentStatus::Pending)));
+    }
+
+    #[test]
+    fn past_the_cap_only_an_approved_key_enrolls() {
+        assert!(!admits(3, 3, None));
+        assert!(!admits(3, 3, Some(EnrollmentStatus::Pending)));
+        assert!(admits(3, 3, Some(EnrollmentStatus::Approved)));
+        assert!(admits(7, 3, Some(EnrollmentStatus::Approved)));
+    }
+
+    #[test]
+    fn the_cap_must_be_a_positive_count() {
+        assert_eq!(parse_cap(" 5 "), Some(5));
+        assert_eq!(parse_cap("0"), None);
+        assert_eq!(parse_cap("-1"), None);
+        assert_eq!(parse_cap("lots"), None);
+    }
+
+    #[test]
+    fn approvals_and_denials_sign_different_messages() {
+        assert_eq!(
+            decision_message(true, 4, "04ab"),
+            "quest-device-approve:4:04ab"
+        );
+        assert_eq!(
+            decision_message(false, 4, "04ab"),
+            "quest-device-deny:4:04ab"
+        );
+    }
+
+    #[test]
+    fn every_status_has_a_name() {
+        assert_eq!(status_name(EnrollmentStatus::Pending), "pending");
+        assert_eq!(status_name(EnrollmentStatus::Approved), "approved");
+        assert_eq!(status_name(EnrollmentStatus::Denied), "denied");
+        assert_eq!(status_name(EnrollmentStatus::Enrolled), "enrolled");
+    }
+}
@@ -1,3 +1,4 @@
+pub mod enrollment;
 pub mod key;
 pub mod label;
 pub mod proof;
@@ -8,13 +9,14 @@ use axum::http::Method;
 use axum::http::request::Parts;
 use axum::middleware::Next;
 use axum::response::{IntoResponse, Response};
-use entity::{devices, users};
+use entity::{devices, users, wallet_pass};
 use fred::prelude::{KeysInterface, Pool};
 use fred::types::{Expiration, SetOptions};
 use sea_orm::prelude::Uuid;
-use sea_orm::sea_query::OnConflict;
+use sea_orm::sea_query::{Expr, OnConflict};
 use sea_orm::{
-    ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait, ModelTrait, QueryFilter, QueryOrder,
+    ActiveModelTrait, ActiveValue, ColumnTrait, DatabaseConnection, DatabaseTransaction,
+    EntityTrait, ModelTrait, QueryFilter, QueryOrder, QuerySelect, TransactionTrait,
 };
 
 use crate::auth::AuthError;
@@ -26,10 +28,14 @@ pub const NONCE_TTL_SECS: i64 = 120;
 
 pub const TICKET_TTL_SECS: i64 = 600;
 
+pub const ROTATE_CONTEXT: &str = "quest-device-rotate:";
+
 #[derive(Clone)]
 pub struct Devices {
     db: DatabaseConnection,
     valkey: Pool,
+    /// Devices a user may hold before new ones need approval.
+    cap: u64,
 }
 
 fn nonce_key(nonce: &str) -> String {
@@ -56,7 +62,11 @@ fn is_token(value: &str) -> bool {
 
 impl Devices {
     pub fn new(db: DatabaseConnection, valkey: Pool) -> Self {
-        Self { db, valkey }
+        Self {
+            db,
+            valkey,
+            cap: enrollment::cap_from_env(),
+        }
     }
 
     async fn put(&self, key: String, value: &str, ttl: i64) -> Result<(), AuthError> {
@@ -112,6 +122,43 @@ impl Devices {
             .and_then(|raw| serde_json::from_str(&raw).ok())
             .ok_or(unverified)?;
 
+        let txn = self.db.begin().await.map_err(db_down)?;
+
+        // Serialises a user's enrollments so two phones cannot both take
+        // the last place under the cap.
+        users::Entity::find_by_id(owner)
+            .lock_exclusive()
+            .one(&txn)
+            .await
+            .map_err(db_down)?
+            .ok_or(AuthError::Upstream("user_row_missing"))?;
+
+        let known = devices::Entity::find_by_id(&held.public_key)
+            .one(&txn)
+            .await
+            .map_err(db_down)?;
+
+        match known {
+            Some(bound) if bound.user_id != owner => {
+                txn.rollback().await.ok();
+                return Err(AuthError::Conflict("device_owned"));
+            }
+            Some(_) => {
+                txn.commit().await.map_err(db_down)?;
+                return Ok(held.public_key);
+            }
+            None => {}
+        }
+
+        let admitted =
+            enrollment::admit(&txn, owner, &held.public_key, held.label.clone(), self.cap).await?;
+
+        if !admitted {
+            // Keep the pending request; it is what gets approved.
+            txn.commit().await.map_err(db_down)?;
+            return Err(AuthError::Conflict("device_approval_pending"));
+        }
+
         let fresh = devices::ActiveModel {
             public_key: ActiveValue::Set(held.public_key.clone()),
             user_id: ActiveValue::Set(owner),
@@ -125,20 +172,22 @@ impl Devices {
                     .do_nothing_on([devices::Column::PublicKey])
                     .to_owned(),
             )
-            .exec_without_returning(&self.db)
+            .exec_without_returning(&txn)
             .await
             .map_err(db_down)?;
 
         let bound = devices::Entity::find_by_id(&held.public_key)
-            .one(&self.db)
+            .one(&txn)
             .await
             .map_err(db_down)?
             .ok_or(AuthError::Upstream("device_row_missing"))?;
 
         if bound.user_id != owner {
+            txn.rollback().await.ok();
             return Err(AuthError::Conflict("device_owned"));
         }
 
+        txn.commit().await.map_err(db_down)?;
         Ok(held.public_key)
     }
 
@@ -152,21 +201,119 @@ impl Devices {
             .map_err(db_down)
     }
 
-    pub async fn revoke(&self, andrew_id: &str, public_key: &str) -> Result<(), AuthError> {
+    /// Removes the device and revokes the wallet pass it signed, if any;
+    /// true when a pass was revoked and should be pushed.
+    pub async fn revoke(&self, andrew_id: &str, public_key: &str) -> Result<bool, AuthError> {
         let unknown = AuthError::NotFound("device_unknown");
+        let txn = self.db.begin().await.map_err(db_down)?;
+
         let (device, owner) = devices::Entity::find_by_id(public_key)
             .find_also_related(users::Entity)
-            .one(&self.db)
+            .one(&txn)
             .await
             .map_err(db_down)?
             .ok_or(unknown)?;
 
         if owner.map(|owner| owner.andrew_id).as_deref() != Some(andrew_id) {
+            txn.rollback().await.ok();
             return Err(unknown);
         }
 
-        device.delete(&self.db).await.map_err(db_down)?;
-        Ok(())
+        // Bumping `updated_at` marks the pass changed; once the caller pushes
+        // it, Wallet fetches it again and finds it refuses to build until the
+        // holder re-signs it.
+        let now = chrono::Utc::now();
+        let revoked = wallet_pass::Entity::update_many()
+            .col_expr(wallet_pass::Column::RevokedAt, Expr::value(now))
+            .col_expr(wallet_pass::Column::UpdatedAt, Expr::value(now))
+            .filter(wallet_pass::Column::UserId.eq(device.user_id))
+            .filter(wallet_pass::Column::PublicKey.eq(device.public_key.as_str()))
+            .filter(wallet_pass::Column::RevokedAt.is_null())
+            .exec(&txn)
+            .await
+            .map_err(db_down)?;
+
+        device.delete(&txn).await.map_err(db_down)?;
+        txn.commit().await.map_err(db_down)?;
+
+        Ok(revoked.rows_affected > 0)
+    }
+
+    /// Replaces `old` with the key in `rotation`, keeping its owner and
+    /// label. Both keys must sign `quest-device-rotate:<nonce>:<new key>`:
+    /// the old one to endorse the new, the new one to prove it is held.
+    /// The device row and the wallet pass move in one transaction, handed
+    /// back uncommitted so the caller can move the session alongside it.
+    pub async fn rotate(
+        &self,
+        owner: Uuid,
+        old: &str,
+        rotation: Rotation,
+    ) -> Result<Pending, AuthError> {
+        let Rotation {
+            nonce,
+            key,
+            endorsement,
+            possession,
+            pass,
+        } = rotation;
+
+        if !is_token(&nonce) {
+            return Err(AuthError::Unauthorized("nonce_invalid"));
+        }
+        self.take(nonce_key(&nonce))
+            .await?
+            .ok_or(AuthError::Unauthorized("nonce_invalid"))?;
+
+        if key.hex() == old {
+            return Err(AuthError::BadRequest("rotation_same_key"));
+        }
+
+        let message = rotation_message(&nonce, key.hex());
+        let endorsed = DeviceKey::parse(old)
+            .is_some_and(|current| current.verifies(message.as_bytes(), &endorsement));
+        if !endorsed {
+            return Err(AuthError::Unauthorized("rotation_endorsement"));
+        }
+        if !key.verifies(message.as_bytes(), &possession) {
+            return Err(AuthError::Unauthorized("rotation_possession"));
+        }
+
+        let txn = self.db.begin().await.map_err(db_down)?;
+
+        let current = devices::Entity::find_by_id(old)
+            .lock_exclusive()
+            .one(&txn)
+            .await
+            .map_err(db_down)?
+            .filter(|device| device.user_id == owner)
+            .ok_or(AuthError::NotFound("device_unknown"))?;
+
+        let taken = devices::Entity::find_by_id(key.hex())
+            .one(&txn)
+            .await
+            .map_err(db_down)?;
+        if taken.is_some() {
+            txn.rollback().await.ok();
+            return Err(AuthError::Conflict("device_owned"));
+        }
+
+        devices::ActiveModel {
+            public_key: ActiveValue::Set(key.hex().to_owned()),
+            user_id: ActiveValue::Set(owner),
+            label: ActiveValue::Set(current.label.clone()),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await
+        .map_err(db_down)?;
+
+        enrollment::record(&txn, owner, key.hex(), current.label.clone()).await?;
+        let moved = crate::passes::rebind(&txn, owner, old, &key, pass).await?;
+
+        current.delete(&txn).await.map_err(db_down)?;
+
+        Ok(Pending { txn, moved })
     }
 
     async fn claim_jti(&self, jti: &str, now: i64) -> Result<bool, AuthError> {
@@ -252,6 +399,37 @@ impl Devices {
     }
 }
 
+/// A rotation written but not yet committed; dropping it rolls back.
+pub struct Pending {
+    txn: DatabaseTransaction,
+    /// The wallet pass moved to the new key.
+    pub moved: bool,
+}
+
+impl Pending {
+    pub async fn commit(self) -> Result<bool, AuthError> {
+        self.txn.commit().await.map_err(db_down)?;
+        Ok(self.moved)
+    }
+}
+
+/// What both keys sign to rotate from one to the other.
+fn rotation_message(nonce: &str, key: &str) -> String {
+    format!("{ROTATE_CONTEXT}{nonce}:{key}")
+}
+
+/// A request to swap a device's key for `key`.
+pub struct Rotation {
+    pub nonce: String,
+    pub key: DeviceKey,
+    /// The old key's signature over the rotation message.
+    pub endorsement: Vec<u8>,
+    /// The new key's signature over the same message.
+    pub possession: Vec<u8>,
+    /// The new key's signature for the wallet pass, if one is held.
+    pub pass: Option<crate::passes::Signed>,
+}
+
 #[derive(serde::Serialize, serde::Deserialize)]
 struct Held {
     public_key: String,
@@ -271,6 +449,8 @@ fn store_down(err: fred::error::Error) -> AuthError {
 fn bootstrap(method: &Method, path: &str) -> bool {
     match path {
         _ if method == Method::OPTIONS => true,
+        // Wallet authenticates with the pass's own token instead.
+        _ if path.starts_with("/api/passkit/v1/") => true,
         "/api/health" => method == Method::GET,
         "/tap" => method == Method::GET,
         "/.well-known/apple-app-site-association" => method == Method::GET,
@@ -313,3 +493,46 @@ impl From<devices::Model> for DeviceView {
         }
     }
 }
+
+#[cfg(test)]
+mod rotation_tests {
+    use p256::ecdsa::signature::Signer as _;
+    use p256::elliptic_curve::rand_core::OsRng;
+    use p256::elliptic_curve::sec1::ToEncodedPoint as _;
+
+    use super::*;
+
+    fn device() -> (p256::ecdsa::SigningKey, DeviceKey) {
+        let signing = p256::ecdsa::SigningKey::random(&mut OsRng);
+        let public = hex::encode(
+            p256::PublicKey::from(*signing.verifying_key())
+                .to_encoded_point(false)
+                .as_bytes(),
+        );
+        let key = DeviceKey::parse(&public).expect("key parses");
+        (signing, key)
+    }
+
+    #[test]
+    fn tokens_are_32_hex_digits() {
+        assert!(is_token(&token()));
+        assert!(!is_token("abc"));
+        assert!(!is_token(&"g".repeat(32)));
+    }
+
+    #[test]
+    fn the_rotation_message_binds_nonce_and_new_key() {
+        let (old_signing, old) = device();
+        let (_, new) = device();
+        let (_, other) = device();
+
+        let message = rotation_message("n1", new.hex());
+        assert_eq!(message, format!("quest-device-rotate:n1:{}", new.hex()));
+
+        let endorsement: p256::ecdsa::Signature = old_signing.sign(message.as_bytes());
+        let endorsement = endorsement.to_bytes();
+        assert!(old.verifies(message.as_bytes(), &endorsement));
+        assert!(!old.verifies(rotation_message("n1", other.hex()).as_bytes(), &endorsement));
+        assert!(!old.verifies(rotation_message("n2", new.hex()).as_bytes(), &endorsement));
+    }
+}
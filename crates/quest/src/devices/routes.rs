@@ -7,11 +7,13 @@ use utoipa::ToSchema;
 use utoipa_axum::router::OpenApiRouter;
 use utoipa_axum::routes;
 
+use super::enrollment::{Decider, EnrollmentView};
 use super::key::{DeviceKey, decode};
 use super::{DeviceView, Devices, NONCE_TTL_SECS, TICKET_TTL_SECS, label};
 use crate::auth::extract::{CurrentDevice, CurrentUser, SignedIn};
-use crate::auth::session::DEVICE_KEY;
+use crate::auth::session::{DEVICE_KEY, SessionUser};
 use crate::auth::{AuthErrBody, AuthError};
+use crate::passes::Passes;
 use crate::users::Users;
 
 const LOGIN_CONTEXT: &str = "quest-device-login:";
@@ -28,6 +30,9 @@ pub fn manage(devices: Devices) -> OpenApiRouter {
     OpenApiRouter::new()
         .routes(routes!(list))
         .routes(routes!(revoke))
+        .routes(routes!(enrollments))
+        .routes(routes!(approve_enrollment))
+        .routes(routes!(deny_enrollment))
         .with_state(devices)
 }
 
@@ -202,6 +207,9 @@ async fn list(
 #[derive(Serialize, ToSchema)]
 struct Revoked {
     revoked: bool,
+    /// The wallet pass this device signed was revoked with it; reissue it
+    /// from another device with `POST /passes/reissue`.
+    pass_revoked: bool,
 }
 
 #[utoipa::path(
@@ -218,13 +226,15 @@ struct Revoked {
 )]
 async fn revoke(
     State(devices): State<Devices>,
+    Extension(users): Extension<Users>,
+    Extension(passes): Extension<Passes>,
     CurrentUser(user): CurrentUser,
     CurrentDevice(bound): CurrentDevice,
     session: Session,
     Path(public_key): Path<String>,
 ) -> Result<Json<Revoked>, AuthError> {
     let key = DeviceKey::parse(&public_key).ok_or(AuthError::NotFound("device_unknown"))?;
-    devices.revoke(&user.andrew_id, key.hex()).await?;
+    let pass_revoked = devices.revoke(&user.andrew_id, key.hex()).await?;
 
     if key.hex() == bound {
         session
@@ -233,5 +243,128 @@ async fn revoke(
             .map_err(|_| AuthError::Upstream("session_store_unavailable"))?;
     }
 
-    Ok(Json(Revoked { revoked: true }))
+    // The revocation already committed; a missed push only leaves the old
+    // pass in Wallet until it next refreshes and finds it voided.
+    if pass_revoked {
+        let pushed = async {
+            let row = users.row(&user).await?;
+            passes.push(row.id).await
+        }
+        .await;
+
+        if let Err(err) = pushed {
+            eprintln!("devices: pass push after revoke failed: {err:?}");
+        }
+    }
+
+    Ok(Json(Revoked {
+        revoked: true,
+        pass_revoked,
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/devices/enrollments",
+    tag = "devices",
+    responses(
+        (status = OK, body = Vec<EnrollmentView>),
+        (status = UNAUTHORIZED, body = AuthErrBody),
+        (status = BAD_GATEWAY, body = AuthErrBody),
+    ),
+)]
+async fn enrollments(
+    State(devices): State<Devices>,
+    Extension(users): Extension<Users>,
+    CurrentUser(user): CurrentUser,
+) -> Result<Json<Vec<EnrollmentView>>, AuthError> {
+    let row = users.row(&user).await?;
+
+    Ok(Json(devices.enrollments(row.id).await?))
+}
+
+#[derive(Deserialize, ToSchema)]
+struct Decision {
+    /// This device's signature over
+    /// `quest-device-approve:<id>:<public key>` (`quest-device-deny:` to deny).
+    signature: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/devices/enrollments/{id}/approve",
+    tag = "devices",
+    params(("id" = i64, Path, description = "Enrollment id")),
+    request_body = Decision,
+    responses(
+        (status = OK, body = EnrollmentView),
+        (status = BAD_REQUEST, body = AuthErrBody),
+        (status = UNAUTHORIZED, body = AuthErrBody),
+        (status = NOT_FOUND, body = AuthErrBody),
+        (status = CONFLICT, body = AuthErrBody),
+        (status = BAD_GATEWAY, body = AuthErrBody),
+    ),
+)]
+async fn approve_enrollment(
+    State(devices): State<Devices>,
+    Extension(users): Extension<Users>,
+    CurrentUser(user): CurrentUser,
+    CurrentDevice(bound): CurrentDevice,
+    Path(id): Path<i64>,
+    Json(body): Json<Decision>,
+) -> Result<Json<EnrollmentView>, AuthError> {
+    decide(&devices, &users, &user, &bound, id, true, &body).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/devices/enrollments/{id}/deny",
+    tag = "devices",
+    params(("id" = i64, Path, description = "Enrollment id")),
+    request_body = Decision,
+    responses(
+        (status = OK, body = EnrollmentView),
+        (status = BAD_REQUEST, body = AuthErrBody),
+        (status = UNAUTHORIZED, body = AuthErrBody),
+        (status = NOT_FOUND, body = AuthErrBody),
+        (status = CONFLICT, body = AuthErrBody),
+        (status = BAD_GATEWAY, body = AuthErrBody),
+    ),
+)]
+async fn deny_enrollment(
+    State(devices): State<Devices>,
+    Extension(users): Extension<Users>,
+    CurrentUser(user): CurrentUser,
+    CurrentDevice(bound): CurrentDevice,
+    Path(id): Path<i64>,
+    Json(body): Json<Decision>,
+) -> Result<Json<EnrollmentView>, AuthError> {
+    decide(&devices, &users, &user, &bound, id, false, &body).await
+}
+
+async fn decide(
+    devices: &Devices,
+    users: &Users,
+    user: &SessionUser,
+    bound: &str,
+    id: i64,
+    approve: bool,
+    body: &Decision,
+) -> Result<Json<EnrollmentView>, AuthError> {
+    let signature = decode(&body.signature).ok_or(AuthError::BadRequest("signature_malformed"))?;
+    let row = users.row(user).await?;
+
+    let decided = devices
+        .decide(
+            id,
+            approve,
+            Decider::Device {
+                owner: row.id,
+                public_key: bound,
+                signature: &signature,
+            },
+        )
+        .await?;
+
+    Ok(Json(decided))
 }
@@ -0,0 +1,369 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use entity::{auction, bid, items, purchases, users};
+use sea_orm::prelude::Uuid;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, ConnectionTrait, DbBackend, EntityTrait,
+    FromQueryResult, QueryFilter, QueryOrder, QuerySelect, Statement, TransactionTrait, sea_query,
+};
+
+use super::{Items, SOLD, Sold, db_down};
+use crate::auth::AuthError;
+use crate::tokens::{Scope, balances_of};
+
+/// Where an auction stands for one bidder.
+pub struct Standing {
+    pub auction: auction::Model,
+    /// The highest held bid, or the winning one once settled.
+    pub leading: Option<bid::Model>,
+    pub bids: usize,
+    /// The bidder's own held bid, if any.
+    pub mine: Option<bid::Model>,
+}
+
+impl Standing {
+    /// The least a new bid from the viewer has to be.
+    pub fn minimum(&self) -> i64 {
+        let floor = if self.auction.sealed {
+            self.auction.reserve
+        } else {
+            self.leading.as_ref().map_or(self.auction.reserve, |top| {
+                top.amount + self.auction.increment
+            })
+        };
+
+        match &self.mine {
+            Some(own) if self.auction.sealed => floor.max(own.amount + self.auction.increment),
+            _ => floor,
+        }
+    }
+}
+
+pub struct Placed {
+    pub bid: bid::Model,
+    pub scottycoins: i64,
+}
+
+pub struct Settled {
+    pub auction: auction::Model,
+    pub winner: Option<bid::Model>,
+    /// The top bid lost out because the item had sold out meanwhile.
+    pub out_of_stock: bool,
+}
+
+/// Highest amount first; an equal amount never beats the earlier bid.
+async fn held_bids<C: ConnectionTrait>(conn: &C, item: Uuid) -> Result<Vec<bid::Model>, AuthError> {
+    bid::Entity::find()
+        .filter(bid::Column::ItemId.eq(item))
+        .filter(bid::Column::ReleasedAt.is_null())
+        .order_by_desc(bid::Column::Amount)
+        .order_by_asc(bid::Column::Id)
+        .lock_exclusive()
+        .all(conn)
+        .await
+        .map_err(db_down)
+}
+
+async fn release<C: ConnectionTrait>(conn: &C, ids: Vec<i64>) -> Result<(), AuthError> {
+    if ids.is_empty() {
+        return Ok(());
+    }
+
+    bid::Entity::update_many()
+        .col_expr(
+            bid::Column::ReleasedAt,
+            sea_query::Expr::current_timestamp(),
+        )
+        .filter(bid::Column::Id.is_in(ids))
+        .exec(conn)
+        .await
+        .map_err(db_down)?;
+
+    Ok(())
+}
+
+impl Items {
+    pub async fn auctions(
+        &self,
+        items: &[Uuid],
+        viewer: Uuid,
+    ) -> Result<HashMap<Uuid, Standing>, AuthError> {
+        if items.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let auctions = auction::Entity::find()
+            .filter(auction::Column::ItemId.is_in(items.iter().copied()))
+            .all(&self.db)
+            .await
+            .map_err(db_down)?;
+
+        if auctions.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let bids = bid::Entity::find()
+            .filter(bid::Column::ItemId.is_in(auctions.iter().map(|row| row.item_id)))
+            .order_by_desc(bid::Column::Amount)
+            .order_by_asc(bid::Column::Id)
+            .all(&self.db)
+            .await
+            .map_err(db_down)?;
+
+        let mut grouped: HashMap<Uuid, Vec<bid::Model>> = HashMap::new();
+        for row in bids {
+            grouped.entry(row.item_id).or_default().push(row);
+        }
+
+        Ok(auctions
+            .into_iter()
+            .map(|auction| {
+                let bids = grouped.remove(&auction.item_id).unwrap_or_default();
+
+                let leading = match auction.winning_bid {
+                    Some(winner) => bids.iter().find(|row| row.id == winner).cloned(),
+                    None if auction.settled_at.is_some() => None,
+                    None => bids.iter().find(|row| row.released_at.is_none()).cloned(),
+                };
+                let mine = bids
+                    .iter()
+                    .find(|row| row.user_id == viewer && row.released_at.is_none())
+                    .cloned();
+
+                let standing = Standing {
+                    bids: bids.len(),
+                    auction,
+                    leading,
+                    mine,
+                };
+                (standing.auction.item_id, standing)
+            })
+            .collect())
+    }
+
+    /// Places a bid, holding `amount` in escrow until the bidder is outbid
+    /// or the auction settles. A bidder raising their own bid only needs
+    /// the difference on hand.
+    pub async fn bid(&self, user: Uuid, item: Uuid, amount: i64) -> Result<Placed, AuthError> {
+        if amount < 1 {
+            return Err(AuthError::BadRequest("bid_invalid"));
+        }
+
+        let txn = self.db.begin().await.map_err(db_down)?;
+
+        // Serialises a player's escrow across auctions running side by side.
+        // The player row comes first, as in every other spend path.
+        users::Entity::find_by_id(user)
+            .lock_exclusive()
+            .one(&txn)
+            .await
+            .map_err(db_down)?
+            .ok_or(AuthError::NotFound("user_unknown"))?;
+
+        let auction = auction::Entity::find_by_id(item)
+            .lock_exclusive()
+            .one(&txn)
+            .await
+            .map_err(db_down)?
+            .ok_or(AuthError::NotFound("auction_unknown"))?;
+
+        if auction.settled_at.is_some() {
+            return Err(AuthError::Conflict("auction_settled"));
+        }
+        if Utc::now() >= auction.ends_at {
+            return Err(AuthError::Conflict("auction_ended"));
+        }
+
+        let held = held_bids(&txn, item).await?;
+        let mine = held.iter().find(|row| row.user_id == user).cloned();
+
+        let standing = Standing {
+            leading: held.first().cloned(),
+            bids: held.len(),
+            mine: mine.clone(),
+            auction,
+        };
+
+        if amount < standing.minimum() {
+            return Err(AuthError::Conflict("bid_too_low"));
+        }
+
+        let own = mine.as_ref().map_or(0, |row| row.amount);
+        let balance = balances_of(&txn, user, Scope::Lifetime).await?;
+        if amount > balance.scottycoins + own {
+            return Err(AuthError::Conflict("insufficient_coins"));
+        }
+
+        // Live auctions only ever hold the top bid; sealed ones hold one per
+        // bidder until settlement.
+        let released: Vec<i64> = held
+            .iter()
+            .filter(|row| row.user_id == user || !standing.auction.sealed)
+            .map(|row| row.id)
+            .collect();
+
+        release(&txn, released).await?;
+
+        let saved = bid::ActiveModel {
+            item_id: ActiveValue::Set(item),
+            user_id: ActiveValue::Set(user),
+            amount: ActiveValue::Set(amount),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await
+        .map_err(db_down)?;
+
+        txn.commit().await.map_err(db_down)?;
+
+        Ok(Placed {
+            bid: saved,
+            scottycoins: balance.scottycoins + own - amount,
+        })
+    }
+
+    /// Closes an ended auction. The top bid becomes a purchase at the bid
+    /// amount and every other bid's escrow is released. When the item has
+    /// sold out in the meantime the auction closes without a winner and
+    /// every bid is released.
+    pub async fn settle(&self, item: Uuid, by: &str) -> Result<Settled, AuthError> {
+        let txn = self.db.begin().await.map_err(db_down)?;
+
+        let row = items::Entity::find_by_id(item)
+            .lock_exclusive()
+            .one(&txn)
+            .await
+            .map_err(db_down)?
+            .ok_or(AuthError::NotFound("item_unknown"))?;
+
+        let auction = auction::Entity::find_by_id(item)
+            .lock_exclusive()
+            .one(&txn)
+            .await
+            .map_err(db_down)?
+            .ok_or(AuthError::NotFound("auction_unknown"))?;
+
+        if auction.settled_at.is_some() {
+            return Err(AuthError::Conflict("auction_settled"));
+        }
+        if Utc::now() < auction.ends_at {
+            return Err(AuthError::Conflict("auction_running"));
+        }
+
+        let held = held_bids(&txn, item).await?;
+        let mut winner = held.first().cloned();
+
+        release(&txn, held.iter().map(|row| row.id).collect()).await?;
+
+        let sold = Sold::find_by_statement(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            SOLD,
+            [item.into()],
+        ))
+        .one(&txn)
+        .await
+        .map_err(db_down)?
+        .map_or(0, |count| count.sold);
+
+        let out_of_stock = winner.is_some() && row.quantity_available - sold < 1;
+        if out_of_stock {
+            winner = None;
+        }
+
+        let mut purchase = None;
+        if let Some(top) = &winner {
+            let saved = purchases::ActiveModel {
+                user_id: ActiveValue::Set(top.user_id),
+                item_id: ActiveValue::Set(item),
+                quantity: ActiveValue::Set(1),
+                unit_cost: ActiveValue::Set(top.amount),
+                ..Default::default()
+            }
+            .insert(&txn)
+            .await
+            .map_err(db_down)?;
+
+            purchase = Some(saved.purchase_id);
+        }
+
+        let settled = auction::ActiveModel {
+            item_id: ActiveValue::Unchanged(item),
+            purchase_id: ActiveValue::Set(purchase),
+            winning_bid: ActiveValue::Set(winner.as_ref().map(|top| top.id)),
+            settled_by: ActiveValue::Set(Some(by.to_owned())),
+            settled_at: ActiveValue::Set(Some(Utc::now().into())),
+            ..Default::default()
+        }
+        .update(&txn)
+        .await
+        .map_err(db_down)?;
+
+        txn.commit().await.map_err(db_down)?;
+
+        Ok(Settled {
+            auction: settled,
+            winner,
+            out_of_stock,
+        })
+    }
+}
+
+#[cfg(test)]
+mod minimum_tests {
+    use super::*;
+
+    fn auction(sealed: bool) -> auction::Model {
+        auction::Model {
+            item_id: Uuid::from_u128(1),
+            sealed,
+            reserve: 10,
+            increment: 5,
+            ends_at: Utc::now().into(),
+            purchase_id: None,
+            winning_bid: None,
+            settled_by: None,
+            settled_at: None,
+        }
+    }
+
+    fn bid(id: i64, user: u128, amount: i64) -> bid::Model {
+        bid::Model {
+            id,
+            item_id: Uuid::from_u128(1),
+            user_id: Uuid::from_u128(user),
+            amount,
+            created_at: Utc::now().into(),
+            released_at: None,
+        }
+    }
+
+    fn standing(sealed: bool, leading: Option<bid::Model>, mine: Option<bid::Model>) -> Standing {
+        Standing {
+            auction: auction(sealed),
+            bids: usize::from(leading.is_some()),
+            leading,
+            mine,
+        }
+    }
+
+    #[test]
+    fn an_empty_auction_starts_at_the_reserve() {
+        assert_eq!(standing(false, None, None).minimum(), 10);
+        assert_eq!(standing(true, None, None).minimum(), 10);
+    }
+
+    #[test]
+    fn a_live_bid_has_to_beat_the_leader_by_the_increment() {
+        assert_eq!(standing(false, Some(bid(1, 2, 40)), None).minimum(), 45);
+    }
+
+    #[test]
+    fn a_sealed_bid_ignores_others_but_must_raise_your_own() {
+        assert_eq!(standing(true, Some(bid(1, 2, 40)), None).minimum(), 10);
+        assert_eq!(
+            standing(true, Some(bid(1, 2, 40)), Some(bid(2, 3, 20))).minimum(),
+            25
+        );
+    }
+}
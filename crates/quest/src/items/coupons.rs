@@ -0,0 +1,164 @@
+use chrono::Utc;
+use entity::{coupon, coupon_item, coupon_redemption, purchases};
+use sea_orm::prelude::Uuid;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, ConnectionTrait, EntityTrait, PaginatorTrait,
+    QueryFilter, QuerySelect,
+};
+
+use super::db_down;
+use crate::auth::AuthError;
+
+/// A code accepted for one checkout. Each redemption takes the discount off
+/// a single unit.
+pub struct Claim {
+    pub coupon: coupon::Model,
+    /// Items the code covers; every item when empty.
+    items: Vec<Uuid>,
+}
+
+impl Claim {
+    pub fn covers(&self, item: Uuid) -> bool {
+        self.items.is_empty() || self.items.contains(&item)
+    }
+
+    /// What one unit listed at `unit_cost` sells for with the code.
+    pub fn price(&self, unit_cost: i64) -> i64 {
+        match (self.coupon.percent_off, self.coupon.amount_off) {
+            (Some(percent), _) => unit_cost - unit_cost.saturating_mul(i64::from(percent)) / 100,
+            (None, Some(amount)) => (unit_cost - amount).max(0),
+            (None, None) => unit_cost,
+        }
+    }
+}
+
+/// Looks up `code` for `user`, locking the coupon so concurrent checkouts
+/// cannot both take its last redemption. Refuses codes with no redemption
+/// left for the user.
+pub async fn claim<C: ConnectionTrait>(
+    conn: &C,
+    code: &str,
+    user: Uuid,
+) -> Result<Claim, AuthError> {
+    let code = code.trim().to_uppercase();
+    if code.is_empty() {
+        return Err(AuthError::BadRequest("coupon_code_invalid"));
+    }
+
+    let coupon = coupon::Entity::find_by_id(code.clone())
+        .lock_exclusive()
+        .one(conn)
+        .await
+        .map_err(db_down)?
+        .ok_or(AuthError::NotFound("coupon_unknown"))?;
+
+    if coupon.expires_at.is_some_and(|at| at <= Utc::now()) {
+        return Err(AuthError::Conflict("coupon_expired"));
+    }
+
+    let items = coupon_item::Entity::find()
+        .select_only()
+        .column(coupon_item::Column::ItemId)
+        .filter(coupon_item::Column::Code.eq(code.clone()))
+        .into_tuple::<Uuid>()
+        .all(conn)
+        .await
+        .map_err(db_down)?;
+
+    if let Some(cap) = coupon.max_redemptions {
+        let used = coupon_redemption::Entity::find()
+            .filter(coupon_redemption::Column::Code.eq(code.clone()))
+            .count(conn)
+            .await
+            .map_err(db_down)?;
+
+        if used >= u64::try_from(cap).unwrap_or(0) {
+            return Err(AuthError::Conflict("coupon_exhausted"));
+        }
+    }
+
+    if let Some(cap) = coupon.max_per_user {
+        let mine = coupon_redemption::Entity::find()
+            .filter(coupon_redemption::Column::Code.eq(code))
+            .filter(coupon_redemption::Column::UserId.eq(user))
+            .count(conn)
+            .await
+            .map_err(db_down)?;
+
+        if mine >= u64::try_from(cap).unwrap_or(0) {
+            return Err(AuthError::Conflict("coupon_already_used"));
+        }
+    }
+
+    Ok(Claim { coupon, items })
+}
+
+/// Records that the single unit on `row` was bought with the code at
+/// `list_cost`.
+pub async fn redeem<C: ConnectionTrait>(
+    conn: &C,
+    claim: &Claim,
+    row: &purchases::Model,
+    list_cost: i64,
+) -> Result<(), AuthError> {
+    coupon_redemption::ActiveModel {
+        code: ActiveValue::Set(claim.coupon.code.clone()),
+        user_id: ActiveValue::Set(row.user_id),
+        item_id: ActiveValue::Set(row.item_id),
+        purchase_id: ActiveValue::Set(Some(row.purchase_id)),
+        quantity: ActiveValue::Set(row.quantity),
+        list_cost: ActiveValue::Set(list_cost),
+        unit_cost: ActiveValue::Set(row.unit_cost),
+        ..Default::default()
+    }
+    .insert(conn)
+    .await
+    .map_err(db_down)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod claim_tests {
+    use super::*;
+
+    fn claim(percent_off: Option<i32>, amount_off: Option<i64>, items: Vec<Uuid>) -> Claim {
+        Claim {
+            coupon: coupon::Model {
+                code: "ORIENT20".to_owned(),
+                percent_off,
+                amount_off,
+                max_redemptions: None,
+                max_per_user: Some(1),
+                expires_at: None,
+                created_at: Utc::now().into(),
+            },
+            items,
+        }
+    }
+
+    #[test]
+    fn percent_off_rounds_the_discount_down() {
+        let twenty = claim(Some(20), None, Vec::new());
+        assert_eq!(twenty.price(100), 80);
+        assert_eq!(twenty.price(7), 6);
+        assert_eq!(twenty.price(0), 0);
+    }
+
+    #[test]
+    fn amount_off_never_goes_below_free() {
+        let five = claim(None, Some(5), Vec::new());
+        assert_eq!(five.price(12), 7);
+        assert_eq!(five.price(3), 0);
+    }
+
+    #[test]
+    fn a_code_covers_its_items_or_everything() {
+        let shirt = Uuid::from_u128(1);
+        let mug = Uuid::from_u128(2);
+
+        assert!(claim(Some(10), None, Vec::new()).covers(mug));
+        assert!(claim(Some(10), None, vec![shirt]).covers(shirt));
+        assert!(!claim(Some(10), None, vec![shirt]).covers(mug));
+    }
+}
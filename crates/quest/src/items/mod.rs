@@ -1,13 +1,21 @@
+pub mod auction;
+pub mod coupons;
 pub mod options;
+pub mod pickup;
+pub mod raffle;
+pub mod refunds;
 pub mod routes;
+pub mod waitlist;
 
 use std::collections::HashMap;
 
-use entity::{items, purchases};
-use sea_orm::prelude::Uuid;
+use chrono::{DateTime, Utc};
+use entity::enums::TicketState;
+use entity::{items, purchases, users};
+use sea_orm::prelude::{DateTimeWithTimeZone, Uuid};
 use sea_orm::{
-    ActiveModelTrait, ActiveValue, DatabaseConnection, DbBackend, DbErr, EntityTrait,
-    FromQueryResult, QuerySelect, Statement, TransactionTrait,
+    ActiveModelTrait, ActiveValue, ConnectionTrait, DatabaseConnection, DbBackend, DbErr,
+    EntityTrait, FromQueryResult, QuerySelect, Statement, TransactionTrait,
 };
 
 use crate::auth::AuthError;
@@ -22,6 +30,20 @@ SELECT
     "items"."image_url"      AS "image_url",
     "items"."background_url" AS "background_url",
     "items"."icon_shade"     AS "icon_shade",
+    "items"."max_per_order"  AS "max_per_order",
+    "items"."max_per_user"   AS "max_per_user",
+    "items"."window_limit"   AS "window_limit",
+    "items"."window_hours"   AS "window_hours",
+    "items"."refund_window_hours" AS "refund_window_hours",
+    "items"."available_from"  AS "available_from",
+    "items"."available_until" AS "available_until",
+    "items"."raffle"          AS "raffle",
+    (
+        SELECT COALESCE(SUM("waitlist"."quantity"), 0)
+        FROM "waitlist"
+        WHERE "waitlist"."item_id" = "items"."id"
+          AND "waitlist"."reserved_until" > now()
+    )::BIGINT AS "reserved",
     GREATEST(
         "items"."quantity_available" - COALESCE(SUM("purchases"."quantity"), 0),
         0
@@ -39,10 +61,18 @@ WHERE "item_id" = $1
 "#;
 
 const BOUGHT_BY_USER: &str = r#"
-SELECT COALESCE(SUM("quantity"), 0)::BIGINT AS "sold"
+SELECT
+    "purchases"."item_id" AS "item_id",
+    SUM("purchases"."quantity")::BIGINT AS "total",
+    COALESCE(SUM("purchases"."quantity") FILTER (
+        WHERE "purchases"."purchased_at"
+            > now() - make_interval(hours => "items"."window_hours")
+    ), 0)::BIGINT AS "recent"
 FROM "purchases"
-WHERE "item_id" = $1
-  AND "user_id" = $2
+JOIN "items" ON "items"."id" = "purchases"."item_id"
+WHERE "purchases"."user_id" = $1
+  AND ($2::uuid IS NULL OR "purchases"."item_id" = $2)
+GROUP BY "purchases"."item_id"
 "#;
 
 const LEDGER: &str = r#"
@@ -53,13 +83,25 @@ SELECT
     "purchases"."quantity"                       AS "quantity",
     "purchases"."unit_cost"                      AS "cost",
     "items"."image_url"                          AS "image_url",
-    "purchases"."received_item_date" IS NOT NULL AS "delivered"
+    "purchases"."received_item_date" IS NOT NULL AS "delivered",
+    "purchases"."ticket"                         AS "ticket",
+    "purchases"."pickup_slot_id"                 AS "pickup_slot_id",
+    "pickup_location"."name"                     AS "pickup_location",
+    "pickup_location"."directions"               AS "pickup_directions",
+    "pickup_slot"."starts_at"                    AS "pickup_starts_at",
+    "pickup_slot"."ends_at"                      AS "pickup_ends_at"
 FROM "purchases"
 JOIN "items" ON "items"."id" = "purchases"."item_id"
+LEFT JOIN "pickup_slot"
+    ON "pickup_slot"."id" = "purchases"."pickup_slot_id"
+LEFT JOIN "pickup_location"
+    ON "pickup_location"."id" = "pickup_slot"."location_id"
 WHERE "purchases"."user_id" = $1
 ORDER BY "purchases"."purchase_id" DESC
 "#;
 
+pub const MAX_CART: usize = 20;
+
 #[derive(Clone)]
 pub struct Items {
     db: DatabaseConnection,
@@ -74,9 +116,139 @@ pub struct Stocked {
     pub image_url: Option<String>,
     pub background_url: Option<String>,
     pub icon_shade: Option<String>,
+    pub max_per_order: i64,
+    pub max_per_user: Option<i64>,
+    pub window_limit: Option<i64>,
+    pub window_hours: Option<i32>,
+    pub refund_window_hours: Option<i32>,
+    pub available_from: Option<DateTimeWithTimeZone>,
+    pub available_until: Option<DateTimeWithTimeZone>,
+    pub raffle: bool,
+    /// Units held for waitlisted players, already taken off `stock`.
+    pub reserved: i64,
     pub stock: i64,
 }
 
+impl Stocked {
+    pub fn limits(&self) -> Limits {
+        Limits {
+            per_order: self.max_per_order,
+            per_user: self.max_per_user,
+            per_window: self.window_limit,
+        }
+    }
+}
+
+/// Where an item or choice sits relative to its sale window.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Sale {
+    Soon,
+    Open,
+    Over,
+}
+
+impl Sale {
+    pub fn at(
+        from: Option<DateTimeWithTimeZone>,
+        until: Option<DateTimeWithTimeZone>,
+        now: DateTime<Utc>,
+    ) -> Self {
+        if from.is_some_and(|from| now < from) {
+            Self::Soon
+        } else if until.is_some_and(|until| now >= until) {
+            Self::Over
+        } else {
+            Self::Open
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Soon => "coming_soon",
+            Self::Open => "on_sale",
+            Self::Over => "ended",
+        }
+    }
+}
+
+/// Refuses a cart that could never be bought, before anything is locked.
+fn check_cart(lines: &[Line]) -> Result<(), AuthError> {
+    if lines.is_empty() {
+        return Err(AuthError::BadRequest("cart_empty"));
+    }
+    if lines.len() > MAX_CART {
+        return Err(AuthError::BadRequest("cart_too_large"));
+    }
+    if lines.iter().any(|line| line.quantity < 1) {
+        return Err(AuthError::BadRequest("quantity_invalid"));
+    }
+    Ok(())
+}
+
+/// How much of an item one player may take.
+#[derive(Clone, Copy, Debug)]
+pub struct Limits {
+    pub per_order: i64,
+    pub per_user: Option<i64>,
+    pub per_window: Option<i64>,
+}
+
+impl Limits {
+    pub fn of(item: &items::Model) -> Self {
+        Self {
+            per_order: item.max_per_order,
+            per_user: item.max_per_user,
+            per_window: item.window_limit,
+        }
+    }
+
+    /// The most the player can buy in their next order.
+    pub fn allowance(&self, bought: Bought) -> i64 {
+        let mut left = self.per_order;
+        if let Some(cap) = self.per_user {
+            left = left.min(cap - bought.total);
+        }
+        if let Some(cap) = self.per_window {
+            left = left.min(cap - bought.recent);
+        }
+        left.max(0)
+    }
+
+    /// Names the limit an order of `quantity` would break, if any.
+    pub fn check(&self, bought: Bought, quantity: i64) -> Result<(), AuthError> {
+        if quantity > self.per_order {
+            return Err(AuthError::BadRequest("order_limit_exceeded"));
+        }
+        if self
+            .per_user
+            .is_some_and(|cap| bought.total + quantity > cap)
+        {
+            return Err(AuthError::Conflict("purchase_limit_reached"));
+        }
+        if self
+            .per_window
+            .is_some_and(|cap| bought.recent + quantity > cap)
+        {
+            return Err(AuthError::Conflict("purchase_window_limit_reached"));
+        }
+        Ok(())
+    }
+}
+
+/// A player's holdings of one item, overall and inside its limit window.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Bought {
+    pub total: i64,
+    pub recent: i64,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct Held {
+    item_id: Uuid,
+    total: i64,
+    recent: i64,
+}
+
 #[derive(Debug, FromQueryResult)]
 struct Sold {
     sold: i64,
@@ -92,6 +264,8 @@ pub struct Receipt {
     pub item: Uuid,
     pub name: String,
     pub cost: i64,
+    /// Coins a coupon took off each unit.
+    pub discount: i64,
     pub quantity: i64,
     pub spent: i64,
     pub stock: i64,
@@ -108,6 +282,36 @@ pub struct Ledger {
     pub cost: i64,
     pub image_url: Option<String>,
     pub delivered: bool,
+    pub ticket: Option<TicketState>,
+    pub pickup_slot_id: Option<Uuid>,
+    pub pickup_location: Option<String>,
+    pub pickup_directions: Option<String>,
+    pub pickup_starts_at: Option<DateTimeWithTimeZone>,
+    pub pickup_ends_at: Option<DateTimeWithTimeZone>,
+}
+
+pub struct Line {
+    pub item: Uuid,
+    pub quantity: i64,
+    pub chosen: Vec<options::Choice>,
+}
+
+pub struct Checkout {
+    pub receipts: Vec<Receipt>,
+    pub spent: i64,
+    pub scottycoins: i64,
+}
+
+struct Staged {
+    item: Uuid,
+    quantity: i64,
+    picked: Vec<options::Picked>,
+    /// Unit price before any coupon.
+    list_cost: i64,
+    unit_cost: i64,
+    spent: i64,
+    /// The single unit the coupon was spent on.
+    redeemed: bool,
 }
 
 pub struct Refunded {
@@ -115,6 +319,38 @@ pub struct Refunded {
     pub scottycoins: i64,
 }
 
+struct Part {
+    quantity: i64,
+    unit_cost: i64,
+    redeemed: bool,
+}
+
+/// Breaks a cart line into purchases: the coupon unit at `discounted`, when
+/// the code covers the line, and the rest at `list_cost`.
+fn split(quantity: i64, list_cost: i64, discounted: Option<i64>) -> Vec<Part> {
+    let Some(unit_cost) = discounted else {
+        return vec![Part {
+            quantity,
+            unit_cost: list_cost,
+            redeemed: false,
+        }];
+    };
+
+    let mut parts = vec![Part {
+        quantity: 1,
+        unit_cost,
+        redeemed: true,
+    }];
+    if quantity > 1 {
+        parts.push(Part {
+            quantity: quantity - 1,
+            unit_cost: list_cost,
+            redeemed: false,
+        });
+    }
+    parts
+}
+
 impl Items {
     pub fn new(db: DatabaseConnection) -> Self {
         Self { db }
@@ -142,11 +378,17 @@ impl Items {
             {
                 item.stock = stock;
             }
+            item.stock = (item.stock - item.reserved).max(0);
         }
 
         Ok(stocked)
     }
 
+    /// What `user` already holds of every item they have bought.
+    pub async fn bought(&self, user: Uuid) -> Result<HashMap<Uuid, Bought>, AuthError> {
+        bought_by(&self.db, user, None).await
+    }
+
     pub async fn options_of(
         &self,
         items: &[Uuid],
@@ -187,110 +429,225 @@ impl Items {
         item: Uuid,
         quantity: i64,
         chosen: &[options::Choice],
-    ) -> Result<Receipt, AuthError> {
-        if quantity < 1 {
-            return Err(AuthError::BadRequest("quantity_invalid"));
-        }
+        coupon: Option<&str>,
+    ) -> Result<Checkout, AuthError> {
+        let line = Line {
+            item,
+            quantity,
+            chosen: chosen
+                .iter()
+                .map(|pick| options::Choice {
+                    option_id: pick.option_id,
+                    value: pick.value.clone(),
+                })
+                .collect(),
+        };
+
+        self.checkout(user, vec![line], coupon).await
+    }
+
+    /// Buys every line of a cart or none of them. A coupon discounts one
+    /// unit of the first line it covers, which is bought as its own
+    /// purchase so its price stays apart from the rest of the line.
+    pub async fn checkout(
+        &self,
+        user: Uuid,
+        lines: Vec<Line>,
+        coupon: Option<&str>,
+    ) -> Result<Checkout, AuthError> {
+        check_cart(&lines)?;
 
         let txn = self.db.begin().await.map_err(db_down)?;
 
-        let row = items::Entity::find_by_id(item)
+        // The player row first, as hint unlocks and bids take it, so no two
+        // spends can read the same balance; then items in id order so two
+        // overlapping carts cannot deadlock.
+        users::Entity::find_by_id(user)
             .lock_exclusive()
             .one(&txn)
             .await
             .map_err(db_down)?
-            .ok_or(AuthError::NotFound("item_unknown"))?;
+            .ok_or(AuthError::NotFound("user_unknown"))?;
+
+        let mut ids: Vec<Uuid> = lines.iter().map(|line| line.item).collect();
+        ids.sort_unstable();
+        ids.dedup();
 
-        if quantity != 1 {
-            return Err(AuthError::BadRequest("quantity_invalid"));
+        let mut rows: HashMap<Uuid, items::Model> = HashMap::with_capacity(ids.len());
+        for id in ids {
+            let row = items::Entity::find_by_id(id)
+                .lock_exclusive()
+                .one(&txn)
+                .await
+                .map_err(db_down)?
+                .ok_or(AuthError::NotFound("item_unknown"))?;
+            rows.insert(id, row);
         }
 
-        let bought = Sold::find_by_statement(Statement::from_sql_and_values(
-            DbBackend::Postgres,
-            BOUGHT_BY_USER,
-            [item.into(), user.into()],
-        ))
-        .one(&txn)
-        .await
-        .map_err(db_down)?
-        .map_or(0, |count| count.sold);
+        let claim = match coupon {
+            Some(code) => Some(coupons::claim(&txn, code, user).await?),
+            None => None,
+        };
 
-        if bought >= 1 {
-            return Err(AuthError::Conflict("purchase_limit_reached"));
-        }
+        let now = Utc::now();
+        let mut bought = bought_by(&txn, user, None).await?;
+        let mut staged = Vec::with_capacity(lines.len());
+        let mut total = 0_i64;
+        let mut redeemed = false;
 
-        let defined = options::of_item(&txn, item).await?;
-        let picked = options::resolve(&defined, chosen)?;
+        for line in lines {
+            let row = &rows[&line.item];
 
-        let stock = if let Some(stock) = options::managed_stock(&defined) {
-            stock
-        } else {
-            let sold = Sold::find_by_statement(Statement::from_sql_and_values(
-                DbBackend::Postgres,
-                SOLD,
-                [item.into()],
-            ))
-            .one(&txn)
-            .await
-            .map_err(db_down)?
-            .map_or(0, |count| count.sold);
+            match Sale::at(row.available_from, row.available_until, now) {
+                Sale::Open => {}
+                Sale::Soon => return Err(AuthError::Conflict("item_not_yet_available")),
+                Sale::Over => return Err(AuthError::Conflict("item_sale_ended")),
+            }
 
-            (row.quantity_available - sold).max(0)
-        };
+            if row.raffle {
+                let committed = entity::raffle::Entity::find_by_id(row.id)
+                    .one(&txn)
+                    .await
+                    .map_err(db_down)?
+                    .ok_or(AuthError::Conflict("raffle_not_open"))?;
 
-        if quantity > stock {
-            return Err(AuthError::Conflict("out_of_stock"));
-        }
+                if committed.drawn_at.is_some() {
+                    return Err(AuthError::Conflict("raffle_drawn"));
+                }
+            }
+
+            let auctioned = entity::auction::Entity::find_by_id(row.id)
+                .one(&txn)
+                .await
+                .map_err(db_down)?;
+
+            if auctioned.is_some() {
+                return Err(AuthError::Conflict("item_auctioned"));
+            }
+
+            let held = bought.entry(row.id).or_default();
+            Limits::of(row).check(*held, line.quantity)?;
+            held.total += line.quantity;
+            held.recent += line.quantity;
+
+            let defined = options::of_item(&txn, row.id).await?;
+            let picked = options::resolve(&defined, &line.chosen, now)?;
+
+            let list_cost = picked.iter().find_map(|pick| pick.cost).unwrap_or(row.cost);
 
-        let unit_cost = picked.iter().find_map(|pick| pick.cost).unwrap_or(row.cost);
+            if list_cost < 0 {
+                return Err(AuthError::BadRequest("option_price_invalid"));
+            }
 
-        if unit_cost < 0 {
-            return Err(AuthError::BadRequest("option_price_invalid"));
+            // The code comes off one unit of the first line it covers.
+            let discounted = match claim.as_ref() {
+                Some(claim) if !redeemed && claim.covers(row.id) => {
+                    redeemed = true;
+                    Some(claim.price(list_cost))
+                }
+                _ => None,
+            };
+
+            for part in split(line.quantity, list_cost, discounted) {
+                let spent = part
+                    .unit_cost
+                    .checked_mul(part.quantity)
+                    .ok_or(AuthError::BadRequest("quantity_invalid"))?;
+                total = total
+                    .checked_add(spent)
+                    .ok_or(AuthError::BadRequest("quantity_invalid"))?;
+
+                staged.push(Staged {
+                    item: row.id,
+                    quantity: part.quantity,
+                    picked: picked.clone(),
+                    list_cost,
+                    unit_cost: part.unit_cost,
+                    spent,
+                    redeemed: part.redeemed,
+                });
+            }
         }
 
-        let spent = unit_cost
-            .checked_mul(quantity)
-            .ok_or(AuthError::BadRequest("quantity_invalid"))?;
+        if claim.is_some() && !redeemed {
+            return Err(AuthError::Conflict("coupon_not_applicable"));
+        }
 
         let balance = balances_of(&txn, user, Scope::Lifetime).await?;
-        if spent > balance.scottycoins {
+        if total > balance.scottycoins {
             return Err(AuthError::Conflict("insufficient_coins"));
         }
-        options::take_stock(&txn, &defined, &picked, quantity).await?;
-
-        let saved = purchases::ActiveModel {
-            user_id: ActiveValue::Set(user),
-            item_id: ActiveValue::Set(item),
-            quantity: ActiveValue::Set(quantity),
-            unit_cost: ActiveValue::Set(unit_cost),
-            ..Default::default()
-        }
-        .insert(&txn)
-        .await
-        .map_err(db_down)?;
 
-        let chosen: Vec<Chose> = picked
-            .iter()
-            .map(|pick| Chose {
-                label: pick.label.clone(),
-                value: pick.value.clone(),
-            })
-            .collect();
+        let scottycoins = balance.scottycoins - total;
+        let mut receipts = Vec::with_capacity(staged.len());
+
+        for line in staged {
+            let row = &rows[&line.item];
+
+            waitlist::promote(&txn, row).await?;
+
+            // Re-read so a second line for the same item sees the first's take.
+            let defined = options::of_item(&txn, row.id).await?;
+
+            // Stock reserved for someone further up the waitlist is not for sale.
+            let held = waitlist::held_by_others(&txn, row.id, user).await?;
+            let stock = stock_of(&txn, row, &defined).await? - waitlist::reserved(&held, None);
+
+            if line.quantity > stock {
+                return Err(AuthError::Conflict("out_of_stock"));
+            }
+
+            waitlist::check_choices(&defined, &line.picked, &held, line.quantity)?;
+            options::take_stock(&txn, &defined, &line.picked, line.quantity).await?;
+            waitlist::consume(&txn, row.id, user, &line.picked).await?;
+
+            let saved = purchases::ActiveModel {
+                user_id: ActiveValue::Set(user),
+                item_id: ActiveValue::Set(row.id),
+                quantity: ActiveValue::Set(line.quantity),
+                unit_cost: ActiveValue::Set(line.unit_cost),
+                ticket: ActiveValue::Set(row.raffle.then_some(TicketState::Entered)),
+                ..Default::default()
+            }
+            .insert(&txn)
+            .await
+            .map_err(db_down)?;
+
+            let chosen: Vec<Chose> = line
+                .picked
+                .iter()
+                .map(|pick| Chose {
+                    label: pick.label.clone(),
+                    value: pick.value.clone(),
+                })
+                .collect();
+
+            options::attach(&txn, saved.purchase_id, line.picked).await?;
+
+            if let Some(claim) = claim.as_ref().filter(|_| line.redeemed) {
+                coupons::redeem(&txn, claim, &saved, line.list_cost).await?;
+            }
 
-        options::attach(&txn, saved.purchase_id, picked).await?;
+            receipts.push(Receipt {
+                purchase_id: saved.purchase_id,
+                item: row.id,
+                name: row.name.clone(),
+                cost: line.unit_cost,
+                discount: line.list_cost - line.unit_cost,
+                quantity: line.quantity,
+                spent: line.spent,
+                stock: stock - line.quantity,
+                scottycoins,
+                chosen,
+            });
+        }
 
         txn.commit().await.map_err(db_down)?;
 
-        Ok(Receipt {
-            purchase_id: saved.purchase_id,
-            item: row.id,
-            name: row.name,
-            cost: unit_cost,
-            quantity,
-            spent,
-            stock: stock - quantity,
-            scottycoins: balance.scottycoins - spent,
-            chosen,
+        Ok(Checkout {
+            receipts,
+            spent: total,
+            scottycoins,
         })
     }
 
@@ -310,6 +667,7 @@ impl Items {
         user: Uuid,
         purchase: i64,
         quantity: i64,
+        reason: Option<&str>,
     ) -> Result<Refunded, AuthError> {
         if quantity < 1 {
             return Err(AuthError::BadRequest("refund_quantity_invalid"));
@@ -333,6 +691,11 @@ impl Items {
             return Err(AuthError::Conflict("purchase_delivered"));
         }
 
+        if row.ticket == Some(TicketState::Lost) {
+            txn.rollback().await.ok();
+            return Err(AuthError::Conflict("ticket_lost"));
+        }
+
         if quantity > row.quantity {
             txn.rollback().await.ok();
             return Err(AuthError::Conflict("refund_too_large"));
@@ -344,35 +707,43 @@ impl Items {
             .await
             .map_err(db_down)?;
 
-        let Some(_item) = item else {
+        let Some(item) = item else {
             txn.rollback().await.ok();
             return Err(AuthError::NotFound("item_unknown"));
         };
 
-        let refunded = row
-            .unit_cost
-            .checked_mul(quantity)
-            .ok_or(AuthError::BadRequest("refund_quantity_invalid"))?;
-
-        options::restore_stock(&txn, row.item_id, row.purchase_id, quantity).await?;
-
-        let remaining = row.quantity - quantity;
-        if remaining == 0 {
-            purchases::Entity::delete_by_id(row.purchase_id)
-                .exec(&txn)
-                .await
-                .map_err(db_down)?;
-        } else {
-            purchases::ActiveModel {
-                purchase_id: ActiveValue::Unchanged(row.purchase_id),
-                quantity: ActiveValue::Set(remaining),
-                ..Default::default()
-            }
-            .update(&txn)
+        let auctioned = entity::auction::Entity::find_by_id(row.item_id)
+            .one(&txn)
             .await
             .map_err(db_down)?;
+
+        if auctioned.is_some() {
+            txn.rollback().await.ok();
+            return Err(AuthError::Conflict("purchase_auctioned"));
+        }
+
+        // Orders from before purchase times were recorded have no window to
+        // be inside, so only the desk can refund them.
+        let closes = item.refund_window_hours.map(|hours| {
+            row.purchased_at
+                .map(|at| at + chrono::Duration::hours(i64::from(hours)))
+        });
+
+        if closes.is_some_and(|closes| closes.is_none_or(|closes| closes <= Utc::now())) {
+            txn.rollback().await.ok();
+            return Err(AuthError::Conflict("refund_window_closed"));
         }
 
+        let refund = refunds::Refund {
+            quantity,
+            amount: None,
+            reason: reason.unwrap_or("player_request"),
+            returned: false,
+            damaged: false,
+        };
+
+        let refunded = refunds::give_back(&txn, &row, &item, &refund, None).await?;
+
         let balance = balances_of(&txn, user, Scope::Lifetime).await?;
 
         txn.commit().await.map_err(db_down)?;
@@ -384,7 +755,185 @@ impl Items {
     }
 }
 
+/// Units of `row` still unsold, counting option-managed stock when set.
+async fn stock_of<C: ConnectionTrait>(
+    conn: &C,
+    row: &items::Model,
+    defined: &[entity::item_option::Model],
+) -> Result<i64, AuthError> {
+    if let Some(stock) = options::managed_stock(defined) {
+        return Ok(stock);
+    }
+
+    let sold = Sold::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        SOLD,
+        [row.id.into()],
+    ))
+    .one(conn)
+    .await
+    .map_err(db_down)?
+    .map_or(0, |count| count.sold);
+
+    Ok((row.quantity_available - sold).max(0))
+}
+
+async fn bought_by<C: ConnectionTrait>(
+    conn: &C,
+    user: Uuid,
+    item: Option<Uuid>,
+) -> Result<HashMap<Uuid, Bought>, AuthError> {
+    let rows = Held::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        BOUGHT_BY_USER,
+        [user.into(), item.into()],
+    ))
+    .all(conn)
+    .await
+    .map_err(db_down)?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let bought = Bought {
+                total: row.total,
+                recent: row.recent,
+            };
+            (row.item_id, bought)
+        })
+        .collect())
+}
+
 fn db_down(err: DbErr) -> AuthError {
     eprintln!("items: {err}");
     AuthError::Upstream("database_unavailable")
 }
+
+#[cfg(test)]
+mod limit_tests {
+    use super::*;
+
+    const LIMITS: Limits = Limits {
+        per_order: 3,
+        per_user: Some(5),
+        per_window: Some(2),
+    };
+
+    fn bought(total: i64, recent: i64) -> Bought {
+        Bought { total, recent }
+    }
+
+    #[test]
+    fn allowance_takes_the_tightest_limit() {
+        assert_eq!(LIMITS.allowance(bought(0, 0)), 2);
+        assert_eq!(LIMITS.allowance(bought(4, 0)), 1);
+        assert_eq!(LIMITS.allowance(bought(5, 0)), 0);
+        assert_eq!(LIMITS.allowance(bought(1, 3)), 0);
+
+        let open = Limits {
+            per_order: 3,
+            per_user: None,
+            per_window: None,
+        };
+        assert_eq!(open.allowance(bought(40, 40)), 3);
+    }
+
+    #[test]
+    fn check_names_the_limit_broken() {
+        assert!(LIMITS.check(bought(0, 0), 2).is_ok());
+        assert!(matches!(
+            LIMITS.check(bought(0, 0), 4),
+            Err(AuthError::BadRequest("order_limit_exceeded"))
+        ));
+        assert!(matches!(
+            LIMITS.check(bought(4, 0), 2),
+            Err(AuthError::Conflict("purchase_limit_reached"))
+        ));
+        assert!(matches!(
+            LIMITS.check(bought(1, 1), 2),
+            Err(AuthError::Conflict("purchase_window_limit_reached"))
+        ));
+    }
+}
+
+#[cfg(test)]
+mod cart_tests {
+    use super::*;
+
+    fn line(quantity: i64) -> Line {
+        Line {
+            item: Uuid::nil(),
+            quantity,
+            chosen: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn carts_are_checked_before_locking() {
+        assert!(check_cart(&[line(1), line(3)]).is_ok());
+        assert!(matches!(
+            check_cart(&[]),
+            Err(AuthError::BadRequest("cart_empty"))
+        ));
+        assert!(matches!(
+            check_cart(&(0..=MAX_CART).map(|_| line(1)).collect::<Vec<_>>()),
+            Err(AuthError::BadRequest("cart_too_large"))
+        ));
+        assert!(matches!(
+            check_cart(&[line(1), line(0)]),
+            Err(AuthError::BadRequest("quantity_invalid"))
+        ));
+    }
+
+    fn shape(parts: Vec<Part>) -> Vec<(i64, i64, bool)> {
+        parts
+            .into_iter()
+            .map(|part| (part.quantity, part.unit_cost, part.redeemed))
+            .collect()
+    }
+
+    #[test]
+    fn a_coupon_comes_off_one_unit_only() {
+        assert_eq!(
+            shape(split(3, 50, Some(40))),
+            [(1, 40, true), (2, 50, false)]
+        );
+        assert_eq!(shape(split(1, 50, Some(40))), [(1, 40, true)]);
+    }
+
+    #[test]
+    fn a_line_without_the_coupon_stays_whole() {
+        assert_eq!(shape(split(3, 50, None)), [(3, 50, false)]);
+    }
+}
+
+#[cfg(test)]
+mod sale_tests {
+    use super::*;
+
+    fn at(raw: &str) -> DateTimeWithTimeZone {
+        raw.parse().unwrap()
+    }
+
+    #[test]
+    fn sale_windows_open_at_from_and_close_at_until() {
+        let from = Some(at("2026-09-01T12:00:00+00:00"));
+        let until = Some(at("2026-09-02T12:00:00+00:00"));
+
+        let before = at("2026-09-01T11:59:59+00:00").to_utc();
+        let opening = at("2026-09-01T12:00:00+00:00").to_utc();
+        let closing = at("2026-09-02T12:00:00+00:00").to_utc();
+
+        assert_eq!(Sale::at(from, until, before), Sale::Soon);
+        assert_eq!(Sale::at(from, until, opening), Sale::Open);
+        assert_eq!(Sale::at(from, until, closing), Sale::Over);
+        assert_eq!(Sale::at(None, None, closing), Sale::Open);
+    }
+
+    #[test]
+    fn sale_states_have_stable_names() {
+        assert_eq!(Sale::Soon.name(), "coming_soon");
+        assert_eq!(Sale::Open.name(), "on_sale");
+        assert_eq!(Sale::Over.name(), "ended");
+    }
+}
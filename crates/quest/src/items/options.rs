@@ -1,3 +1,4 @@
+use chrono::{DateTime, FixedOffset, Utc};
 use entity::enums::OptionKind;
 use entity::{item_option, purchase_option};
 use sea_orm::prelude::Uuid;
@@ -6,6 +7,7 @@ use sea_orm::{
     QueryFilter, QueryOrder, QuerySelect, TransactionTrait,
 };
 
+use super::Sale;
 use crate::auth::AuthError;
 
 pub const MAX_PER_ITEM: usize = 8;
@@ -32,6 +34,12 @@ pub struct ChoiceDef {
 
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub stock: Option<i64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub available_from: Option<DateTime<FixedOffset>>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub available_until: Option<DateTime<FixedOffset>>,
 }
 
 pub struct Spec {
@@ -46,6 +54,7 @@ pub struct Choice {
     pub value: String,
 }
 
+#[derive(Clone)]
 pub struct Picked {
     pub option_id: Uuid,
     pub position: i32,
@@ -68,6 +77,8 @@ pub fn choice_defs_of(row: &item_option::Model) -> Vec<ChoiceDef> {
                             image_url: None,
                             background_url: None,
                             icon_shade: None,
+                            available_from: None,
+                            available_until: None,
                         });
                     }
 
@@ -222,6 +233,13 @@ pub fn vet(specs: &[Spec]) -> Result<(), AuthError> {
             if choice.stock.is_some_and(|stock| stock < 0) {
                 return Err(AuthError::BadRequest("option_stock_invalid"));
             }
+            if choice
+                .available_from
+                .zip(choice.available_until)
+                .is_some_and(|(from, until)| from >= until)
+            {
+                return Err(AuthError::BadRequest("option_window_invalid"));
+            }
             picks.push(folded);
         }
     }
@@ -292,6 +310,7 @@ pub async fn replace(
 pub fn resolve(
     defined: &[item_option::Model],
     chosen: &[Choice],
+    now: DateTime<Utc>,
 ) -> Result<Vec<Picked>, AuthError> {
     for pick in chosen {
         if !defined.iter().any(|row| row.id == pick.option_id) {
@@ -336,6 +355,12 @@ pub fn resolve(
                     .find(|choice| choice.value == given)
                     .ok_or(AuthError::BadRequest("option_answer_invalid"))?;
 
+                match Sale::at(choice.available_from, choice.available_until, now) {
+                    Sale::Open => {}
+                    Sale::Soon => return Err(AuthError::Conflict("option_not_yet_available")),
+                    Sale::Over => return Err(AuthError::Conflict("option_sale_ended")),
+                }
+
                 picked.push(Picked {
                     option_id: row.id,
                     position: row.position,
@@ -0,0 +1,210 @@
+use chrono::Utc;
+use entity::enums::TicketState;
+use entity::{item_pickup_slot, pickup_slot, purchases};
+use sea_orm::prelude::{DateTimeWithTimeZone, Uuid};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, DbBackend, EntityTrait, FromQueryResult,
+    PaginatorTrait, QueryFilter, QuerySelect, Statement, TransactionTrait,
+};
+
+use super::{Items, db_down};
+use crate::auth::AuthError;
+
+const SLOTS: &str = r#"
+SELECT s."id",
+       l."name"       AS "location",
+       l."directions",
+       s."starts_at",
+       s."ends_at",
+       s."capacity",
+       (
+           SELECT COUNT(*)
+           FROM "purchases" p
+           WHERE p."pickup_slot_id" = s."id"
+       )::BIGINT      AS "booked"
+FROM "pickup_slot" s
+JOIN "pickup_location" l ON l."id" = s."location_id"
+JOIN "item_pickup_slot" offered ON offered."slot_id" = s."id"
+WHERE offered."item_id" = $1
+  AND s."starts_at" > now()
+ORDER BY s."starts_at", l."name"
+"#;
+
+const SLOT: &str = r#"
+SELECT s."id",
+       l."name"       AS "location",
+       l."directions",
+       s."starts_at",
+       s."ends_at",
+       s."capacity",
+       (
+           SELECT COUNT(*)
+           FROM "purchases" p
+           WHERE p."pickup_slot_id" = s."id"
+       )::BIGINT      AS "booked"
+FROM "pickup_slot" s
+JOIN "pickup_location" l ON l."id" = s."location_id"
+WHERE s."id" = $1
+"#;
+
+#[derive(Debug, FromQueryResult)]
+pub struct Slot {
+    pub id: Uuid,
+    pub location: String,
+    pub directions: Option<String>,
+    pub starts_at: DateTimeWithTimeZone,
+    pub ends_at: DateTimeWithTimeZone,
+    pub capacity: Option<i32>,
+    /// Orders already booked into the slot.
+    pub booked: i64,
+}
+
+impl Slot {
+    pub fn remaining(&self) -> Option<i64> {
+        self.capacity
+            .map(|capacity| (i64::from(capacity) - self.booked).max(0))
+    }
+}
+
+/// Whether a slot of `capacity` already holds `booked` other orders.
+fn full(capacity: i32, booked: u64) -> bool {
+    booked >= capacity.max(0) as u64
+}
+
+impl Items {
+    /// Upcoming slots offered for collecting `item`.
+    pub async fn slots(&self, item: Uuid) -> Result<Vec<Slot>, AuthError> {
+        Slot::find_by_statement(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            SLOTS,
+            [item.into()],
+        ))
+        .all(&self.db)
+        .await
+        .map_err(db_down)
+    }
+
+    /// Books one of `user`'s undelivered purchases into a pickup slot, or
+    /// clears the booking when `slot` is `None`.
+    pub async fn book(
+        &self,
+        user: Uuid,
+        purchase: i64,
+        slot: Option<Uuid>,
+    ) -> Result<Option<Slot>, AuthError> {
+        let txn = self.db.begin().await.map_err(db_down)?;
+
+        let row = purchases::Entity::find_by_id(purchase)
+            .lock_exclusive()
+            .one(&txn)
+            .await
+            .map_err(db_down)?
+            .filter(|row| row.user_id == user)
+            .ok_or(AuthError::NotFound("purchase_unknown"))?;
+
+        if row.received_item_date.is_some() {
+            return Err(AuthError::Conflict("purchase_delivered"));
+        }
+
+        if matches!(row.ticket, Some(TicketState::Entered | TicketState::Lost)) {
+            return Err(AuthError::Conflict("ticket_not_won"));
+        }
+
+        if let Some(slot) = slot {
+            let found = pickup_slot::Entity::find_by_id(slot)
+                .lock_exclusive()
+                .one(&txn)
+                .await
+                .map_err(db_down)?
+                .ok_or(AuthError::NotFound("pickup_slot_unknown"))?;
+
+            let offered = item_pickup_slot::Entity::find_by_id((row.item_id, slot))
+                .one(&txn)
+                .await
+                .map_err(db_down)?;
+
+            if offered.is_none() {
+                return Err(AuthError::Conflict("pickup_slot_not_offered"));
+            }
+
+            if found.starts_at <= Utc::now() {
+                return Err(AuthError::Conflict("pickup_slot_passed"));
+            }
+
+            if let Some(capacity) = found.capacity {
+                let booked = purchases::Entity::find()
+                    .filter(purchases::Column::PickupSlotId.eq(slot))
+                    .filter(purchases::Column::PurchaseId.ne(purchase))
+                    .count(&txn)
+                    .await
+                    .map_err(db_down)?;
+
+                if full(capacity, booked) {
+                    return Err(AuthError::Conflict("pickup_slot_full"));
+                }
+            }
+        }
+
+        purchases::ActiveModel {
+            purchase_id: ActiveValue::Unchanged(purchase),
+            pickup_slot_id: ActiveValue::Set(slot),
+            ..Default::default()
+        }
+        .update(&txn)
+        .await
+        .map_err(db_down)?;
+
+        let booked = match slot {
+            Some(slot) => Slot::find_by_statement(Statement::from_sql_and_values(
+                DbBackend::Postgres,
+                SLOT,
+                [slot.into()],
+            ))
+            .one(&txn)
+            .await
+            .map_err(db_down)?,
+            None => None,
+        };
+
+        txn.commit().await.map_err(db_down)?;
+
+        Ok(booked)
+    }
+}
+
+#[cfg(test)]
+mod slot_tests {
+    use super::*;
+
+    fn slot(capacity: Option<i32>, booked: i64) -> Slot {
+        Slot {
+            id: Uuid::from_u128(1),
+            location: "Tartan desk".to_owned(),
+            directions: None,
+            starts_at: Utc::now().into(),
+            ends_at: Utc::now().into(),
+            capacity,
+            booked,
+        }
+    }
+
+    #[test]
+    fn remaining_counts_down_and_stops_at_zero() {
+        assert_eq!(slot(Some(5), 2).remaining(), Some(3));
+        assert_eq!(slot(Some(5), 5).remaining(), Some(0));
+        assert_eq!(slot(Some(2), 4).remaining(), Some(0));
+    }
+
+    #[test]
+    fn an_uncapped_slot_has_no_remaining_count() {
+        assert_eq!(slot(None, 40).remaining(), None);
+    }
+
+    #[test]
+    fn full_once_bookings_reach_capacity() {
+        assert!(!full(3, 2));
+        assert!(full(3, 3));
+        assert!(full(0, 0));
+        assert!(full(-1, 0));
+    }
+}
@@ -0,0 +1,342 @@
+use std::collections::HashMap;
+
+use entity::enums::TicketState;
+use entity::{items, purchase_option, purchases, raffle};
+use sea_orm::prelude::Uuid;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, EntityTrait, QueryFilter, QueryOrder, QuerySelect,
+    TransactionTrait,
+};
+use sha2::{Digest, Sha256};
+
+use super::{Items, db_down, options, refunds, waitlist};
+use crate::auth::AuthError;
+
+pub struct Winner {
+    pub purchase_id: i64,
+    pub user_id: Uuid,
+    /// Position in the ticket list the draw ran over.
+    pub ticket: usize,
+}
+
+pub struct Drawn {
+    pub item: Uuid,
+    pub commitment: String,
+    pub seed: String,
+    pub tickets: usize,
+    pub winners: Vec<Winner>,
+    pub refunded: i64,
+}
+
+/// Picks `winners` distinct positions out of `tickets` with a partial
+/// Fisher-Yates shuffle driven by SHA-256(seed || round). Anyone holding the
+/// revealed seed and the ticket list can rerun it.
+pub fn pick(seed: &[u8], tickets: usize, winners: usize) -> Vec<usize> {
+    let mut pool: Vec<usize> = (0..tickets).collect();
+    let rounds = winners.min(tickets);
+
+    for round in 0..rounds {
+        let digest = Sha256::new()
+            .chain_update(seed)
+            .chain_update((round as u64).to_be_bytes())
+            .finalize();
+        let roll = u64::from_be_bytes(digest[..8].try_into().unwrap_or_default());
+        let span = (tickets - round) as u64;
+        pool.swap(round, round + (roll % span) as usize);
+    }
+
+    pool.truncate(rounds);
+    pool
+}
+
+/// Whether `seed` is the one `commitment` was made to.
+pub fn reveals(seed: &str, commitment: &str) -> bool {
+    hex::encode(Sha256::digest(seed.as_bytes())) == commitment
+}
+
+impl Items {
+    pub async fn raffles(&self, items: &[Uuid]) -> Result<HashMap<Uuid, raffle::Model>, AuthError> {
+        if items.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let rows = raffle::Entity::find()
+            .filter(raffle::Column::ItemId.is_in(items.iter().copied()))
+            .all(&self.db)
+            .await
+            .map_err(db_down)?;
+
+        Ok(rows.into_iter().map(|row| (row.item_id, row)).collect())
+    }
+
+    /// Opens ticket sales against the SHA-256 `commitment` of a seed the
+    /// operator keeps to themselves until [`Items::draw`].
+    pub async fn commit(
+        &self,
+        item: Uuid,
+        commitment: &str,
+        by: &str,
+    ) -> Result<raffle::Model, AuthError> {
+        let commitment = commitment.trim().to_ascii_lowercase();
+        if commitment.len() != 64 || hex::decode(&commitment).is_err() {
+            return Err(AuthError::BadRequest("raffle_commitment_invalid"));
+        }
+
+        let txn = self.db.begin().await.map_err(db_down)?;
+
+        let row = items::Entity::find_by_id(item)
+            .lock_exclusive()
+            .one(&txn)
+            .await
+            .map_err(db_down)?
+            .ok_or(AuthError::NotFound("item_unknown"))?;
+
+        if !row.raffle {
+            return Err(AuthError::Conflict("item_not_a_raffle"));
+        }
+
+        let known = raffle::Entity::find_by_id(item)
+            .one(&txn)
+            .await
+            .map_err(db_down)?;
+
+        if known.is_some() {
+            return Err(AuthError::Conflict("raffle_committed"));
+        }
+
+        let saved = raffle::ActiveModel {
+            item_id: ActiveValue::Set(item),
+            commitment: ActiveValue::Set(commitment),
+            committed_by: ActiveValue::Set(by.to_owned()),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await
+        .map_err(db_down)?;
+
+        txn.commit().await.map_err(db_down)?;
+        Ok(saved)
+    }
+
+    /// Reveals the seed and draws. Winning tickets become ordinary
+    /// deliverable purchases; a purchase holding both winning and losing
+    /// tickets is split in two. Losing tickets are refunded on request, and
+    /// the waitlist is dropped since nobody can buy a drawn raffle.
+    pub async fn draw(
+        &self,
+        item: Uuid,
+        seed: &str,
+        winners: i32,
+        refund_losers: bool,
+        by: &str,
+    ) -> Result<Drawn, AuthError> {
+        if winners < 1 {
+            return Err(AuthError::BadRequest("raffle_winners_invalid"));
+        }
+
+        let txn = self.db.begin().await.map_err(db_down)?;
+
+        items::Entity::find_by_id(item)
+            .lock_exclusive()
+            .one(&txn)
+            .await
+            .map_err(db_down)?
+            .ok_or(AuthError::NotFound("item_unknown"))?;
+
+        let committed = raffle::Entity::find_by_id(item)
+            .lock_exclusive()
+            .one(&txn)
+            .await
+            .map_err(db_down)?
+            .ok_or(AuthError::Conflict("raffle_uncommitted"))?;
+
+        if committed.drawn_at.is_some() {
+            return Err(AuthError::Conflict("raffle_drawn"));
+        }
+
+        if !reveals(seed, &committed.commitment) {
+            return Err(AuthError::BadRequest("raffle_seed_mismatch"));
+        }
+
+        let entered = purchases::Entity::find()
+            .filter(purchases::Column::ItemId.eq(item))
+            .filter(purchases::Column::Ticket.eq(TicketState::Entered))
+            .order_by_asc(purchases::Column::PurchaseId)
+            .lock_exclusive()
+            .all(&txn)
+            .await
+            .map_err(db_down)?;
+
+        // One slot per ticket, in purchase order, so the list is reproducible.
+        let tickets: Vec<usize> = entered
+            .iter()
+            .enumerate()
+            .flat_map(|(index, row)| std::iter::repeat_n(index, row.quantity.max(0) as usize))
+            .collect();
+
+        let chosen = pick(seed.as_bytes(), tickets.len(), winners as usize);
+
+        let mut wins: HashMap<usize, i64> = HashMap::new();
+        let mut drawn = Vec::with_capacity(chosen.len());
+        for slot in chosen {
+            let index = tickets[slot];
+            *wins.entry(index).or_default() += 1;
+            drawn.push(Winner {
+                purchase_id: entered[index].purchase_id,
+                user_id: entered[index].user_id,
+                ticket: slot,
+            });
+        }
+
+        let mut refunded = 0_i64;
+        for (index, row) in entered.iter().enumerate() {
+            let won = wins.get(&index).copied().unwrap_or(0);
+            let lost = row.quantity - won;
+
+            if won > 0 {
+                purchases::ActiveModel {
+                    purchase_id: ActiveValue::Unchanged(row.purchase_id),
+                    quantity: ActiveValue::Set(won),
+                    ticket: ActiveValue::Set(Some(TicketState::Won)),
+                    ..Default::default()
+                }
+                .update(&txn)
+                .await
+                .map_err(db_down)?;
+            }
+
+            if lost == 0 {
+                continue;
+            }
+
+            if refund_losers {
+                options::restore_stock(&txn, item, row.purchase_id, lost).await?;
+                refunded += row.unit_cost * lost;
+
+                let refund = refunds::Refund {
+                    quantity: lost,
+                    amount: None,
+                    reason: "raffle_lost",
+                    returned: false,
+                    damaged: false,
+                };
+                refunds::record(&txn, row, &refund, row.unit_cost * lost, Some(by)).await?;
+
+                if won == 0 {
+                    purchases::Entity::delete_by_id(row.purchase_id)
+                        .exec(&txn)
+                        .await
+                        .map_err(db_down)?;
+                }
+                continue;
+            }
+
+            if won == 0 {
+                purchases::ActiveModel {
+                    purchase_id: ActiveValue::Unchanged(row.purchase_id),
+                    ticket: ActiveValue::Set(Some(TicketState::Lost)),
+                    ..Default::default()
+                }
+                .update(&txn)
+                .await
+                .map_err(db_down)?;
+                continue;
+            }
+
+            let split = purchases::ActiveModel {
+                user_id: ActiveValue::Set(row.user_id),
+                item_id: ActiveValue::Set(item),
+                quantity: ActiveValue::Set(lost),
+                unit_cost: ActiveValue::Set(row.unit_cost),
+                purchased_at: ActiveValue::Set(row.purchased_at),
+                ticket: ActiveValue::Set(Some(TicketState::Lost)),
+                ..Default::default()
+            }
+            .insert(&txn)
+            .await
+            .map_err(db_down)?;
+
+            let picks = options::of_purchases(&txn, &[row.purchase_id]).await?;
+            if !picks.is_empty() {
+                purchase_option::Entity::insert_many(picks.into_iter().map(|pick| {
+                    purchase_option::ActiveModel {
+                        purchase_id: ActiveValue::Set(split.purchase_id),
+                        position: ActiveValue::Set(pick.position),
+                        label: ActiveValue::Set(pick.label),
+                        value: ActiveValue::Set(pick.value),
+                    }
+                }))
+                .exec(&txn)
+                .await
+                .map_err(db_down)?;
+            }
+        }
+
+        // Checkout refuses a drawn raffle, so restocked tickets go to no one.
+        waitlist::clear(&txn, item).await?;
+
+        raffle::ActiveModel {
+            item_id: ActiveValue::Unchanged(item),
+            seed: ActiveValue::Set(Some(seed.to_owned())),
+            winners: ActiveValue::Set(Some(winners)),
+            drawn_by: ActiveValue::Set(Some(by.to_owned())),
+            drawn_at: ActiveValue::Set(Some(chrono::Utc::now().into())),
+            ..Default::default()
+        }
+        .update(&txn)
+        .await
+        .map_err(db_down)?;
+
+        txn.commit().await.map_err(db_down)?;
+
+        Ok(Drawn {
+            item,
+            commitment: committed.commitment,
+            seed: seed.to_owned(),
+            tickets: tickets.len(),
+            winners: drawn,
+            refunded,
+        })
+    }
+}
+
+#[cfg(test)]
+mod draw_tests {
+    use super::*;
+
+    const SEED: &[u8] = b"carnival-2026-raffle";
+
+    #[test]
+    fn a_seed_always_draws_the_same_winners() {
+        let first = pick(SEED, 1000, 3);
+
+        assert_eq!(first.len(), 3);
+        assert_eq!(first, pick(SEED, 1000, 3));
+        assert_ne!(first, pick(b"another seed", 1000, 3));
+
+        let mut distinct = first.clone();
+        distinct.sort_unstable();
+        distinct.dedup();
+        assert_eq!(distinct.len(), 3);
+        assert!(first.iter().all(|&slot| slot < 1000));
+    }
+
+    #[test]
+    fn more_winners_than_tickets_picks_every_ticket() {
+        let mut all = pick(SEED, 5, 8);
+        all.sort_unstable();
+        assert_eq!(all, vec![0, 1, 2, 3, 4]);
+
+        assert!(pick(SEED, 0, 3).is_empty());
+    }
+
+    #[test]
+    fn only_the_committed_seed_reveals() {
+        let seed = "carnival-2026-raffle";
+        let commitment = hex::encode(Sha256::digest(seed.as_bytes()));
+
+        assert!(reveals(seed, &commitment));
+        assert!(!reveals("carnival-2026-rafflf", &commitment));
+        assert!(!reveals(seed, &commitment.to_ascii_uppercase()));
+    }
+}
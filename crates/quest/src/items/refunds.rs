@@ -0,0 +1,266 @@
+use entity::{items, purchases, refund};
+use sea_orm::prelude::Uuid;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ConnectionTrait, EntityTrait, QuerySelect, TransactionTrait,
+};
+
+use super::{Items, Refunded, db_down, options, waitlist};
+use crate::auth::AuthError;
+use crate::tokens::{Scope, balances_of};
+
+const REASON_MAX: usize = 500;
+
+pub struct Refund<'a> {
+    pub quantity: i64,
+    /// Coins to give back; the full price of the units when absent.
+    pub amount: Option<i64>,
+    pub reason: &'a str,
+    /// The item came back to the desk.
+    pub returned: bool,
+    pub damaged: bool,
+}
+
+/// Writes the audit row for units already taken off `row`.
+pub async fn record<C: ConnectionTrait>(
+    conn: &C,
+    row: &purchases::Model,
+    refund: &Refund<'_>,
+    amount: i64,
+    by: Option<&str>,
+) -> Result<(), AuthError> {
+    refund::ActiveModel {
+        purchase_id: ActiveValue::Set(row.purchase_id),
+        item_id: ActiveValue::Set(row.item_id),
+        user_id: ActiveValue::Set(row.user_id),
+        quantity: ActiveValue::Set(refund.quantity),
+        unit_cost: ActiveValue::Set(row.unit_cost),
+        amount: ActiveValue::Set(amount),
+        reason: ActiveValue::Set(refund.reason.trim().to_owned()),
+        delivered: ActiveValue::Set(row.received_item_date.is_some()),
+        returned: ActiveValue::Set(refund.returned),
+        damaged: ActiveValue::Set(refund.damaged),
+        refunded_by: ActiveValue::Set(by.map(str::to_owned)),
+        ..Default::default()
+    }
+    .insert(conn)
+    .await
+    .map_err(db_down)?;
+
+    Ok(())
+}
+
+/// Validates `refund` against `row` and works out the coins it gives back.
+fn priced(row: &purchases::Model, refund: &Refund<'_>) -> Result<i64, AuthError> {
+    if refund.quantity < 1 {
+        return Err(AuthError::BadRequest("refund_quantity_invalid"));
+    }
+    if refund.quantity > row.quantity {
+        return Err(AuthError::Conflict("refund_too_large"));
+    }
+
+    let reason = refund.reason.trim();
+    if reason.is_empty() {
+        return Err(AuthError::BadRequest("refund_reason_missing"));
+    }
+    if reason.chars().count() > REASON_MAX {
+        return Err(AuthError::BadRequest("refund_reason_too_long"));
+    }
+
+    let full = row
+        .unit_cost
+        .checked_mul(refund.quantity)
+        .ok_or(AuthError::BadRequest("refund_quantity_invalid"))?;
+    let amount = refund.amount.unwrap_or(full);
+    if !(0..=full).contains(&amount) {
+        return Err(AuthError::BadRequest("refund_amount_invalid"));
+    }
+
+    Ok(amount)
+}
+
+/// Whether the refunded units can go back on sale.
+fn restocks(row: &purchases::Model, refund: &Refund<'_>) -> bool {
+    row.received_item_date.is_none() || (refund.returned && !refund.damaged)
+}
+
+/// Takes units off a purchase and audits it. Units go back on sale unless
+/// they left the desk and did not come back in one piece.
+pub async fn give_back<C: ConnectionTrait>(
+    conn: &C,
+    row: &purchases::Model,
+    item: &items::Model,
+    refund: &Refund<'_>,
+    by: Option<&str>,
+) -> Result<i64, AuthError> {
+    let amount = priced(row, refund)?;
+    let restock = restocks(row, refund);
+
+    if restock {
+        options::restore_stock(conn, row.item_id, row.purchase_id, refund.quantity).await?;
+    } else {
+        // Dropping the purchase would otherwise put the units back on sale.
+        items::ActiveModel {
+            id: ActiveValue::Unchanged(item.id),
+            quantity_available: ActiveValue::Set(item.quantity_available - refund.quantity),
+            ..Default::default()
+        }
+        .update(conn)
+        .await
+        .map_err(db_down)?;
+    }
+
+    let remaining = row.quantity - refund.quantity;
+    if remaining == 0 {
+        purchases::Entity::delete_by_id(row.purchase_id)
+            .exec(conn)
+            .await
+            .map_err(db_down)?;
+    } else {
+        purchases::ActiveModel {
+            purchase_id: ActiveValue::Unchanged(row.purchase_id),
+            quantity: ActiveValue::Set(remaining),
+            ..Default::default()
+        }
+        .update(conn)
+        .await
+        .map_err(db_down)?;
+    }
+
+    record(conn, row, refund, amount, by).await?;
+
+    if restock {
+        waitlist::promote(conn, item).await?;
+    }
+
+    Ok(amount)
+}
+
+impl Items {
+    /// A refund made at the desk: delivered purchases included, at any
+    /// amount up to what was paid.
+    pub async fn refund_as(
+        &self,
+        user: Uuid,
+        purchase: i64,
+        refund: Refund<'_>,
+        by: &str,
+    ) -> Result<Refunded, AuthError> {
+        let txn = self.db.begin().await.map_err(db_down)?;
+
+        let row = purchases::Entity::find_by_id(purchase)
+            .lock_exclusive()
+            .one(&txn)
+            .await
+            .map_err(db_down)?
+            .filter(|row| row.user_id == user)
+            .ok_or(AuthError::NotFound("purchase_unknown"))?;
+
+        let item = items::Entity::find_by_id(row.item_id)
+            .lock_exclusive()
+            .one(&txn)
+            .await
+            .map_err(db_down)?
+            .ok_or(AuthError::NotFound("item_unknown"))?;
+
+        let refunded = give_back(&txn, &row, &item, &refund, Some(by)).await?;
+        let balance = balances_of(&txn, user, Scope::Lifetime).await?;
+
+        txn.commit().await.map_err(db_down)?;
+
+        Ok(Refunded {
+            refunded,
+            scottycoins: balance.scottycoins,
+        })
+    }
+}
+
+#[cfg(test)]
+mod refund_tests {
+    use chrono::Utc;
+
+    use super::*;
+
+    fn bought(quantity: i64, delivered: bool) -> purchases::Model {
+        purchases::Model {
+            purchase_id: 1,
+            user_id: Uuid::from_u128(1),
+            item_id: Uuid::from_u128(2),
+            quantity,
+            unit_cost: 30,
+            received_item_date: delivered.then(|| Utc::now().date_naive()),
+            purchased_at: None,
+            ticket: None,
+            pickup_slot_id: None,
+            delivered_by: None,
+            delivered_at: None,
+        }
+    }
+
+    fn refund<'a>(quantity: i64, amount: Option<i64>) -> Refund<'a> {
+        Refund {
+            quantity,
+            amount,
+            reason: "wrong size",
+            returned: false,
+            damaged: false,
+        }
+    }
+
+    #[test]
+    fn a_refund_defaults_to_the_price_paid() {
+        assert_eq!(priced(&bought(3, false), &refund(2, None)).unwrap(), 60);
+        assert_eq!(priced(&bought(3, false), &refund(2, Some(15))).unwrap(), 15);
+        assert_eq!(priced(&bought(3, false), &refund(1, Some(0))).unwrap(), 0);
+    }
+
+    #[test]
+    fn a_refund_cannot_exceed_what_was_bought_or_paid() {
+        assert!(matches!(
+            priced(&bought(3, false), &refund(4, None)),
+            Err(AuthError::Conflict("refund_too_large"))
+        ));
+        assert!(matches!(
+            priced(&bought(3, false), &refund(0, None)),
+            Err(AuthError::BadRequest("refund_quantity_invalid"))
+        ));
+        assert!(matches!(
+            priced(&bought(3, false), &refund(1, Some(31))),
+            Err(AuthError::BadRequest("refund_amount_invalid"))
+        ));
+        assert!(matches!(
+            priced(&bought(3, false), &refund(1, Some(-1))),
+            Err(AuthError::BadRequest("refund_amount_invalid"))
+        ));
+    }
+
+    #[test]
+    fn a_refund_needs_a_short_reason() {
+        let mut blank = refund(1, None);
+        blank.reason = "   ";
+        assert!(matches!(
+            priced(&bought(1, false), &blank),
+            Err(AuthError::BadRequest("refund_reason_missing"))
+        ));
+
+        let long = "x".repeat(REASON_MAX + 1);
+        let mut rambling = refund(1, None);
+        rambling.reason = &long;
+        assert!(matches!(
+            priced(&bought(1, false), &rambling),
+            Err(AuthError::BadRequest("refund_reason_too_long"))
+        ));
+    }
+
+    #[test]
+    fn only_undelivered_or_intact_returns_go_back_on_sale() {
+        assert!(restocks(&bought(1, false), &refund(1, None)));
+        assert!(!restocks(&bought(1, true), &refund(1, None)));
+
+        let mut back = refund(1, None);
+        back.returned = true;
+        assert!(restocks(&bought(1, true), &back));
+
+        back.damaged = true;
+        assert!(!restocks(&bought(1, true), &back));
+    }
+}
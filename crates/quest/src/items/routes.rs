@@ -1,14 +1,21 @@
+use std::collections::HashMap;
+
 use axum::extract::rejection::JsonRejection;
 use axum::extract::{Path, State};
 use axum::{Extension, Json};
+use chrono::Utc;
+use sea_orm::ActiveEnum;
 use sea_orm::prelude::Uuid;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 use utoipa_axum::router::OpenApiRouter;
 use utoipa_axum::routes;
 
+use super::auction::{Placed, Standing};
 use super::options::{self, Choice};
-use super::{Items, Ledger, Receipt, Stocked};
+use super::pickup::Slot;
+use super::waitlist::Spot;
+use super::{Bought, Checkout, Items, Ledger, Line, Receipt, Sale, Stocked};
 use crate::auth::extract::CurrentUser;
 use crate::auth::{AuthErrBody, AuthError};
 use crate::users::Users;
@@ -17,8 +24,14 @@ pub fn router(items: Items) -> OpenApiRouter {
     OpenApiRouter::new()
         .routes(routes!(list))
         .routes(routes!(buy))
+        .routes(routes!(checkout))
+        .routes(routes!(bid))
+        .routes(routes!(join_waitlist, leave_waitlist))
+        .routes(routes!(queues))
         .routes(routes!(mine))
         .routes(routes!(give_back))
+        .routes(routes!(pickup_slots))
+        .routes(routes!(book_pickup))
         .with_state(items)
 }
 
@@ -43,6 +56,63 @@ impl From<entity::item_option::Model> for OptionView {
     }
 }
 
+/// The published commitment, and the seed once the draw has happened.
+#[derive(Serialize, ToSchema)]
+pub struct RaffleView {
+    commitment: String,
+    seed: Option<String>,
+    winners: Option<i32>,
+    drawn_at: Option<String>,
+}
+
+impl From<entity::raffle::Model> for RaffleView {
+    fn from(row: entity::raffle::Model) -> Self {
+        Self {
+            commitment: row.commitment,
+            seed: row.seed,
+            winners: row.winners,
+            drawn_at: row.drawn_at.map(|at| at.to_rfc3339()),
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct AuctionView {
+    reserve: i64,
+    increment: i64,
+    /// Sealed auctions hide the leading amount until settlement.
+    sealed: bool,
+    ends_at: String,
+    bids: usize,
+    leading: Option<i64>,
+    /// The least the caller can bid next.
+    minimum: i64,
+    /// The caller's bid currently held in escrow.
+    my_bid: Option<i64>,
+    settled_at: Option<String>,
+}
+
+impl From<Standing> for AuctionView {
+    fn from(standing: Standing) -> Self {
+        let minimum = standing.minimum();
+        let settled = standing.auction.settled_at.is_some();
+        Self {
+            reserve: standing.auction.reserve,
+            increment: standing.auction.increment,
+            sealed: standing.auction.sealed,
+            ends_at: standing.auction.ends_at.to_rfc3339(),
+            bids: standing.bids,
+            leading: standing
+                .leading
+                .filter(|_| settled || !standing.auction.sealed)
+                .map(|top| top.amount),
+            minimum,
+            my_bid: standing.mine.map(|own| own.amount),
+            settled_at: standing.auction.settled_at.map(|at| at.to_rfc3339()),
+        }
+    }
+}
+
 #[derive(Serialize, ToSchema)]
 pub struct ItemView {
     id: String,
@@ -53,11 +123,41 @@ pub struct ItemView {
     background_url: Option<String>,
     icon_shade: Option<String>,
     stock: i64,
+    max_per_order: i64,
+    max_per_user: Option<i64>,
+    window_limit: Option<i64>,
+    window_hours: Option<i32>,
+    /// Hours after purchase a player may still refund it themselves;
+    /// unlimited when absent.
+    refund_window_hours: Option<i32>,
+    /// How many the caller can still buy in one order.
+    allowance: i64,
+    /// `coming_soon`, `on_sale` or `ended`.
+    sale: String,
+    /// When a `coming_soon` item opens, for the countdown.
+    available_from: Option<String>,
+    available_until: Option<String>,
+    /// Purchases of a raffle item are tickets.
+    is_raffle: bool,
+    raffle: Option<RaffleView>,
+    /// Auctioned items are bid on rather than bought.
+    auction: Option<AuctionView>,
+    /// Until when stock is held for the caller off the waitlist.
+    reserved_until: Option<String>,
     options: Vec<OptionView>,
 }
 
 impl ItemView {
-    fn build(row: Stocked, options: Vec<OptionView>) -> Self {
+    fn build(
+        row: Stocked,
+        bought: Bought,
+        raffle: Option<RaffleView>,
+        auction: Option<AuctionView>,
+        reserved_until: Option<String>,
+        options: Vec<OptionView>,
+    ) -> Self {
+        let allowance = row.limits().allowance(bought);
+        let sale = Sale::at(row.available_from, row.available_until, Utc::now());
         Self {
             id: row.id.to_string(),
             name: row.name,
@@ -67,6 +167,19 @@ impl ItemView {
             background_url: row.background_url,
             icon_shade: row.icon_shade,
             stock: row.stock,
+            max_per_order: row.max_per_order,
+            max_per_user: row.max_per_user,
+            window_limit: row.window_limit,
+            window_hours: row.window_hours,
+            refund_window_hours: row.refund_window_hours,
+            allowance,
+            sale: sale.name().to_owned(),
+            available_from: row.available_from.map(|at| at.to_rfc3339()),
+            available_until: row.available_until.map(|at| at.to_rfc3339()),
+            is_raffle: row.raffle,
+            raffle,
+            auction,
+            reserved_until,
             options,
         }
     }
@@ -91,17 +204,39 @@ struct Shelf {
 )]
 async fn list(
     State(items): State<Items>,
-    CurrentUser(_user): CurrentUser,
+    Extension(users): Extension<Users>,
+    CurrentUser(user): CurrentUser,
 ) -> Result<Json<Shelf>, AuthError> {
+    let holder = users.row(&user).await?;
+    let mut bought = items.bought(holder.id).await?;
     let rows = items.list().await?;
     let ids: Vec<Uuid> = rows.iter().map(|row| row.id).collect();
     let mut defined = items.options_of(&ids).await?;
+    let mut raffles = items.raffles(&ids).await?;
+    let mut auctions = items.auctions(&ids, holder.id).await?;
+
+    let mut reservations: HashMap<Uuid, String> = HashMap::new();
+    for spot in items.waiting(holder.id).await? {
+        if let Some(until) = spot.entry.reserved_until {
+            reservations.insert(spot.entry.item_id, until.to_rfc3339());
+        }
+    }
 
     let views: Vec<ItemView> = rows
         .into_iter()
         .map(|row| {
             let mine = defined.remove(&row.id).unwrap_or_default();
-            ItemView::build(row, mine.into_iter().map(OptionView::from).collect())
+            let held = bought.remove(&row.id).unwrap_or_default();
+            let raffle = raffles.remove(&row.id).map(RaffleView::from);
+            let auction = auctions.remove(&row.id).map(AuctionView::from);
+            ItemView::build(
+                row,
+                held,
+                raffle,
+                auction,
+                reservations.remove(&row.id),
+                mine.into_iter().map(OptionView::from).collect(),
+            )
         })
         .collect();
 
@@ -123,6 +258,9 @@ struct BuyBody {
     quantity: i64,
     #[serde(default)]
     options: Vec<PickBody>,
+    /// Coupon code to apply, case-insensitive.
+    #[serde(default)]
+    coupon: Option<String>,
 }
 
 fn one() -> i64 {
@@ -141,6 +279,8 @@ struct Purchased {
     item_id: String,
     name: String,
     cost: i64,
+    /// Coins a coupon took off each unit.
+    discount: i64,
     quantity: i64,
     spent: i64,
     stock: i64,
@@ -155,6 +295,7 @@ impl From<Receipt> for Purchased {
             item_id: receipt.item.to_string(),
             name: receipt.name,
             cost: receipt.cost,
+            discount: receipt.discount,
             quantity: receipt.quantity,
             spent: receipt.spent,
             stock: receipt.stock,
@@ -178,7 +319,7 @@ impl From<Receipt> for Purchased {
     params(("id" = String, Path, description = "Item id")),
     request_body = BuyBody,
     responses(
-        (status = OK, body = Purchased),
+        (status = OK, body = CheckedOut),
         (status = BAD_REQUEST, body = AuthErrBody),
         (status = NOT_FOUND, body = AuthErrBody),
         (status = CONFLICT, body = AuthErrBody),
@@ -192,14 +333,30 @@ async fn buy(
     CurrentUser(user): CurrentUser,
     Path(id): Path<String>,
     body: Result<Json<BuyBody>, JsonRejection>,
-) -> Result<Json<Purchased>, AuthError> {
+) -> Result<Json<CheckedOut>, AuthError> {
     let id = Uuid::parse_str(&id).map_err(|_| AuthError::BadRequest("item_id_invalid"))?;
-    let (quantity, picks) = match body {
-        Ok(Json(body)) => (body.quantity, body.options),
-        Err(JsonRejection::MissingJsonContentType(_)) => (1, Vec::new()),
+    let (quantity, picks, coupon) = match body {
+        Ok(Json(body)) => (body.quantity, body.options, body.coupon),
+        Err(JsonRejection::MissingJsonContentType(_)) => (1, Vec::new(), None),
         Err(_) => return Err(AuthError::BadRequest("purchase_body_invalid")),
     };
 
+    let chosen = choices(picks)?;
+
+    let row = users.row(&user).await?;
+
+    if !row.player {
+        return Err(AuthError::Forbidden("not_a_player"));
+    }
+
+    let done = items
+        .purchase(row.id, id, quantity, &chosen, coupon.as_deref())
+        .await?;
+
+    Ok(Json(done.into()))
+}
+
+fn choices(picks: Vec<PickBody>) -> Result<Vec<Choice>, AuthError> {
     let mut chosen = Vec::with_capacity(picks.len());
     for pick in picks {
         chosen.push(Choice {
@@ -208,6 +365,140 @@ async fn buy(
             value: pick.value,
         });
     }
+    Ok(chosen)
+}
+
+#[derive(Deserialize, ToSchema)]
+struct CartLine {
+    item_id: String,
+    #[serde(default = "one")]
+    quantity: i64,
+    #[serde(default)]
+    options: Vec<PickBody>,
+}
+
+#[derive(Deserialize, ToSchema)]
+struct CartBody {
+    lines: Vec<CartLine>,
+    /// Coupon code, taken off one unit of the first line it covers.
+    #[serde(default)]
+    coupon: Option<String>,
+}
+
+/// One purchase per line, plus one for the unit a coupon came off.
+#[derive(Serialize, ToSchema)]
+struct CheckedOut {
+    purchases: Vec<Purchased>,
+    spent: i64,
+    scottycoins: i64,
+}
+
+impl From<Checkout> for CheckedOut {
+    fn from(done: Checkout) -> Self {
+        Self {
+            purchases: done.receipts.into_iter().map(Purchased::from).collect(),
+            spent: done.spent,
+            scottycoins: done.scottycoins,
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/items/checkout",
+    tag = "items",
+    request_body = CartBody,
+    responses(
+        (status = OK, body = CheckedOut),
+        (status = BAD_REQUEST, body = AuthErrBody),
+        (status = NOT_FOUND, body = AuthErrBody),
+        (status = CONFLICT, body = AuthErrBody),
+        (status = FORBIDDEN, body = AuthErrBody),
+        (status = UNAUTHORIZED, body = AuthErrBody),
+        (status = BAD_GATEWAY, body = AuthErrBody),
+    ),
+)]
+async fn checkout(
+    State(items): State<Items>,
+    Extension(users): Extension<Users>,
+    CurrentUser(user): CurrentUser,
+    body: Result<Json<CartBody>, JsonRejection>,
+) -> Result<Json<CheckedOut>, AuthError> {
+    let Json(body) = body.map_err(|_| AuthError::BadRequest("cart_body_invalid"))?;
+
+    let mut lines = Vec::with_capacity(body.lines.len());
+    for line in body.lines {
+        lines.push(Line {
+            item: Uuid::parse_str(&line.item_id)
+                .map_err(|_| AuthError::BadRequest("item_id_invalid"))?,
+            quantity: line.quantity,
+            chosen: choices(line.options)?,
+        });
+    }
+
+    let row = users.row(&user).await?;
+
+    if !row.player {
+        return Err(AuthError::Forbidden("not_a_player"));
+    }
+
+    let done = items
+        .checkout(row.id, lines, body.coupon.as_deref())
+        .await?;
+
+    Ok(Json(done.into()))
+}
+
+#[derive(Deserialize, ToSchema)]
+struct BidBody {
+    amount: i64,
+}
+
+#[derive(Serialize, ToSchema)]
+struct BidPlaced {
+    bid_id: i64,
+    item_id: String,
+    amount: i64,
+    /// Balance left after this bid went into escrow.
+    scottycoins: i64,
+}
+
+impl From<Placed> for BidPlaced {
+    fn from(placed: Placed) -> Self {
+        Self {
+            bid_id: placed.bid.id,
+            item_id: placed.bid.item_id.to_string(),
+            amount: placed.bid.amount,
+            scottycoins: placed.scottycoins,
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/items/{id}/bids",
+    tag = "items",
+    params(("id" = String, Path, description = "Auctioned item id")),
+    request_body = BidBody,
+    responses(
+        (status = OK, body = BidPlaced),
+        (status = BAD_REQUEST, body = AuthErrBody),
+        (status = NOT_FOUND, body = AuthErrBody),
+        (status = CONFLICT, body = AuthErrBody),
+        (status = FORBIDDEN, body = AuthErrBody),
+        (status = UNAUTHORIZED, body = AuthErrBody),
+        (status = BAD_GATEWAY, body = AuthErrBody),
+    ),
+)]
+async fn bid(
+    State(items): State<Items>,
+    Extension(users): Extension<Users>,
+    CurrentUser(user): CurrentUser,
+    Path(id): Path<String>,
+    body: Result<Json<BidBody>, JsonRejection>,
+) -> Result<Json<BidPlaced>, AuthError> {
+    let id = Uuid::parse_str(&id).map_err(|_| AuthError::BadRequest("item_id_invalid"))?;
+    let Json(body) = body.map_err(|_| AuthError::BadRequest("bid_body_invalid"))?;
 
     let row = users.row(&user).await?;
 
@@ -215,9 +506,144 @@ async fn buy(
         return Err(AuthError::Forbidden("not_a_player"));
     }
 
-    Ok(Json(
-        items.purchase(row.id, id, quantity, &chosen).await?.into(),
-    ))
+    Ok(Json(items.bid(row.id, id, body.amount).await?.into()))
+}
+
+#[derive(Deserialize, ToSchema)]
+struct WaitBody {
+    #[serde(default = "one")]
+    quantity: i64,
+    /// Wait for one choice of an option rather than the item as a whole.
+    #[serde(default)]
+    option: Option<PickBody>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct WaitView {
+    item_id: String,
+    item: String,
+    choice: Option<String>,
+    quantity: i64,
+    joined_at: String,
+    /// Place in the queue; absent once stock is reserved.
+    position: Option<usize>,
+    /// Only the caller can buy the reserved stock until then.
+    reserved_until: Option<String>,
+}
+
+impl From<Spot> for WaitView {
+    fn from(spot: Spot) -> Self {
+        Self {
+            item_id: spot.entry.item_id.to_string(),
+            item: spot.item,
+            choice: spot.entry.choice,
+            quantity: spot.entry.quantity,
+            joined_at: spot.entry.joined_at.to_rfc3339(),
+            position: spot.position,
+            reserved_until: spot.entry.reserved_until.map(|at| at.to_rfc3339()),
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/items/{id}/waitlist",
+    tag = "items",
+    params(("id" = String, Path, description = "Item id")),
+    request_body = WaitBody,
+    responses(
+        (status = OK, body = WaitView),
+        (status = BAD_REQUEST, body = AuthErrBody),
+        (status = NOT_FOUND, body = AuthErrBody),
+        (status = CONFLICT, body = AuthErrBody),
+        (status = FORBIDDEN, body = AuthErrBody),
+        (status = UNAUTHORIZED, body = AuthErrBody),
+        (status = BAD_GATEWAY, body = AuthErrBody),
+    ),
+)]
+async fn join_waitlist(
+    State(items): State<Items>,
+    Extension(users): Extension<Users>,
+    CurrentUser(user): CurrentUser,
+    Path(id): Path<String>,
+    body: Result<Json<WaitBody>, JsonRejection>,
+) -> Result<Json<WaitView>, AuthError> {
+    let id = Uuid::parse_str(&id).map_err(|_| AuthError::BadRequest("item_id_invalid"))?;
+    let (quantity, option) = match body {
+        Ok(Json(body)) => (body.quantity, body.option),
+        Err(JsonRejection::MissingJsonContentType(_)) => (1, None),
+        Err(_) => return Err(AuthError::BadRequest("waitlist_body_invalid")),
+    };
+
+    let choice = choices(option.into_iter().collect())?.pop();
+
+    let row = users.row(&user).await?;
+
+    if !row.player {
+        return Err(AuthError::Forbidden("not_a_player"));
+    }
+
+    Ok(Json(items.wait(row.id, id, quantity, choice).await?.into()))
+}
+
+#[derive(Serialize, ToSchema)]
+struct LeftWaitlist {
+    left: bool,
+}
+
+#[utoipa::path(
+    delete,
+    path = "/items/{id}/waitlist",
+    tag = "items",
+    params(("id" = String, Path, description = "Item id")),
+    responses(
+        (status = OK, body = LeftWaitlist),
+        (status = BAD_REQUEST, body = AuthErrBody),
+        (status = NOT_FOUND, body = AuthErrBody),
+        (status = UNAUTHORIZED, body = AuthErrBody),
+        (status = BAD_GATEWAY, body = AuthErrBody),
+    ),
+)]
+async fn leave_waitlist(
+    State(items): State<Items>,
+    Extension(users): Extension<Users>,
+    CurrentUser(user): CurrentUser,
+    Path(id): Path<String>,
+) -> Result<Json<LeftWaitlist>, AuthError> {
+    let id = Uuid::parse_str(&id).map_err(|_| AuthError::BadRequest("item_id_invalid"))?;
+    let row = users.row(&user).await?;
+
+    items.leave(row.id, id).await?;
+
+    Ok(Json(LeftWaitlist { left: true }))
+}
+
+#[derive(Serialize, ToSchema)]
+struct Queues {
+    waitlist: Vec<WaitView>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/me/waitlist",
+    tag = "items",
+    responses(
+        (status = OK, body = Queues),
+        (status = UNAUTHORIZED, body = AuthErrBody),
+        (status = BAD_GATEWAY, body = AuthErrBody),
+    ),
+)]
+async fn queues(
+    State(items): State<Items>,
+    Extension(users): Extension<Users>,
+    CurrentUser(user): CurrentUser,
+) -> Result<Json<Queues>, AuthError> {
+    let row = users.row(&user).await?;
+    let spots = items.waiting(row.id).await?;
+
+    Ok(Json(Queues {
+        waitlist: spots.into_iter().map(WaitView::from).collect(),
+    }))
 }
 
 #[derive(Serialize, ToSchema)]
@@ -229,6 +655,10 @@ struct PurchaseView {
     cost: i64,
     image_url: Option<String>,
     delivered: bool,
+    /// `entered`, `won` or `lost` for raffle tickets.
+    ticket: Option<String>,
+    /// Where and when to collect, once a slot is booked.
+    pickup: Option<PickupView>,
     options: Vec<PickedView>,
 }
 
@@ -242,6 +672,17 @@ impl PurchaseView {
             cost: row.cost,
             image_url: row.image_url,
             delivered: row.delivered,
+            ticket: row.ticket.map(|state| state.to_value()),
+            pickup: row
+                .pickup_slot_id
+                .zip(row.pickup_starts_at.zip(row.pickup_ends_at))
+                .map(|(slot_id, (starts_at, ends_at))| PickupView {
+                    slot_id: slot_id.to_string(),
+                    location: row.pickup_location.unwrap_or_default(),
+                    directions: row.pickup_directions,
+                    starts_at: starts_at.to_rfc3339(),
+                    ends_at: ends_at.to_rfc3339(),
+                }),
             options,
         }
     }
@@ -295,6 +736,8 @@ async fn mine(
 struct RefundBody {
     #[serde(default = "one")]
     quantity: i64,
+    #[serde(default)]
+    reason: Option<String>,
 }
 
 #[derive(Serialize, ToSchema)]
@@ -328,17 +771,143 @@ async fn give_back(
     let id = id
         .parse::<i64>()
         .map_err(|_| AuthError::BadRequest("purchase_id_invalid"))?;
-    let quantity = match body {
-        Ok(Json(body)) => body.quantity,
-        Err(JsonRejection::MissingJsonContentType(_)) => 1,
+    let (quantity, reason) = match body {
+        Ok(Json(body)) => (body.quantity, body.reason),
+        Err(JsonRejection::MissingJsonContentType(_)) => (1, None),
         Err(_) => return Err(AuthError::BadRequest("refund_body_invalid")),
     };
 
     let row = users.row(&user).await?;
-    let done = items.refund(row.id, id, quantity).await?;
+    let done = items
+        .refund(
+            row.id,
+            id,
+            quantity,
+            reason.as_deref().filter(|reason| !reason.trim().is_empty()),
+        )
+        .await?;
 
     Ok(Json(RefundView {
         refunded: done.refunded,
         scottycoins: done.scottycoins,
     }))
 }
+
+#[derive(Serialize, ToSchema)]
+struct PickupView {
+    slot_id: String,
+    location: String,
+    directions: Option<String>,
+    starts_at: String,
+    ends_at: String,
+}
+
+#[derive(Serialize, ToSchema)]
+struct SlotView {
+    slot_id: String,
+    location: String,
+    directions: Option<String>,
+    starts_at: String,
+    ends_at: String,
+    capacity: Option<i32>,
+    /// Orders the slot can still take; absent when it is unlimited.
+    remaining: Option<i64>,
+}
+
+impl From<Slot> for SlotView {
+    fn from(slot: Slot) -> Self {
+        Self {
+            remaining: slot.remaining(),
+            slot_id: slot.id.to_string(),
+            location: slot.location,
+            directions: slot.directions,
+            starts_at: slot.starts_at.to_rfc3339(),
+            ends_at: slot.ends_at.to_rfc3339(),
+            capacity: slot.capacity,
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct PickupSlots {
+    slots: Vec<SlotView>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/items/{id}/pickup-slots",
+    tag = "items",
+    params(("id" = String, Path, description = "Item id")),
+    responses(
+        (status = OK, body = PickupSlots),
+        (status = BAD_REQUEST, body = AuthErrBody),
+        (status = UNAUTHORIZED, body = AuthErrBody),
+        (status = BAD_GATEWAY, body = AuthErrBody),
+    ),
+)]
+async fn pickup_slots(
+    State(items): State<Items>,
+    CurrentUser(_user): CurrentUser,
+    Path(id): Path<String>,
+) -> Result<Json<PickupSlots>, AuthError> {
+    let id = Uuid::parse_str(&id).map_err(|_| AuthError::BadRequest("item_id_invalid"))?;
+    let slots = items.slots(id).await?;
+
+    Ok(Json(PickupSlots {
+        slots: slots.into_iter().map(SlotView::from).collect(),
+    }))
+}
+
+#[derive(Deserialize, ToSchema)]
+struct BookBody {
+    /// `null` cancels the booking.
+    slot_id: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct Booking {
+    purchase_id: i64,
+    pickup: Option<SlotView>,
+}
+
+#[utoipa::path(
+    put,
+    path = "/purchases/{id}/pickup",
+    tag = "items",
+    params(("id" = i64, Path, description = "Purchase id")),
+    request_body = BookBody,
+    responses(
+        (status = OK, body = Booking),
+        (status = BAD_REQUEST, body = AuthErrBody),
+        (status = NOT_FOUND, body = AuthErrBody),
+        (status = CONFLICT, body = AuthErrBody),
+        (status = UNAUTHORIZED, body = AuthErrBody),
+        (status = BAD_GATEWAY, body = AuthErrBody),
+    ),
+)]
+async fn book_pickup(
+    State(items): State<Items>,
+    Extension(users): Extension<Users>,
+    CurrentUser(user): CurrentUser,
+    Path(id): Path<String>,
+    body: Result<Json<BookBody>, JsonRejection>,
+) -> Result<Json<Booking>, AuthError> {
+    let id = id
+        .parse::<i64>()
+        .map_err(|_| AuthError::BadRequest("purchase_id_invalid"))?;
+    let Json(body) = body.map_err(|_| AuthError::BadRequest("pickup_body_invalid"))?;
+
+    let slot = body
+        .slot_id
+        .map(|raw| Uuid::parse_str(&raw))
+        .transpose()
+        .map_err(|_| AuthError::BadRequest("pickup_slot_id_invalid"))?;
+
+    let row = users.row(&user).await?;
+    let booked = items.book(row.id, id, slot).await?;
+
+    Ok(Json(Booking {
+        purchase_id: id,
+        pickup: booked.map(SlotView::from),
+    }))
+}
@@ -0,0 +1,561 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Utc};
+use entity::{item_option, items, waitlist};
+use sea_orm::prelude::Uuid;
+use sea_orm::sea_query::Expr;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, Condition, ConnectionTrait, EntityTrait,
+    PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, TransactionTrait,
+};
+
+use super::options::{self, Picked};
+use super::{Items, db_down, stock_of};
+use crate::auth::AuthError;
+
+/// How long a waiter has to buy once stock is reserved for them.
+const HOLD_MINUTES: i64 = 30;
+
+pub struct Spot {
+    pub entry: waitlist::Model,
+    pub item: String,
+    /// Place among the unreserved waiters for the item; `None` once reserved.
+    pub position: Option<usize>,
+}
+
+/// `user`'s entries out of `entries`, oldest first, each with its place
+/// among the item's unreserved waiters. Reservations that lapsed before
+/// `now` no longer count.
+fn places(
+    entries: Vec<waitlist::Model>,
+    user: Uuid,
+    now: DateTime<Utc>,
+) -> Vec<(waitlist::Model, Option<usize>)> {
+    let mut queued: HashMap<Uuid, usize> = HashMap::new();
+    let mut mine = Vec::new();
+    for entry in entries {
+        let position = match entry.reserved_until {
+            Some(until) if until > now => None,
+            Some(_) => continue,
+            None => {
+                let ahead = queued.entry(entry.item_id).or_default();
+                *ahead += 1;
+                Some(*ahead)
+            }
+        };
+
+        if entry.user_id == user {
+            mine.push((entry, position));
+        }
+    }
+    mine
+}
+
+fn keyed(entry: &waitlist::Model) -> Option<(i32, String)> {
+    entry.position.zip(entry.choice.clone())
+}
+
+/// Live reservations on `item` belonging to anyone but `user`.
+pub async fn held_by_others<C: ConnectionTrait>(
+    conn: &C,
+    item: Uuid,
+    user: Uuid,
+) -> Result<Vec<waitlist::Model>, AuthError> {
+    waitlist::Entity::find()
+        .filter(waitlist::Column::ItemId.eq(item))
+        .filter(waitlist::Column::UserId.ne(user))
+        .filter(waitlist::Column::ReservedUntil.gt(Utc::now()))
+        .all(conn)
+        .await
+        .map_err(db_down)
+}
+
+/// Units held by `held`, either in total or against one choice.
+pub fn reserved(held: &[waitlist::Model], choice: Option<(i32, &str)>) -> i64 {
+    held.iter()
+        .filter(|entry| match choice {
+            None => true,
+            Some((position, value)) => {
+                entry.position == Some(position) && entry.choice.as_deref() == Some(value)
+            }
+        })
+        .map(|entry| entry.quantity)
+        .sum()
+}
+
+/// Rejects picks whose per-choice stock is spoken for by someone else.
+pub fn check_choices(
+    defined: &[item_option::Model],
+    picked: &[Picked],
+    held: &[waitlist::Model],
+    quantity: i64,
+) -> Result<(), AuthError> {
+    for pick in picked {
+        let Some(row) = defined.iter().find(|row| row.id == pick.option_id) else {
+            continue;
+        };
+
+        let stock = options::choice_defs_of(row)
+            .into_iter()
+            .find(|choice| choice.value == pick.value)
+            .and_then(|choice| choice.stock);
+
+        let Some(stock) = stock else {
+            continue;
+        };
+
+        if stock - reserved(held, Some((pick.position, pick.value.as_str()))) < quantity {
+            return Err(AuthError::Conflict("out_of_stock"));
+        }
+    }
+
+    Ok(())
+}
+
+/// Clears `user`'s entries that a purchase of `picked` satisfies.
+pub async fn consume<C: ConnectionTrait>(
+    conn: &C,
+    item: Uuid,
+    user: Uuid,
+    picked: &[Picked],
+) -> Result<(), AuthError> {
+    let mut matches = Condition::any().add(waitlist::Column::Position.is_null());
+    for pick in picked {
+        matches = matches.add(
+            Condition::all()
+                .add(waitlist::Column::Position.eq(pick.position))
+                .add(waitlist::Column::Choice.eq(pick.value.clone())),
+        );
+    }
+
+    waitlist::Entity::delete_many()
+        .filter(waitlist::Column::ItemId.eq(item))
+        .filter(waitlist::Column::UserId.eq(user))
+        .filter(matches)
+        .exec(conn)
+        .await
+        .map_err(db_down)?;
+
+    Ok(())
+}
+
+/// Drops lapsed reservations and hands whatever stock is free to the
+/// oldest waiters it covers. Callers hold the item row lock.
+pub async fn promote<C: ConnectionTrait>(conn: &C, row: &items::Model) -> Result<(), AuthError> {
+    if drawn(conn, row).await? {
+        return Ok(());
+    }
+
+    let now = Utc::now();
+
+    waitlist::Entity::delete_many()
+        .filter(waitlist::Column::ItemId.eq(row.id))
+        .filter(waitlist::Column::ReservedUntil.lte(now))
+        .exec(conn)
+        .await
+        .map_err(db_down)?;
+
+    let entries = waitlist::Entity::find()
+        .filter(waitlist::Column::ItemId.eq(row.id))
+        .order_by_asc(waitlist::Column::JoinedAt)
+        .order_by_asc(waitlist::Column::Id)
+        .all(conn)
+        .await
+        .map_err(db_down)?;
+
+    if entries.iter().all(|entry| entry.reserved_until.is_some()) {
+        return Ok(());
+    }
+
+    let defined = options::of_item(conn, row.id).await?;
+
+    let mut free = stock_of(conn, row, &defined).await?;
+    let mut left: HashMap<(i32, String), i64> = HashMap::new();
+    for option in &defined {
+        for choice in options::choice_defs_of(option) {
+            if let Some(stock) = choice.stock {
+                left.insert((option.position, choice.value), stock);
+            }
+        }
+    }
+
+    for entry in entries
+        .iter()
+        .filter(|entry| entry.reserved_until.is_some())
+    {
+        free -= entry.quantity;
+        if let Some(stock) = keyed(entry).and_then(|key| left.get_mut(&key)) {
+            *stock -= entry.quantity;
+        }
+    }
+
+    let mut promoted = Vec::new();
+    for entry in entries
+        .iter()
+        .filter(|entry| entry.reserved_until.is_none())
+    {
+        let key = keyed(entry);
+        let choice = key.as_ref().and_then(|key| left.get(key)).copied();
+
+        if entry.quantity > choice.map_or(free, |stock| stock.min(free)) {
+            continue;
+        }
+
+        free -= entry.quantity;
+        if let Some(stock) = key.and_then(|key| left.get_mut(&key)) {
+            *stock -= entry.quantity;
+        }
+        promoted.push(entry.id);
+    }
+
+    if promoted.is_empty() {
+        return Ok(());
+    }
+
+    waitlist::Entity::update_many()
+        .col_expr(waitlist::Column::ReservedAt, Expr::value(now))
+        .col_expr(
+            waitlist::Column::ReservedUntil,
+            Expr::value(now + Duration::minutes(HOLD_MINUTES)),
+        )
+        .filter(waitlist::Column::Id.is_in(promoted))
+        .exec(conn)
+        .await
+        .map_err(db_down)?;
+
+    Ok(())
+}
+
+/// Whether `row` is a raffle whose draw already ran; checkout refuses those,
+/// so nobody should be queued for or promoted to its tickets.
+async fn drawn<C: ConnectionTrait>(conn: &C, row: &items::Model) -> Result<bool, AuthError> {
+    if !row.raffle {
+        return Ok(false);
+    }
+
+    let committed = entity::raffle::Entity::find_by_id(row.id)
+        .one(conn)
+        .await
+        .map_err(db_down)?;
+
+    Ok(committed.is_some_and(|committed| committed.drawn_at.is_some()))
+}
+
+/// Drops every waiter on `item`, reserved or not.
+pub async fn clear<C: ConnectionTrait>(conn: &C, item: Uuid) -> Result<(), AuthError> {
+    waitlist::Entity::delete_many()
+        .filter(waitlist::Column::ItemId.eq(item))
+        .exec(conn)
+        .await
+        .map_err(db_down)?;
+
+    Ok(())
+}
+
+impl Items {
+    /// Queues `user` for `quantity` of an item that is out of stock, or of
+    /// one choice on it.
+    pub async fn wait(
+        &self,
+        user: Uuid,
+        item: Uuid,
+        quantity: i64,
+        choice: Option<options::Choice>,
+    ) -> Result<Spot, AuthError> {
+        if quantity < 1 {
+            return Err(AuthError::BadRequest("quantity_invalid"));
+        }
+
+        let txn = self.db.begin().await.map_err(db_down)?;
+
+        let row = items::Entity::find_by_id(item)
+            .lock_exclusive()
+            .one(&txn)
+            .await
+            .map_err(db_down)?
+            .ok_or(AuthError::NotFound("item_unknown"))?;
+
+        let auctioned = entity::auction::Entity::find_by_id(item)
+            .one(&txn)
+            .await
+            .map_err(db_down)?;
+
+        if auctioned.is_some() {
+            return Err(AuthError::Conflict("item_auctioned"));
+        }
+
+        if drawn(&txn, &row).await? {
+            return Err(AuthError::Conflict("raffle_drawn"));
+        }
+
+        promote(&txn, &row).await?;
+
+        let defined = options::of_item(&txn, item).await?;
+
+        let pinned = match choice {
+            None => None,
+            Some(choice) => {
+                let option = defined
+                    .iter()
+                    .find(|row| row.id == choice.option_id)
+                    .ok_or(AuthError::BadRequest("option_unknown"))?;
+
+                let known = options::choice_defs_of(option)
+                    .into_iter()
+                    .find(|known| known.value == choice.value.trim())
+                    .ok_or(AuthError::BadRequest("option_answer_invalid"))?;
+
+                Some((option.position, known.value, known.stock))
+            }
+        };
+
+        let held = held_by_others(&txn, item, user).await?;
+        let mut free = stock_of(&txn, &row, &defined).await? - reserved(&held, None);
+        if let Some((position, value, Some(stock))) = &pinned {
+            free = free.min(stock - reserved(&held, Some((*position, value.as_str()))));
+        }
+
+        if quantity <= free {
+            return Err(AuthError::Conflict("item_in_stock"));
+        }
+
+        let (position, choice) = match pinned {
+            Some((position, value, _)) => (Some(position), Some(value)),
+            None => (None, None),
+        };
+
+        let mut same = waitlist::Entity::find()
+            .filter(waitlist::Column::ItemId.eq(item))
+            .filter(waitlist::Column::UserId.eq(user));
+        same = match (position, &choice) {
+            (Some(position), Some(value)) => same
+                .filter(waitlist::Column::Position.eq(position))
+                .filter(waitlist::Column::Choice.eq(value.clone())),
+            _ => same.filter(waitlist::Column::Position.is_null()),
+        };
+
+        if same.one(&txn).await.map_err(db_down)?.is_some() {
+            return Err(AuthError::Conflict("waitlist_joined"));
+        }
+
+        let entry = waitlist::ActiveModel {
+            item_id: ActiveValue::Set(item),
+            user_id: ActiveValue::Set(user),
+            position: ActiveValue::Set(position),
+            choice: ActiveValue::Set(choice),
+            quantity: ActiveValue::Set(quantity),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await
+        .map_err(db_down)?;
+
+        let ahead = waitlist::Entity::find()
+            .filter(waitlist::Column::ItemId.eq(item))
+            .filter(waitlist::Column::ReservedAt.is_null())
+            .filter(waitlist::Column::Id.ne(entry.id))
+            .count(&txn)
+            .await
+            .map_err(db_down)?;
+
+        txn.commit().await.map_err(db_down)?;
+
+        Ok(Spot {
+            entry,
+            item: row.name,
+            position: Some(ahead as usize + 1),
+        })
+    }
+
+    /// Drops `user` from an item's queues and hands any stock they had
+    /// reserved to the next waiters.
+    pub async fn leave(&self, user: Uuid, item: Uuid) -> Result<(), AuthError> {
+        let txn = self.db.begin().await.map_err(db_down)?;
+
+        let row = items::Entity::find_by_id(item)
+            .lock_exclusive()
+            .one(&txn)
+            .await
+            .map_err(db_down)?
+            .ok_or(AuthError::NotFound("waitlist_unknown"))?;
+
+        let gone = waitlist::Entity::delete_many()
+            .filter(waitlist::Column::ItemId.eq(item))
+            .filter(waitlist::Column::UserId.eq(user))
+            .exec(&txn)
+            .await
+            .map_err(db_down)?;
+
+        if gone.rows_affected == 0 {
+            return Err(AuthError::NotFound("waitlist_unknown"));
+        }
+
+        promote(&txn, &row).await?;
+
+        txn.commit().await.map_err(db_down)?;
+
+        Ok(())
+    }
+
+    /// Every queue `user` stands in. This only reads: lapsed reservations
+    /// are left out here and rotated by the next write to the item.
+    pub async fn waiting(&self, user: Uuid) -> Result<Vec<Spot>, AuthError> {
+        let mine = waitlist::Entity::find()
+            .select_only()
+            .column(waitlist::Column::ItemId)
+            .filter(waitlist::Column::UserId.eq(user))
+            .distinct()
+            .into_tuple::<Uuid>()
+            .all(&self.db)
+            .await
+            .map_err(db_down)?;
+
+        if mine.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let names: HashMap<Uuid, String> = items::Entity::find()
+            .select_only()
+            .column(items::Column::Id)
+            .column(items::Column::Name)
+            .filter(items::Column::Id.is_in(mine.iter().copied()))
+            .into_tuple::<(Uuid, String)>()
+            .all(&self.db)
+            .await
+            .map_err(db_down)?
+            .into_iter()
+            .collect();
+
+        let entries = waitlist::Entity::find()
+            .filter(waitlist::Column::ItemId.is_in(mine))
+            .order_by_asc(waitlist::Column::JoinedAt)
+            .order_by_asc(waitlist::Column::Id)
+            .all(&self.db)
+            .await
+            .map_err(db_down)?;
+
+        Ok(places(entries, user, Utc::now())
+            .into_iter()
+            .map(|(entry, position)| Spot {
+                item: names.get(&entry.item_id).cloned().unwrap_or_default(),
+                entry,
+                position,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod waitlist_tests {
+    use entity::enums::OptionKind;
+    use serde_json::json;
+
+    use super::*;
+
+    fn entry(id: i64, user: u128, choice: Option<(i32, &str)>, quantity: i64) -> waitlist::Model {
+        waitlist::Model {
+            id,
+            item_id: Uuid::from_u128(1),
+            user_id: Uuid::from_u128(user),
+            position: choice.map(|(position, _)| position),
+            choice: choice.map(|(_, value)| value.to_owned()),
+            quantity,
+            joined_at: Utc::now().into(),
+            reserved_at: None,
+            reserved_until: None,
+        }
+    }
+
+    fn until(mut row: waitlist::Model, until: DateTime<Utc>) -> waitlist::Model {
+        row.reserved_at = Some(Utc::now().into());
+        row.reserved_until = Some(until.into());
+        row
+    }
+
+    fn sizes() -> item_option::Model {
+        item_option::Model {
+            id: Uuid::from_u128(9),
+            item_id: Uuid::from_u128(1),
+            label: "Size".to_owned(),
+            kind: OptionKind::Select,
+            choices: json!([{"value": "M", "stock": 3}, "L"]),
+            required: true,
+            position: 0,
+        }
+    }
+
+    fn pick(value: &str) -> Picked {
+        Picked {
+            option_id: Uuid::from_u128(9),
+            position: 0,
+            label: "Size".to_owned(),
+            value: value.to_owned(),
+            cost: None,
+        }
+    }
+
+    #[test]
+    fn reserved_counts_the_whole_item_or_one_choice() {
+        let held = [
+            entry(1, 2, None, 2),
+            entry(2, 3, Some((0, "M")), 1),
+            entry(3, 4, Some((0, "L")), 4),
+        ];
+
+        assert_eq!(reserved(&held, None), 7);
+        assert_eq!(reserved(&held, Some((0, "M"))), 1);
+        assert_eq!(reserved(&held, Some((1, "M"))), 0);
+    }
+
+    #[test]
+    fn check_choices_refuses_stock_held_for_others() {
+        let defined = [sizes()];
+        let held = [entry(1, 2, Some((0, "M")), 2)];
+
+        assert!(check_choices(&defined, &[pick("M")], &held, 1).is_ok());
+        assert!(matches!(
+            check_choices(&defined, &[pick("M")], &held, 2),
+            Err(AuthError::Conflict("out_of_stock"))
+        ));
+        // Choices without their own stock are left to the item count.
+        assert!(check_choices(&defined, &[pick("L")], &held, 50).is_ok());
+    }
+
+    #[test]
+    fn places_skip_reserved_and_lapsed_entries() {
+        let now = Utc::now();
+        let me = 7;
+        let entries = vec![
+            until(entry(1, 2, None, 1), now + Duration::minutes(5)),
+            until(entry(2, 3, None, 1), now - Duration::minutes(5)),
+            entry(3, 4, None, 1),
+            entry(4, me, None, 1),
+        ];
+
+        let mine = places(entries, Uuid::from_u128(me), now);
+        assert_eq!(mine.len(), 1);
+        assert_eq!(mine[0].1, Some(2));
+    }
+
+    #[test]
+    fn a_live_reservation_has_no_place() {
+        let now = Utc::now();
+        let me = 7;
+        let entries = vec![
+            entry(1, 2, None, 1),
+            until(entry(2, me, None, 1), now + Duration::minutes(5)),
+        ];
+
+        let mine = places(entries, Uuid::from_u128(me), now);
+        assert_eq!(mine[0].1, None);
+    }
+
+    #[test]
+    fn a_lapsed_reservation_is_dropped() {
+        let now = Utc::now();
+        let me = 7;
+        let entries = vec![until(entry(1, me, None, 1), now)];
+
+        assert!(places(entries, Uuid::from_u128(me), now).is_empty());
+    }
+}
@@ -3,7 +3,7 @@ pub mod routes;
 use std::sync::LazyLock;
 
 use sea_orm::prelude::Uuid;
-use sea_orm::{DatabaseConnection, DbBackend, DbErr, FromQueryResult, Statement};
+use sea_orm::{ConnectionTrait, DatabaseConnection, DbBackend, DbErr, FromQueryResult, Statement};
 use serde::Serialize;
 use utoipa::ToSchema;
 
@@ -254,21 +254,7 @@ impl Leaderboard {
     }
 
     pub async fn standings(&self, user: Uuid, metric: Metric) -> Result<Standings, AuthError> {
-        let coins = matches!(metric, Metric::Coins);
-
-        let standings = Standing::find_by_statement(Statement::from_sql_and_values(
-            DbBackend::Postgres,
-            STANDINGS.as_str(),
-            [
-                DAILY_CAP.into(),
-                DAILY_BONUS.into(),
-                user.into(),
-                coins.into(),
-            ],
-        ))
-        .all(&self.db)
-        .await
-        .map_err(db_down)?;
+        let standings = standings_of(&self.db, user, metric).await?;
 
         let mine = standings.iter().find(|row| row.you);
 
@@ -314,6 +300,40 @@ impl Leaderboard {
     }
 }
 
+async fn standings_of<C: ConnectionTrait>(
+    conn: &C,
+    user: Uuid,
+    metric: Metric,
+) -> Result<Vec<Standing>, AuthError> {
+    let coins = matches!(metric, Metric::Coins);
+
+    Standing::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        STANDINGS.as_str(),
+        [
+            DAILY_CAP.into(),
+            DAILY_BONUS.into(),
+            user.into(),
+            coins.into(),
+        ],
+    ))
+    .all(conn)
+    .await
+    .map_err(db_down)
+}
+
+/// Where `user` places on the board, or `None` when they are not on it.
+pub async fn rank_of<C: ConnectionTrait>(
+    conn: &C,
+    user: Uuid,
+    metric: Metric,
+) -> Result<Option<i64>, AuthError> {
+    Ok(standings_of(conn, user, metric)
+        .await?
+        .into_iter()
+        .find_map(|row| row.you.then_some(row.rank)))
+}
+
 fn cup_for(community: &str, earned: i64) -> Option<Cup> {
     let target = TARGETS
         .iter()
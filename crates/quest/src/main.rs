@@ -15,6 +15,7 @@ mod openapi;
 mod passes;
 mod portal;
 mod staff;
+mod submissions;
 mod taps;
 mod tokens;
 mod updates;
@@ -194,6 +195,7 @@ async fn main() {
             );
             let devices = services.devices.clone();
             let users = services.users.clone();
+            let passes = services.passes.clone();
 
             let (routed, _) = openapi::split(&services);
             let portal_api = openapi::portal_router(&services);
@@ -207,6 +209,7 @@ async fn main() {
                 .merge(portal_api)
                 .layer(axum::Extension(devices))
                 .layer(axum::Extension(users))
+                .layer(axum::Extension(passes))
                 .layer(sessions)
                 .layer(axum::middleware::from_fn(auth::extract::bearer_id))
         }
@@ -69,6 +69,7 @@ pub struct Services {
     pub taps: crate::taps::Taps,
     pub passes: crate::passes::Passes,
     pub staff: crate::staff::Staff,
+    pub submissions: crate::submissions::Submissions,
     pub portal: crate::portal::Portal,
     pub desk: crate::portal::trade::Desk,
     pub assets: crate::portal::assets::Assets,
@@ -91,6 +92,7 @@ impl Services {
             tokens: crate::tokens::Tokens::new(db.clone()),
             passes,
             staff: crate::staff::Staff::new(db.clone()),
+            submissions: crate::submissions::Submissions::new(db.clone()),
             portal: crate::portal::Portal::new(db.clone()),
             taps: crate::taps::Taps::new(db.clone(), std::sync::Arc::new(master)),
             desk: crate::portal::trade::Desk::new(db.clone()),
@@ -142,6 +144,10 @@ pub fn api_router(services: &Services) -> utoipa_axum::router::OpenApiRouter {
             services.staff.clone(),
             services.taps.clone(),
         ))
+        .merge(crate::submissions::routes::router(
+            services.submissions.clone(),
+            services.assets.clone(),
+        ))
 }
 
 fn portal_paths(services: &Services) -> utoipa_axum::router::OpenApiRouter {
@@ -153,6 +159,8 @@ fn portal_paths(services: &Services) -> utoipa_axum::router::OpenApiRouter {
             services.desk.clone(),
             services.passes.clone(),
             services.assets.clone(),
+            services.submissions.clone(),
+            services.devices.clone(),
         ),
     )
 }
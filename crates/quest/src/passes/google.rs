@@ -0,0 +1,232 @@
+use base64::Engine as _;
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use rsa::RsaPrivateKey;
+use rsa::pkcs1v15::SigningKey;
+use rsa::pkcs8::DecodePrivateKey as _;
+use rsa::sha2::Sha256;
+use rsa::signature::{SignatureEncoding as _, Signer as _};
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use super::ORGANIZATION;
+
+const SAVE_URL: &str = "https://pay.google.com/gp/v/save/";
+
+const DEFAULT_CLASS: &str = "quest_pass";
+
+const LANGUAGE: &str = "en-US";
+
+/// The fields we need from a downloaded service-account key file.
+#[derive(Deserialize)]
+struct ServiceAccount {
+    client_email: String,
+    private_key: String,
+    private_key_id: Option<String>,
+}
+
+/// Issues Google Wallet generic passes as signed "Save to Google Wallet"
+/// links. Nothing is sent to Google up front: the class and object travel
+/// inside the JWT and Google creates them when the player saves.
+#[derive(Clone)]
+pub struct Wallet {
+    issuer_id: String,
+    class_id: String,
+    client_email: String,
+    key_id: Option<String>,
+    key: SigningKey<Sha256>,
+}
+
+/// Google only allows these in the part of an id after the issuer.
+fn id_safe(suffix: &str) -> bool {
+    !suffix.is_empty()
+        && suffix
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+}
+
+fn localized(value: &str) -> Value {
+    json!({ "defaultValue": { "language": LANGUAGE, "value": value } })
+}
+
+impl Wallet {
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let Some(raw) = crate::auth::env_opt("GOOGLE_WALLET_SERVICE_ACCOUNT") else {
+            return Ok(None);
+        };
+
+        let account = if raw.starts_with('{') {
+            raw
+        } else {
+            let cleaned: String = raw.chars().filter(|c| !c.is_ascii_whitespace()).collect();
+            let decoded = STANDARD.decode(cleaned).map_err(|e| {
+                format!("GOOGLE_WALLET_SERVICE_ACCOUNT is neither JSON nor base64: {e}")
+            })?;
+            String::from_utf8(decoded)
+                .map_err(|e| format!("GOOGLE_WALLET_SERVICE_ACCOUNT base64 is not text: {e}"))?
+        };
+
+        let issuer_id =
+            crate::auth::env_required("GOOGLE_WALLET_ISSUER_ID").map_err(|e| e.to_string())?;
+        let class =
+            crate::auth::env_opt("GOOGLE_WALLET_CLASS").unwrap_or_else(|| DEFAULT_CLASS.to_owned());
+
+        Self::new(&issuer_id, &class, &account).map(Some)
+    }
+
+    fn new(issuer_id: &str, class: &str, account: &str) -> Result<Self, String> {
+        if issuer_id.is_empty() || !issuer_id.chars().all(|c| c.is_ascii_digit()) {
+            return Err(format!(
+                "GOOGLE_WALLET_ISSUER_ID {issuer_id:?} is not a numeric issuer id"
+            ));
+        }
+        if !id_safe(class) {
+            return Err(format!(
+                "GOOGLE_WALLET_CLASS {class:?} may only use letters, digits, '.', '_' and '-'"
+            ));
+        }
+
+        let account: ServiceAccount = serde_json::from_str(account).map_err(|e| {
+            format!("GOOGLE_WALLET_SERVICE_ACCOUNT is not a service-account key: {e}")
+        })?;
+        let key = RsaPrivateKey::from_pkcs8_pem(&account.private_key)
+            .map_err(|e| format!("service-account private_key rejected: {e}"))?;
+
+        Ok(Self {
+            issuer_id: issuer_id.to_owned(),
+            class_id: format!("{issuer_id}.{class}"),
+            client_email: account.client_email,
+            key_id: account.private_key_id,
+            key: SigningKey::new(key),
+        })
+    }
+
+    /// The generic object for one holder, showing the same QR as the
+    /// Apple pass.
+    pub fn object(&self, serial: &str, andrew_id: &str, name: &str, token: &str) -> Value {
+        json!({
+            "id": format!("{}.{}", self.issuer_id, serial),
+            "classId": self.class_id,
+            "state": "ACTIVE",
+            "cardTitle": localized(ORGANIZATION),
+            "header": localized(name),
+            "subheader": localized("Andrew ID"),
+            "hexBackgroundColor": "#d9d9d9",
+            "textModulesData": [
+                { "id": "andrewId", "header": "ANDREW ID", "body": andrew_id },
+            ],
+            "barcode": {
+                "type": "QR_CODE",
+                "value": token,
+            },
+        })
+    }
+
+    /// An RS256 JWT that saves `object` to whichever Google account opens
+    /// its link.
+    pub fn save_jwt(&self, object: Value, issued_at: i64) -> String {
+        let mut header = json!({ "alg": "RS256", "typ": "JWT" });
+        if let Some(kid) = &self.key_id {
+            header["kid"] = json!(kid);
+        }
+
+        let claims = json!({
+            "iss": self.client_email,
+            "aud": "google",
+            "typ": "savetowallet",
+            "iat": issued_at,
+            "origins": [],
+            "payload": {
+                "genericClasses": [{ "id": self.class_id }],
+                "genericObjects": [object],
+            },
+        });
+
+        let signing_input = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(header.to_string()),
+            URL_SAFE_NO_PAD.encode(claims.to_string()),
+        );
+        let signature = self.key.sign(signing_input.as_bytes());
+
+        format!(
+            "{signing_input}.{}",
+            URL_SAFE_NO_PAD.encode(signature.to_bytes())
+        )
+    }
+}
+
+pub fn save_url(jwt: &str) -> String {
+    format!("{SAVE_URL}{jwt}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use p256::elliptic_curve::rand_core::OsRng;
+    use rsa::pkcs1v15::{Signature, VerifyingKey};
+    use rsa::pkcs8::{EncodePrivateKey as _, LineEnding};
+    use rsa::signature::Verifier as _;
+
+    fn account(key: &RsaPrivateKey) -> String {
+        json!({
+            "type": "service_account",
+            "client_email": "wallet@quest-test.iam.gserviceaccount.com",
+            "private_key_id": "k1",
+            "private_key": key.to_pkcs8_pem(LineEnding::LF).unwrap().as_str(),
+        })
+        .to_string()
+    }
+
+    fn decode(part: &str) -> Value {
+        serde_json::from_slice(&URL_SAFE_NO_PAD.decode(part).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn save_jwt_verifies_and_carries_the_token() {
+        let key = RsaPrivateKey::new(&mut OsRng, 1024).expect("key generates");
+        let wallet = Wallet::new("3388000000022", "quest_pass", &account(&key))
+            .expect("service account loads");
+
+        let object = wallet.object("jw8", "jw8", "Jo Wu", "Q1.jw8.1786400000.c2ln");
+        let jwt = wallet.save_jwt(object, 1786400000);
+        assert!(save_url(&jwt).starts_with("https://pay.google.com/gp/v/save/ey"));
+
+        let (signing_input, signature) = jwt.rsplit_once('.').expect("three parts");
+        let signature =
+            Signature::try_from(URL_SAFE_NO_PAD.decode(signature).unwrap().as_slice()).unwrap();
+        let verifying = VerifyingKey::<Sha256>::new(key.to_public_key());
+        assert!(
+            verifying
+                .verify(signing_input.as_bytes(), &signature)
+                .is_ok()
+        );
+
+        let (header, claims) = signing_input.split_once('.').unwrap();
+        let header = decode(header);
+        assert_eq!(header["alg"], "RS256");
+        assert_eq!(header["kid"], "k1");
+
+        let claims = decode(claims);
+        assert_eq!(claims["aud"], "google");
+        assert_eq!(claims["typ"], "savetowallet");
+        assert_eq!(claims["iss"], "wallet@quest-test.iam.gserviceaccount.com");
+
+        let object = &claims["payload"]["genericObjects"][0];
+        assert_eq!(object["id"], "3388000000022.jw8");
+        assert_eq!(object["classId"], "3388000000022.quest_pass");
+        assert_eq!(object["barcode"]["value"], "Q1.jw8.1786400000.c2ln");
+
+        let tampered = format!("{signing_input}x");
+        assert!(verifying.verify(tampered.as_bytes(), &signature).is_err());
+    }
+
+    #[test]
+    fn bad_configuration_is_refused() {
+        let key = RsaPrivateKey::new(&mut OsRng, 1024).expect("key generates");
+        let account = account(&key);
+
+        assert!(Wallet::new("issuer", "quest_pass", &account).is_err());
+        assert!(Wallet::new("3388000000022", "quest pass", &account).is_err());
+        assert!(Wallet::new("3388000000022", "quest_pass", "{}").is_err());
+    }
+}
@@ -1,33 +1,63 @@
 pub mod assets;
+pub mod google;
+pub mod notify;
 pub mod routes;
+pub mod web;
 
 use std::io::Cursor;
+use std::sync::Arc;
 
 use base64::Engine as _;
 use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use entity::enums::Dorm;
 use entity::{devices, users, wallet_pass};
 use pkpass::sign::{SignConfig, WWDR};
-use pkpass::{Package, PassBuilder, PassConfig, barcode, fields, resource, visual_appearance};
+use pkpass::{
+    Package, PassBuilder, PassConfig, barcode, fields, resource, visual_appearance, web_service,
+};
 use sea_orm::prelude::Uuid;
 use sea_orm::{
-    ActiveValue, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, sea_query,
+    ActiveModelTrait, ActiveValue, ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr,
+    EntityTrait, QueryFilter, QuerySelect, sea_query,
 };
 use x509_cert::Certificate;
 use x509_cert::der::oid::ObjectIdentifier;
 
 use crate::auth::AuthError;
 use crate::devices::key::{DeviceKey, decode_base64};
+use crate::leaderboard::Metric;
+use crate::tokens::Scope;
 
 const TOKEN_PREFIX: &str = "Q1";
 
+const ROTATING_PREFIX: &str = "Q2";
+
 const ISSUE_SKEW_SECS: i64 = 300;
 
+/// How long one rotating code is shown before the app signs the next.
+pub const ROTATION_SECS: i64 = 30;
+
+/// Steps either side of the server's own that still scan, for clock drift
+/// and the time a code spends on screen before the desk reads it.
+const ROTATION_SKEW_STEPS: i64 = 1;
+
+/// The longest a granted Q2 expiry may run before the app must ask again.
+pub const ROTATING_LIFETIME_SECS: i64 = 12 * 60 * 60;
+
+/// Leeway on the expiry for clocks that disagree.
+const EXPIRY_SKEW_SECS: i64 = 60;
+
 const ORGANIZATION: &str = "Terrier Ticket";
 
 #[derive(Clone)]
 pub struct Passes {
     db: DatabaseConnection,
     signing: Option<Signing>,
+    google: Option<google::Wallet>,
+    notifier: Arc<dyn notify::Notifier>,
+    /// Whether never-expiring Q1 tokens still scan. Wallet passes carry
+    /// one, so turning this off leaves only the app's rotating code.
+    accept_static: bool,
 }
 
 #[derive(Clone)]
@@ -35,6 +65,16 @@ struct Signing {
     config: SignConfig,
     pass_type_identifier: String,
     team_identifier: String,
+    /// Where Wallet reaches the web service; passes are static without it.
+    web_service_url: Option<String>,
+}
+
+/// The live side of the pass, read again for every build.
+struct Face {
+    scottycoins: i64,
+    thistlestones: i64,
+    dorm: Option<Dorm>,
+    rank: Option<i64>,
 }
 
 pub struct Issued {
@@ -44,16 +84,28 @@ pub struct Issued {
     pub pkpass: Vec<u8>,
 }
 
+pub struct Saved {
+    pub serial: String,
+    pub token: String,
+    pub fresh: bool,
+    pub jwt: String,
+    pub save_url: String,
+}
+
 pub struct Minted {
     pub token: String,
     pub andrew_id: String,
     pub name: String,
     pub issued_at: i64,
+    /// The expiry to put in Q2 codes until the app asks again.
+    pub expires_at: i64,
 }
 
 pub struct Holder {
     pub andrew_id: String,
     pub issued_at: i64,
+    /// When the scanned code stops working; `None` for a Q1 token.
+    pub expires_at: Option<i64>,
 }
 
 pub struct Signed {
@@ -73,6 +125,11 @@ fn encode_token(andrew_id: &str, issued_at: i64, signature: &[u8]) -> String {
     )
 }
 
+/// What the app signs for each rotating code.
+pub fn rotating_message(andrew_id: &str, expires_at: i64, step: i64) -> String {
+    format!("{ROTATING_PREFIX}.{andrew_id}.{expires_at}.{step}")
+}
+
 pub struct Token {
     pub andrew_id: String,
     pub issued_at: i64,
@@ -97,6 +154,59 @@ impl Token {
     }
 }
 
+/// A `Q2.<andrew>.<expires_at>.<step>.<sig>` code. The app signs a new
+/// one with the device key every [`ROTATION_SECS`], so a screenshot stops
+/// scanning within a step or two.
+pub struct Rotating {
+    pub andrew_id: String,
+    pub expires_at: i64,
+    pub step: i64,
+    pub signature: Vec<u8>,
+}
+
+impl Rotating {
+    pub fn parse(raw: &str) -> Option<Self> {
+        let mut parts = raw.trim().split('.');
+        let (version, andrew_id, expires_at, step, signature) = (
+            parts.next()?,
+            parts.next()?,
+            parts.next()?,
+            parts.next()?,
+            parts.next()?,
+        );
+
+        if parts.next().is_some() || version != ROTATING_PREFIX || andrew_id.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            andrew_id: andrew_id.to_owned(),
+            expires_at: expires_at.parse().ok()?,
+            step: step.parse().ok()?,
+            signature: decode_base64(signature)?,
+        })
+    }
+
+    fn message(&self) -> String {
+        rotating_message(&self.andrew_id, self.expires_at, self.step)
+    }
+
+    /// Refuses a code that has expired, claims too long a life, or was
+    /// signed outside the steps around `now`.
+    fn current(&self, now: i64) -> Result<(), AuthError> {
+        if self.expires_at + EXPIRY_SKEW_SECS < now {
+            return Err(AuthError::Unauthorized("pass_token_expired"));
+        }
+        if self.expires_at - now > ROTATING_LIFETIME_SECS + EXPIRY_SKEW_SECS {
+            return Err(AuthError::BadRequest("pass_token_lifetime"));
+        }
+        if (self.step - now.div_euclid(ROTATION_SECS)).abs() > ROTATION_SKEW_STEPS {
+            return Err(AuthError::Unauthorized("pass_token_stale"));
+        }
+        Ok(())
+    }
+}
+
 fn pem(name: &'static str) -> Result<Option<String>, String> {
     let Some(raw) = crate::auth::env_opt(name) else {
         return Ok(None);
@@ -160,42 +270,73 @@ fn certified(cert: &Certificate, pass_type: &str, team: &str) -> Result<(), Stri
     Ok(())
 }
 
-impl Passes {
-    pub fn unconfigured(db: DatabaseConnection) -> Self {
-        Self { db, signing: None }
+fn apple_from_env() -> Result<Option<Signing>, String> {
+    let (Some(cert), Some(key)) = (pem("PASS_CERT_PEM")?, pem("PASS_KEY_PEM")?) else {
+        return Ok(None);
+    };
+
+    if key.contains("BEGIN RSA PRIVATE KEY") {
+        return Err("PASS_KEY_PEM is PKCS#1; convert with `openssl pkcs8 -topk8 -nocrypt`".into());
+    }
+    if key.contains("ENCRYPTED") {
+        return Err("PASS_KEY_PEM is encrypted; the signer takes no passphrase".into());
     }
 
-    pub fn from_env(db: DatabaseConnection) -> Result<Self, String> {
-        let (Some(cert), Some(key)) = (pem("PASS_CERT_PEM")?, pem("PASS_KEY_PEM")?) else {
-            return Ok(Self { db, signing: None });
-        };
+    let config = SignConfig::new(&WWDR::G4, cert.as_bytes(), &key)
+        .map_err(|e| format!("pass signing certificate rejected: {e}"))?;
 
-        if key.contains("BEGIN RSA PRIVATE KEY") {
-            return Err(
-                "PASS_KEY_PEM is PKCS#1; convert with `openssl pkcs8 -topk8 -nocrypt`".into(),
-            );
-        }
-        if key.contains("ENCRYPTED") {
-            return Err("PASS_KEY_PEM is encrypted; the signer takes no passphrase".into());
+    let pass_type_identifier =
+        crate::auth::env_required("PASS_TYPE_IDENTIFIER").map_err(|e| e.to_string())?;
+    let team_identifier =
+        crate::auth::env_required("PASS_TEAM_IDENTIFIER").map_err(|e| e.to_string())?;
+
+    certified(&config.sign_cert, &pass_type_identifier, &team_identifier)?;
+
+    let web_service_url = crate::auth::env_opt("PASS_WEB_SERVICE_URL")
+        .map(|url| url.trim_end_matches('/').to_owned());
+
+    if let Some(url) = &web_service_url {
+        if !url.starts_with("https://") {
+            return Err(format!(
+                "PASS_WEB_SERVICE_URL {url:?} must be https; Wallet will not call anything else"
+            ));
         }
+    }
 
-        let config = SignConfig::new(&WWDR::G4, cert.as_bytes(), &key)
-            .map_err(|e| format!("pass signing certificate rejected: {e}"))?;
+    Ok(Some(Signing {
+        config,
+        pass_type_identifier,
+        team_identifier,
+        web_service_url,
+    }))
+}
 
-        let pass_type_identifier =
-            crate::auth::env_required("PASS_TYPE_IDENTIFIER").map_err(|e| e.to_string())?;
-        let team_identifier =
-            crate::auth::env_required("PASS_TEAM_IDENTIFIER").map_err(|e| e.to_string())?;
+fn accept_static_from_env() -> Result<bool, String> {
+    match crate::auth::env_opt("PASS_ACCEPT_Q1").as_deref() {
+        None | Some("true" | "1" | "yes") => Ok(true),
+        Some("false" | "0" | "no") => Ok(false),
+        Some(other) => Err(format!("PASS_ACCEPT_Q1 {other:?} is not true or false")),
+    }
+}
 
-        certified(&config.sign_cert, &pass_type_identifier, &team_identifier)?;
+impl Passes {
+    pub fn unconfigured(db: DatabaseConnection) -> Self {
+        Self {
+            db,
+            signing: None,
+            google: None,
+            notifier: Arc::new(notify::Silent),
+            accept_static: true,
+        }
+    }
 
+    pub fn from_env(db: DatabaseConnection) -> Result<Self, String> {
         Ok(Self {
             db,
-            signing: Some(Signing {
-                config,
-                pass_type_identifier,
-                team_identifier,
-            }),
+            signing: apple_from_env()?,
+            google: google::Wallet::from_env()?,
+            notifier: notify::from_env(),
+            accept_static: accept_static_from_env()?,
         })
     }
 
@@ -232,20 +373,43 @@ impl Passes {
             .await
             .map_err(db_down)?;
 
-        if let Some(row) = held {
+        if let Some(row) = held.as_ref().filter(|row| row.revoked_at.is_none()) {
             let signature = decode_base64(&row.signature)
                 .ok_or(AuthError::Upstream("pass_signature_corrupt"))?;
             let message = signed_message(&row.andrew_id, row.issued_at);
 
             if self.signer(user, &message, &signature).await?.is_some() {
-                return Ok((row, false));
+                return Ok((row.clone(), false));
             }
         }
 
+        let offered = match offered {
+            Some(offered) => offered,
+            None if held.as_ref().is_some_and(|row| row.revoked_at.is_some()) => {
+                return Err(AuthError::Forbidden("pass_revoked"));
+            }
+            None => return Err(AuthError::BadRequest("pass_signature_required")),
+        };
+
+        self.sign(user, andrew_id, name, offered, held.is_some())
+            .await
+            .map(|row| (row, true))
+    }
+
+    /// Stores a pass signed by one of `user`'s devices, replacing any held
+    /// one and clearing a revocation, and tells Wallet about the change.
+    async fn sign(
+        &self,
+        user: Uuid,
+        andrew_id: &str,
+        name: &str,
+        offered: Signed,
+        replacing: bool,
+    ) -> Result<wallet_pass::Model, AuthError> {
         let Signed {
             issued_at,
             signature,
-        } = offered.ok_or(AuthError::BadRequest("pass_signature_required"))?;
+        } = offered;
 
         if (crate::devices::proof::now() - issued_at).abs() > ISSUE_SKEW_SECS {
             return Err(AuthError::BadRequest("pass_issued_at_skewed"));
@@ -257,6 +421,7 @@ impl Passes {
             .await?
             .ok_or(AuthError::Unauthorized("pass_signature"))?;
 
+        let now = chrono::Utc::now();
         let fresh = wallet_pass::Model {
             user_id: user,
             serial: andrew_id.to_owned(),
@@ -265,11 +430,18 @@ impl Passes {
             issued_at,
             public_key,
             signature: URL_SAFE_NO_PAD.encode(&signature),
-            created_at: chrono::Utc::now().into(),
+            created_at: now.into(),
+            auth_token: hex::encode(rand::random::<[u8; 32]>()),
+            updated_at: now.into(),
+            shown_scottycoins: None,
+            shown_thistlestones: None,
+            revoked_at: None,
         };
 
         let mut writing: wallet_pass::ActiveModel = fresh.clone().into();
         writing.created_at = ActiveValue::NotSet;
+        writing.shown_scottycoins = ActiveValue::NotSet;
+        writing.shown_thistlestones = ActiveValue::NotSet;
 
         wallet_pass::Entity::insert(writing)
             .on_conflict(
@@ -280,6 +452,8 @@ impl Passes {
                         wallet_pass::Column::IssuedAt,
                         wallet_pass::Column::PublicKey,
                         wallet_pass::Column::Signature,
+                        wallet_pass::Column::UpdatedAt,
+                        wallet_pass::Column::RevokedAt,
                     ])
                     .to_owned(),
             )
@@ -287,7 +461,19 @@ impl Passes {
             .await
             .map_err(db_down)?;
 
-        Ok((fresh, true))
+        // A re-signed pass keeps its web-service token, so read back what
+        // was kept rather than trusting the one just generated.
+        let row = wallet_pass::Entity::find_by_id(user)
+            .one(&self.db)
+            .await
+            .map_err(db_down)?
+            .ok_or(AuthError::Upstream("pass_missing"))?;
+
+        if replacing {
+            self.push(user).await?;
+        }
+
+        Ok(row)
     }
 
     pub async fn issue(
@@ -299,13 +485,62 @@ impl Passes {
     ) -> Result<Issued, AuthError> {
         let signing = self.signing.as_ref().ok_or(AuthError::NotConfigured)?;
         let (row, fresh) = self.ensure(user, andrew_id, name, offered).await?;
+        let face = self.face(&row).await?;
+        let issued = rebuild(signing, &row, &face)?;
+        self.shown(&row, &face).await?;
+
+        Ok(Issued { fresh, ..issued })
+    }
 
-        Ok(Issued {
+    /// A "Save to Google Wallet" link for the same pass and token the
+    /// Apple pass carries.
+    pub async fn google(
+        &self,
+        user: Uuid,
+        andrew_id: &str,
+        name: &str,
+        offered: Option<Signed>,
+    ) -> Result<Saved, AuthError> {
+        let wallet = self.google.as_ref().ok_or(AuthError::NotConfigured)?;
+        let (row, fresh) = self.ensure(user, andrew_id, name, offered).await?;
+        let signature =
+            decode_base64(&row.signature).ok_or(AuthError::Upstream("pass_signature_corrupt"))?;
+        let token = encode_token(&row.andrew_id, row.issued_at, &signature);
+
+        let object = wallet.object(&row.serial, &row.andrew_id, &row.name, &token);
+        let jwt = wallet.save_jwt(object, crate::devices::proof::now());
+
+        Ok(Saved {
+            save_url: google::save_url(&jwt),
+            serial: row.serial,
+            token,
             fresh,
-            ..rebuild(signing, row)?
+            jwt,
         })
     }
 
+    /// Re-signs the pass from the calling device whether or not the held
+    /// one still verifies, so a pass revoked with its device comes back in
+    /// one call.
+    pub async fn reissue(
+        &self,
+        user: Uuid,
+        andrew_id: &str,
+        name: &str,
+        offered: Signed,
+    ) -> Result<Minted, AuthError> {
+        let held = wallet_pass::Entity::find_by_id(user)
+            .one(&self.db)
+            .await
+            .map_err(db_down)?;
+
+        let row = self
+            .sign(user, andrew_id, name, offered, held.is_some())
+            .await?;
+
+        minted(row)
+    }
+
     pub async fn token(
         &self,
         user: Uuid,
@@ -314,48 +549,214 @@ impl Passes {
         offered: Option<Signed>,
     ) -> Result<Minted, AuthError> {
         let (row, _) = self.ensure(user, andrew_id, name, offered).await?;
-        let signature =
-            decode_base64(&row.signature).ok_or(AuthError::Upstream("pass_signature_corrupt"))?;
 
-        Ok(Minted {
-            token: encode_token(&row.andrew_id, row.issued_at, &signature),
-            andrew_id: row.andrew_id,
-            name: row.name,
-            issued_at: row.issued_at,
+        minted(row)
+    }
+
+    /// Reads the holder's balances, dorm and rank.
+    async fn face(&self, row: &wallet_pass::Model) -> Result<Face, AuthError> {
+        let balances = crate::tokens::balances_of(&self.db, row.user_id, Scope::Lifetime).await?;
+        let rank = crate::leaderboard::rank_of(&self.db, row.user_id, Metric::Gems).await?;
+        let dorm = users::Entity::find_by_id(row.user_id)
+            .one(&self.db)
+            .await
+            .map_err(db_down)?
+            .and_then(|holder| holder.dorm);
+
+        Ok(Face {
+            scottycoins: balances.scottycoins,
+            thistlestones: balances.thistlestones,
+            dorm,
+            rank,
         })
     }
 
+    /// Remembers the balances a built pass showed so the next build can
+    /// report what changed. Call only once that build is being handed out.
+    async fn shown(&self, row: &wallet_pass::Model, face: &Face) -> Result<(), AuthError> {
+        if row.shown_scottycoins == Some(face.scottycoins)
+            && row.shown_thistlestones == Some(face.thistlestones)
+        {
+            return Ok(());
+        }
+
+        wallet_pass::Entity::update_many()
+            .col_expr(
+                wallet_pass::Column::ShownScottycoins,
+                sea_query::Expr::value(face.scottycoins),
+            )
+            .col_expr(
+                wallet_pass::Column::ShownThistlestones,
+                sea_query::Expr::value(face.thistlestones),
+            )
+            .filter(wallet_pass::Column::UserId.eq(row.user_id))
+            .exec(&self.db)
+            .await
+            .map_err(db_down)?;
+
+        Ok(())
+    }
+
     pub async fn verify(&self, raw: &str) -> Result<Holder, AuthError> {
+        if let Some(code) = Rotating::parse(raw) {
+            let now = crate::devices::proof::now();
+            code.current(now)?;
+
+            let holder = self
+                .holder_signed(&code.andrew_id, &code.message(), &code.signature)
+                .await?;
+
+            return Ok(Holder {
+                andrew_id: holder.andrew_id,
+                issued_at: code.step * ROTATION_SECS,
+                expires_at: Some(code.expires_at),
+            });
+        }
+
         let token = Token::parse(raw).ok_or(AuthError::BadRequest("pass_token_malformed"))?;
 
+        if !self.accept_static {
+            return Err(AuthError::Unauthorized("pass_token_retired"));
+        }
+
+        let message = signed_message(&token.andrew_id, token.issued_at);
+        let holder = self
+            .holder_signed(&token.andrew_id, &message, &token.signature)
+            .await?;
+
+        Ok(Holder {
+            andrew_id: holder.andrew_id,
+            issued_at: token.issued_at,
+            expires_at: None,
+        })
+    }
+
+    /// The user `andrew_id` names, if one of their devices signed `message`.
+    async fn holder_signed(
+        &self,
+        andrew_id: &str,
+        message: &str,
+        signature: &[u8],
+    ) -> Result<users::Model, AuthError> {
         let holder = users::Entity::find()
-            .filter(users::Column::AndrewId.eq(&token.andrew_id))
+            .filter(users::Column::AndrewId.eq(andrew_id))
             .one(&self.db)
             .await
             .map_err(db_down)?
             .ok_or(AuthError::NotFound("pass_holder_unknown"))?;
 
-        let message = signed_message(&token.andrew_id, token.issued_at);
-        self.signer(holder.id, &message, &token.signature)
-            .await?
-            .ok_or(AuthError::Unauthorized("pass_signature"))?;
+        if self.signer(holder.id, message, signature).await?.is_some() {
+            return Ok(holder);
+        }
 
-        Ok(Holder {
-            andrew_id: holder.andrew_id,
-            issued_at: token.issued_at,
+        // Signed by the device a revoked pass was bound to: say so rather
+        // than reporting a bad signature.
+        let revoked = wallet_pass::Entity::find_by_id(holder.id)
+            .one(&self.db)
+            .await
+            .map_err(db_down)?
+            .filter(|row| row.revoked_at.is_some())
+            .and_then(|row| DeviceKey::parse(&row.public_key))
+            .is_some_and(|key| key.verifies(message.as_bytes(), signature));
+
+        Err(if revoked {
+            AuthError::Forbidden("pass_revoked")
+        } else {
+            AuthError::Unauthorized("pass_signature")
         })
     }
 }
 
-fn rebuild(signing: &Signing, row: wallet_pass::Model) -> Result<Issued, AuthError> {
+/// Moves the live pass `old` signed onto `new`, inside the caller's
+/// transaction, using the new key's signature over a fresh issue time.
+/// True when there was a pass to move.
+pub async fn rebind<C: ConnectionTrait>(
+    conn: &C,
+    user: Uuid,
+    old: &str,
+    new: &DeviceKey,
+    offered: Option<Signed>,
+) -> Result<bool, AuthError> {
+    let Some(row) = wallet_pass::Entity::find_by_id(user)
+        .filter(wallet_pass::Column::PublicKey.eq(old))
+        .filter(wallet_pass::Column::RevokedAt.is_null())
+        .lock_exclusive()
+        .one(conn)
+        .await
+        .map_err(db_down)?
+    else {
+        return Ok(false);
+    };
+
+    let Signed {
+        issued_at,
+        signature,
+    } = offered.ok_or(AuthError::BadRequest("pass_signature_required"))?;
+
+    if (crate::devices::proof::now() - issued_at).abs() > ISSUE_SKEW_SECS {
+        return Err(AuthError::BadRequest("pass_issued_at_skewed"));
+    }
+
+    if !new.verifies(
+        signed_message(&row.andrew_id, issued_at).as_bytes(),
+        &signature,
+    ) {
+        return Err(AuthError::Unauthorized("pass_signature"));
+    }
+
+    wallet_pass::ActiveModel {
+        user_id: ActiveValue::Unchanged(row.user_id),
+        public_key: ActiveValue::Set(new.hex().to_owned()),
+        signature: ActiveValue::Set(URL_SAFE_NO_PAD.encode(&signature)),
+        issued_at: ActiveValue::Set(issued_at),
+        updated_at: ActiveValue::Set(chrono::Utc::now().into()),
+        ..Default::default()
+    }
+    .update(conn)
+    .await
+    .map_err(db_down)?;
+
+    Ok(true)
+}
+
+fn minted(row: wallet_pass::Model) -> Result<Minted, AuthError> {
     let signature =
         decode_base64(&row.signature).ok_or(AuthError::Upstream("pass_signature_corrupt"))?;
-    let token = encode_token(&row.andrew_id, row.issued_at, &signature);
-    let pkpass = build(signing, &row.serial, &row.andrew_id, &row.name, &token)?;
+
+    Ok(Minted {
+        token: encode_token(&row.andrew_id, row.issued_at, &signature),
+        andrew_id: row.andrew_id,
+        name: row.name,
+        issued_at: row.issued_at,
+        expires_at: crate::devices::proof::now() + ROTATING_LIFETIME_SECS,
+    })
+}
+
+/// The token a pass carries, or `None` once it was revoked: Wallet keeps
+/// whatever it is last served, so a revoked pass is voided in place rather
+/// than refused.
+fn scannable(row: &wallet_pass::Model) -> Result<Option<String>, AuthError> {
+    if row.revoked_at.is_some() {
+        return Ok(None);
+    }
+
+    let signature =
+        decode_base64(&row.signature).ok_or(AuthError::Upstream("pass_signature_corrupt"))?;
+
+    Ok(Some(encode_token(
+        &row.andrew_id,
+        row.issued_at,
+        &signature,
+    )))
+}
+
+fn rebuild(signing: &Signing, row: &wallet_pass::Model, face: &Face) -> Result<Issued, AuthError> {
+    let token = scannable(row)?;
+    let pkpass = build(signing, row, face, token.as_deref())?;
 
     Ok(Issued {
-        serial: row.serial,
-        token,
+        serial: row.serial.clone(),
+        token: token.unwrap_or_default(),
         fresh: false,
         pkpass,
     })
@@ -363,30 +764,69 @@ fn rebuild(signing: &Signing, row: wallet_pass::Model) -> Result<Issued, AuthErr
 
 fn build(
     signing: &Signing,
-    serial: &str,
-    andrew_id: &str,
-    name: &str,
-    token: &str,
+    row: &wallet_pass::Model,
+    face: &Face,
+    token: Option<&str>,
 ) -> Result<Vec<u8>, AuthError> {
-    assemble(signing, serial, andrew_id, name, token).map_err(|e| {
+    assemble(signing, row, face, token).map_err(|e| {
         eprintln!("passes: {e}");
         AuthError::Upstream("pass_build_failed")
     })
 }
 
+fn dorm_name(dorm: &Dorm) -> &'static str {
+    match dorm {
+        Dorm::Morewood => "Morewood",
+        Dorm::Etower => "E-Tower",
+        Dorm::Whesco => "Whesco",
+        Dorm::Mcgillboss => "Boss, McGill & Maggie Mo",
+        Dorm::Hammershlag => "Hammerschlag",
+        Dorm::Donner => "Donner",
+        Dorm::Stever => "Stever",
+        Dorm::Mudge => "Mudge",
+        Dorm::Res => "RANCH",
+    }
+}
+
+/// What Wallet says when a balance field changes. It only substitutes the
+/// new value, so the difference from what was last shown is baked in; with
+/// nothing shown before, the message falls back to the new total.
+fn moved(shown: Option<i64>, now: i64, one: &str, many: &str) -> String {
+    let noun = |n: i64| if n == 1 { one } else { many };
+
+    match shown.map(|shown| now - shown) {
+        Some(delta) if delta > 0 => format!("You earned {delta} {}", noun(delta)),
+        Some(delta) if delta < 0 => format!("You spent {} {}", -delta, noun(-delta)),
+        _ => format!("You now have %@ {many}"),
+    }
+}
+
 fn assemble(
     signing: &Signing,
-    serial: &str,
-    andrew_id: &str,
-    name: &str,
-    token: &str,
+    row: &wallet_pass::Model,
+    face: &Face,
+    token: Option<&str>,
 ) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-    let card = fields::Type::StoreCard {
+    let mut card = fields::Type::StoreCard {
         pass_fields: fields::Fields::default(),
     }
+    .add_header_field(fields::Content::new(
+        "scottycoins",
+        &face.scottycoins.to_string(),
+        fields::ContentOptions {
+            label: Some("COINS".into()),
+            change_message: Some(moved(
+                row.shown_scottycoins,
+                face.scottycoins,
+                "coin",
+                "coins",
+            )),
+            ..Default::default()
+        },
+    ))
     .add_secondary_field(fields::Content::new(
         "name",
-        name,
+        &row.name,
         fields::ContentOptions {
             label: Some("NAME".into()),
             ..Default::default()
@@ -394,20 +834,69 @@ fn assemble(
     ))
     .add_secondary_field(fields::Content::new(
         "andrewId",
-        andrew_id,
+        &row.andrew_id,
         fields::ContentOptions {
             label: Some("ANDREW ID".into()),
             text_alignment: Some(fields::TextAlignment::Right),
             ..Default::default()
         },
+    ))
+    .add_auxiliary_field(fields::Content::new(
+        "thistlestones",
+        &face.thistlestones.to_string(),
+        fields::ContentOptions {
+            label: Some("THISTLESTONES".into()),
+            change_message: Some(moved(
+                row.shown_thistlestones,
+                face.thistlestones,
+                "thistlestone",
+                "thistlestones",
+            )),
+            ..Default::default()
+        },
     ));
 
-    let pass = PassBuilder::new(PassConfig {
+    if let Some(dorm) = &face.dorm {
+        card = card.add_auxiliary_field(fields::Content::new(
+            "dorm",
+            dorm_name(dorm),
+            fields::ContentOptions {
+                label: Some("DORM".into()),
+                ..Default::default()
+            },
+        ));
+    }
+
+    if let Some(rank) = face.rank {
+        card = card.add_auxiliary_field(fields::Content::new(
+            "rank",
+            &format!("#{rank}"),
+            fields::ContentOptions {
+                label: Some("RANK".into()),
+                text_alignment: Some(fields::TextAlignment::Right),
+                change_message: Some("You are now ranked %@".into()),
+                ..Default::default()
+            },
+        ));
+    }
+
+    if token.is_none() {
+        card = card.add_back_field(fields::Content::new(
+            "revoked",
+            "This pass was revoked along with its device. Open the app to add a new one.",
+            fields::ContentOptions {
+                label: Some("REVOKED".into()),
+                ..Default::default()
+            },
+        ));
+    }
+
+    let mut builder = PassBuilder::new(PassConfig {
         organization_name: ORGANIZATION.into(),
         description: ORGANIZATION.into(),
         pass_type_identifier: signing.pass_type_identifier.clone(),
         team_identifier: signing.team_identifier.clone(),
-        serial_number: serial.to_owned(),
+        serial_number: row.serial.clone(),
     })
     .logo_text(ORGANIZATION.into())
     .appearance(visual_appearance::VisualAppearance {
@@ -416,14 +905,26 @@ fn assemble(
         label_color: visual_appearance::Color::new(0, 0, 0),
     })
     .set_sharing_prohibited(true)
-    .add_barcode(barcode::Barcode {
-        message: token.to_owned(),
-        format: barcode::BarcodeFormat::QR,
-        alt_text: None,
-        message_encoding: "iso-8859-1".into(),
-    })
-    .fields(card)
-    .build();
+    .voided(token.is_none())
+    .fields(card);
+
+    if let Some(token) = token {
+        builder = builder.add_barcode(barcode::Barcode {
+            message: token.to_owned(),
+            format: barcode::BarcodeFormat::QR,
+            alt_text: None,
+            message_encoding: "iso-8859-1".into(),
+        });
+    }
+
+    if let Some(url) = &signing.web_service_url {
+        builder = builder.web_service(web_service::WebService {
+            authentication_token: row.auth_token.clone(),
+            web_service_url: url.clone(),
+        });
+    }
+
+    let pass = builder.build();
 
     let mut package = Package::new(pass);
 
@@ -520,6 +1021,160 @@ mod token_tests {
     }
 }
 
+#[cfg(test)]
+mod minted_tests {
+    use super::*;
+
+    fn held(signature: &str) -> wallet_pass::Model {
+        let now: sea_orm::prelude::DateTimeWithTimeZone = chrono::Utc::now().into();
+        wallet_pass::Model {
+            user_id: Uuid::from_u128(1),
+            serial: "serial".to_owned(),
+            andrew_id: "jw8".to_owned(),
+            name: "Jo Walker".to_owned(),
+            issued_at: 1786400000,
+            public_key: String::new(),
+            signature: signature.to_owned(),
+            created_at: now,
+            auth_token: "token".to_owned(),
+            updated_at: now,
+            shown_scottycoins: None,
+            shown_thistlestones: None,
+            revoked_at: None,
+        }
+    }
+
+    #[test]
+    fn a_reissued_token_carries_the_stored_signature() {
+        let signature = [7u8; 64];
+        let out = minted(held(&URL_SAFE_NO_PAD.encode(signature))).expect("pass mints");
+
+        let token = Token::parse(&out.token).expect("token parses");
+        assert_eq!(token.andrew_id, "jw8");
+        assert_eq!(token.issued_at, 1786400000);
+        assert_eq!(out.name, "Jo Walker");
+        assert!(out.expires_at > crate::devices::proof::now());
+    }
+
+    #[test]
+    fn a_revoked_pass_carries_no_token() {
+        let live = held(&URL_SAFE_NO_PAD.encode([7u8; 64]));
+        assert!(scannable(&live).unwrap().is_some());
+
+        let revoked = wallet_pass::Model {
+            revoked_at: Some(chrono::Utc::now().into()),
+            ..live
+        };
+        assert_eq!(scannable(&revoked).unwrap(), None);
+    }
+
+    #[test]
+    fn a_corrupt_stored_signature_is_an_upstream_fault() {
+        assert!(matches!(
+            minted(held("not*base64")),
+            Err(AuthError::Upstream("pass_signature_corrupt"))
+        ));
+    }
+}
+
+#[cfg(test)]
+mod rotating_tests {
+    use super::*;
+    use p256::ecdsa::signature::Signer as _;
+    use p256::elliptic_curve::rand_core::OsRng;
+    use p256::elliptic_curve::sec1::ToEncodedPoint as _;
+
+    const NOW: i64 = 1786400000;
+
+    fn code(expires_at: i64, step: i64) -> Rotating {
+        Rotating {
+            andrew_id: "jw8".into(),
+            expires_at,
+            step,
+            signature: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn signed_code_round_trips() {
+        let key = p256::ecdsa::SigningKey::random(&mut OsRng);
+        let public = hex::encode(
+            p256::PublicKey::from(*key.verifying_key())
+                .to_encoded_point(false)
+                .as_bytes(),
+        );
+
+        let step = NOW / ROTATION_SECS;
+        let message = rotating_message("jw8", NOW + 3600, step);
+        let signature: p256::ecdsa::Signature = key.sign(message.as_bytes());
+        let raw = format!("{message}.{}", URL_SAFE_NO_PAD.encode(signature.to_bytes()));
+
+        let parsed = Rotating::parse(&raw).expect("code parses");
+        assert_eq!(parsed.andrew_id, "jw8");
+        assert_eq!(parsed.expires_at, NOW + 3600);
+        assert_eq!(parsed.step, step);
+        assert!(parsed.current(NOW).is_ok());
+
+        let device = DeviceKey::parse(&public).expect("key parses");
+        assert!(device.verifies(parsed.message().as_bytes(), &parsed.signature));
+        assert!(!device.verifies(
+            code(NOW + 3600, step + 1).message().as_bytes(),
+            &parsed.signature
+        ));
+
+        assert!(Token::parse(&raw).is_none());
+        assert!(Rotating::parse("Q1.jw8.1786400000.c2ln").is_none());
+    }
+
+    #[test]
+    fn steps_outside_the_skew_are_stale() {
+        let step = NOW / ROTATION_SECS;
+
+        assert!(code(NOW + 60, step - 1).current(NOW).is_ok());
+        assert!(code(NOW + 60, step + 1).current(NOW).is_ok());
+        assert!(matches!(
+            code(NOW + 60, step - 2).current(NOW),
+            Err(AuthError::Unauthorized("pass_token_stale"))
+        ));
+        assert!(matches!(
+            code(NOW + 60, step + 2).current(NOW),
+            Err(AuthError::Unauthorized("pass_token_stale"))
+        ));
+    }
+
+    #[test]
+    fn expiry_is_enforced_both_ways() {
+        let step = NOW / ROTATION_SECS;
+
+        assert!(code(NOW - EXPIRY_SKEW_SECS, step).current(NOW).is_ok());
+        assert!(matches!(
+            code(NOW - EXPIRY_SKEW_SECS - 1, step).current(NOW),
+            Err(AuthError::Unauthorized("pass_token_expired"))
+        ));
+        assert!(matches!(
+            code(NOW + ROTATING_LIFETIME_SECS + EXPIRY_SKEW_SECS + 1, step).current(NOW),
+            Err(AuthError::BadRequest("pass_token_lifetime"))
+        ));
+    }
+}
+
+#[cfg(test)]
+mod face_tests {
+    use super::*;
+
+    #[test]
+    fn change_messages_carry_the_difference() {
+        assert_eq!(moved(Some(20), 25, "coin", "coins"), "You earned 5 coins");
+        assert_eq!(moved(Some(25), 26, "coin", "coins"), "You earned 1 coin");
+        assert_eq!(moved(Some(26), 16, "coin", "coins"), "You spent 10 coins");
+        assert_eq!(moved(None, 16, "coin", "coins"), "You now have %@ coins");
+        assert_eq!(
+            moved(Some(16), 16, "coin", "coins"),
+            "You now have %@ coins"
+        );
+    }
+}
+
 #[cfg(test)]
 mod certificate_tests {
     use super::*;
@@ -0,0 +1,33 @@
+use std::sync::Arc;
+
+/// Delivers the empty APNs pushes that tell Wallet a pass changed. Wallet
+/// then asks the web service which serials changed and fetches them.
+pub trait Notifier: Send + Sync {
+    fn notify(&self, push_tokens: Vec<String>);
+}
+
+/// Drops every push; passes refresh only when the holder pulls to refresh.
+pub struct Silent;
+
+impl Notifier for Silent {
+    fn notify(&self, _push_tokens: Vec<String>) {}
+}
+
+/// Logs the pushes it would send, for local runs without APNs credentials.
+pub struct Logged;
+
+impl Notifier for Logged {
+    fn notify(&self, push_tokens: Vec<String>) {
+        for token in push_tokens {
+            eprintln!("passes: push {}", token.get(..12).unwrap_or(&token));
+        }
+    }
+}
+
+/// `PASS_PUSH=log` logs pushes; anything else keeps them silent.
+pub fn from_env() -> Arc<dyn Notifier> {
+    match crate::auth::env_opt("PASS_PUSH").as_deref() {
+        Some("log") => Arc::new(Logged),
+        _ => Arc::new(Silent),
+    }
+}
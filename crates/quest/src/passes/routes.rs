@@ -1,6 +1,6 @@
-use axum::extract::State;
 use axum::extract::rejection::JsonRejection;
-use axum::http::{HeaderName, HeaderValue, header};
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, HeaderName, HeaderValue, StatusCode, header};
 use axum::response::{IntoResponse, Response};
 use axum::{Extension, Json};
 use serde::{Deserialize, Serialize};
@@ -19,7 +19,13 @@ pub fn router(passes: Passes) -> OpenApiRouter {
     OpenApiRouter::new()
         .routes(routes!(challenge))
         .routes(routes!(issue))
+        .routes(routes!(google))
+        .routes(routes!(register_device, unregister_device))
+        .routes(routes!(updated_serials))
+        .routes(routes!(latest_pass))
+        .routes(routes!(wallet_log))
         .routes(routes!(token))
+        .routes(routes!(reissue))
         .with_state(passes)
 }
 
@@ -29,6 +35,12 @@ struct PassToken {
     andrew_id: String,
     name: String,
     issued_at: i64,
+    /// Put this in Q2 codes, and ask for a new one once it passes.
+    expires_at: i64,
+    /// Sign `Q2.<andrew_id>.<expires_at>.<step>` with the device key,
+    /// where `step` is unix time divided by this, and show
+    /// `<that message>.<base64url signature>` as the QR code.
+    rotation_secs: i64,
 }
 
 #[utoipa::path(
@@ -40,6 +52,7 @@ struct PassToken {
         (status = OK, body = PassToken),
         (status = BAD_REQUEST, body = AuthErrBody),
         (status = UNAUTHORIZED, body = AuthErrBody),
+        (status = FORBIDDEN, body = AuthErrBody),
         (status = SERVICE_UNAVAILABLE, body = AuthErrBody),
         (status = BAD_GATEWAY, body = AuthErrBody),
     ),
@@ -57,12 +70,51 @@ async fn token(
         .token(row.id, &user.andrew_id, &user.name, offered(&body)?)
         .await?;
 
-    Ok(Json(PassToken {
-        token: held.token,
-        andrew_id: held.andrew_id,
-        name: held.name,
-        issued_at: held.issued_at,
-    }))
+    Ok(Json(PassToken::from(held)))
+}
+
+impl From<super::Minted> for PassToken {
+    fn from(held: super::Minted) -> Self {
+        Self {
+            token: held.token,
+            andrew_id: held.andrew_id,
+            name: held.name,
+            issued_at: held.issued_at,
+            expires_at: held.expires_at,
+            rotation_secs: super::ROTATION_SECS,
+        }
+    }
+}
+
+/// Re-signs the pass from this device, even when the held one still
+/// verifies. This is how a pass revoked along with its device comes back.
+#[utoipa::path(
+    post,
+    path = "/passes/reissue",
+    tag = "passes",
+    request_body = IssueBody,
+    responses(
+        (status = OK, body = PassToken),
+        (status = BAD_REQUEST, body = AuthErrBody),
+        (status = UNAUTHORIZED, body = AuthErrBody),
+        (status = BAD_GATEWAY, body = AuthErrBody),
+    ),
+)]
+async fn reissue(
+    State(passes): State<Passes>,
+    Extension(users): Extension<Users>,
+    CurrentUser(user): CurrentUser,
+    body: Result<Json<IssueBody>, JsonRejection>,
+) -> Result<Json<PassToken>, AuthError> {
+    let Json(body) = body.map_err(|_| AuthError::BadRequest("pass_body_invalid"))?;
+    let offered = offered(&body)?.ok_or(AuthError::BadRequest("pass_signature_required"))?;
+
+    let row = users.row(&user).await?;
+    let held = passes
+        .reissue(row.id, &user.andrew_id, &user.name, offered)
+        .await?;
+
+    Ok(Json(PassToken::from(held)))
 }
 
 #[derive(Serialize, ToSchema)]
@@ -107,6 +159,7 @@ struct IssueBody {
         (status = OK, description = "An Apple Wallet pass", content_type = "application/vnd.apple.pkpass"),
         (status = BAD_REQUEST, body = AuthErrBody),
         (status = UNAUTHORIZED, body = AuthErrBody),
+        (status = FORBIDDEN, body = AuthErrBody),
         (status = SERVICE_UNAVAILABLE, body = AuthErrBody),
         (status = BAD_GATEWAY, body = AuthErrBody),
     ),
@@ -127,6 +180,53 @@ async fn issue(
     Ok(package(issued))
 }
 
+#[derive(Serialize, ToSchema)]
+struct GooglePass {
+    serial: String,
+    token: String,
+    /// Whether this call signed a new pass rather than reusing the held one.
+    fresh: bool,
+    /// Open this to add the pass to Google Wallet.
+    save_url: String,
+    jwt: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/passes/google",
+    tag = "passes",
+    request_body = IssueBody,
+    responses(
+        (status = OK, body = GooglePass),
+        (status = BAD_REQUEST, body = AuthErrBody),
+        (status = UNAUTHORIZED, body = AuthErrBody),
+        (status = FORBIDDEN, body = AuthErrBody),
+        (status = SERVICE_UNAVAILABLE, body = AuthErrBody),
+        (status = BAD_GATEWAY, body = AuthErrBody),
+    ),
+)]
+async fn google(
+    State(passes): State<Passes>,
+    Extension(users): Extension<Users>,
+    CurrentUser(user): CurrentUser,
+    body: Result<Json<IssueBody>, JsonRejection>,
+) -> Result<Json<GooglePass>, AuthError> {
+    let Json(body) = body.map_err(|_| AuthError::BadRequest("pass_body_invalid"))?;
+
+    let row = users.row(&user).await?;
+    let saved = passes
+        .google(row.id, &user.andrew_id, &user.name, offered(&body)?)
+        .await?;
+
+    Ok(Json(GooglePass {
+        serial: saved.serial,
+        token: saved.token,
+        fresh: saved.fresh,
+        save_url: saved.save_url,
+        jwt: saved.jwt,
+    }))
+}
+
 fn offered(body: &IssueBody) -> Result<Option<Signed>, AuthError> {
     match (body.issued_at, body.signature.as_deref()) {
         (Some(issued_at), Some(signature)) => Ok(Some(Signed {
@@ -172,3 +272,203 @@ fn package(issued: super::Issued) -> Response {
     )
         .into_response()
 }
+
+fn authorization(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+}
+
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct RegisterBody {
+    push_token: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/passkit/v1/devices/{device}/registrations/{pass_type}/{serial}",
+    tag = "passes",
+    params(
+        ("device" = String, Path, description = "Device library identifier"),
+        ("pass_type" = String, Path, description = "Pass type identifier"),
+        ("serial" = String, Path, description = "Pass serial number"),
+    ),
+    request_body = RegisterBody,
+    responses(
+        (status = CREATED, description = "Registered"),
+        (status = OK, description = "Already registered"),
+        (status = UNAUTHORIZED, body = AuthErrBody),
+        (status = NOT_FOUND, body = AuthErrBody),
+    ),
+)]
+async fn register_device(
+    State(passes): State<Passes>,
+    Path((device, pass_type, serial)): Path<(String, String, String)>,
+    headers: HeaderMap,
+    body: Result<Json<RegisterBody>, JsonRejection>,
+) -> Result<StatusCode, AuthError> {
+    let Json(body) = body.map_err(|_| AuthError::BadRequest("push_token_missing"))?;
+
+    let created = passes
+        .register(
+            &device,
+            &pass_type,
+            &serial,
+            &body.push_token,
+            authorization(&headers),
+        )
+        .await?;
+
+    Ok(if created {
+        StatusCode::CREATED
+    } else {
+        StatusCode::OK
+    })
+}
+
+#[utoipa::path(
+    delete,
+    path = "/passkit/v1/devices/{device}/registrations/{pass_type}/{serial}",
+    tag = "passes",
+    params(
+        ("device" = String, Path, description = "Device library identifier"),
+        ("pass_type" = String, Path, description = "Pass type identifier"),
+        ("serial" = String, Path, description = "Pass serial number"),
+    ),
+    responses(
+        (status = OK, description = "Unregistered"),
+        (status = UNAUTHORIZED, body = AuthErrBody),
+        (status = NOT_FOUND, body = AuthErrBody),
+    ),
+)]
+async fn unregister_device(
+    State(passes): State<Passes>,
+    Path((device, pass_type, serial)): Path<(String, String, String)>,
+    headers: HeaderMap,
+) -> Result<StatusCode, AuthError> {
+    passes
+        .unregister(&device, &pass_type, &serial, authorization(&headers))
+        .await?;
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize, utoipa::IntoParams)]
+#[serde(rename_all = "camelCase")]
+struct UpdatedQuery {
+    passes_updated_since: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct UpdatedSerials {
+    serial_numbers: Vec<String>,
+    last_updated: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/passkit/v1/devices/{device}/registrations/{pass_type}",
+    tag = "passes",
+    params(
+        ("device" = String, Path, description = "Device library identifier"),
+        ("pass_type" = String, Path, description = "Pass type identifier"),
+        UpdatedQuery,
+    ),
+    responses(
+        (status = OK, body = UpdatedSerials),
+        (status = NO_CONTENT, description = "Nothing changed since the tag"),
+        (status = NOT_FOUND, body = AuthErrBody),
+    ),
+)]
+async fn updated_serials(
+    State(passes): State<Passes>,
+    Path((device, pass_type)): Path<(String, String)>,
+    Query(query): Query<UpdatedQuery>,
+) -> Result<Response, AuthError> {
+    let changed = passes
+        .changed_since(&device, &pass_type, query.passes_updated_since.as_deref())
+        .await?;
+
+    Ok(match changed {
+        Some(changed) => Json(UpdatedSerials {
+            serial_numbers: changed.serials,
+            last_updated: changed.tag,
+        })
+        .into_response(),
+        None => StatusCode::NO_CONTENT.into_response(),
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/passkit/v1/passes/{pass_type}/{serial}",
+    tag = "passes",
+    params(
+        ("pass_type" = String, Path, description = "Pass type identifier"),
+        ("serial" = String, Path, description = "Pass serial number"),
+    ),
+    responses(
+        (status = OK, description = "The current pass", content_type = "application/vnd.apple.pkpass"),
+        (status = UNAUTHORIZED, body = AuthErrBody),
+        (status = NOT_FOUND, body = AuthErrBody),
+    ),
+)]
+async fn latest_pass(
+    State(passes): State<Passes>,
+    Path((pass_type, serial)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Result<Response, AuthError> {
+    let issued = passes
+        .latest(&pass_type, &serial, authorization(&headers))
+        .await?;
+
+    Ok(package(issued))
+}
+
+#[derive(Deserialize, ToSchema)]
+struct LogBody {
+    #[serde(default)]
+    logs: Vec<String>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/passkit/v1/log",
+    tag = "passes",
+    request_body = LogBody,
+    responses((status = OK, description = "Logged")),
+)]
+async fn wallet_log(body: Result<Json<LogBody>, JsonRejection>) -> StatusCode {
+    if let Ok(Json(body)) = body {
+        for line in body.logs {
+            eprintln!("passes: wallet {line}");
+        }
+    }
+
+    StatusCode::OK
+}
+
+#[cfg(test)]
+mod header_tests {
+    use super::*;
+
+    #[test]
+    fn authorization_reads_the_raw_header() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(authorization(&headers), None);
+
+        headers.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_static("ApplePass abc"),
+        );
+        assert_eq!(authorization(&headers), Some("ApplePass abc"));
+
+        headers.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_bytes(b"ApplePass \xff").unwrap(),
+        );
+        assert_eq!(authorization(&headers), None);
+    }
+}
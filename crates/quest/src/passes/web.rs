@@ -0,0 +1,293 @@
+use entity::{pass_registration, wallet_pass};
+use sea_orm::prelude::{DateTimeWithTimeZone, Uuid};
+use sea_orm::{
+    ActiveValue, ColumnTrait, EntityTrait, JoinType, QueryFilter, QuerySelect, RelationTrait,
+    sea_query,
+};
+use subtle::ConstantTimeEq;
+
+use super::{Issued, Passes, Signing, db_down, rebuild};
+use crate::auth::AuthError;
+
+const SCHEME: &str = "ApplePass ";
+
+/// Serials a device should fetch again, and the tag to send next time.
+pub struct Changed {
+    pub serials: Vec<String>,
+    pub tag: String,
+}
+
+fn tag_of(at: DateTimeWithTimeZone) -> i64 {
+    at.timestamp_micros()
+}
+
+/// The token in an `ApplePass` authorization header.
+fn presented(authorization: Option<&str>) -> Result<&str, AuthError> {
+    authorization
+        .and_then(|value| value.strip_prefix(SCHEME))
+        .map(str::trim)
+        .ok_or(AuthError::Unauthorized("pass_auth_missing"))
+}
+
+/// Which of the passes `held` on a device changed after the `since` tag.
+fn changed(held: Vec<wallet_pass::Model>, since: Option<&str>) -> Option<Changed> {
+    // An unreadable tag is treated as none, so the device refetches all.
+    let since = since
+        .and_then(|tag| tag.parse::<i64>().ok())
+        .unwrap_or(i64::MIN);
+
+    let last = held.iter().map(|row| tag_of(row.updated_at)).max()?;
+
+    let serials: Vec<String> = held
+        .into_iter()
+        .filter(|row| tag_of(row.updated_at) > since)
+        .map(|row| row.serial)
+        .collect();
+
+    if serials.is_empty() {
+        return None;
+    }
+
+    Some(Changed {
+        serials,
+        tag: last.to_string(),
+    })
+}
+
+impl Passes {
+    fn web_signing(&self, pass_type: &str) -> Result<&Signing, AuthError> {
+        let signing = self
+            .signing
+            .as_ref()
+            .filter(|signing| signing.web_service_url.is_some())
+            .ok_or(AuthError::NotConfigured)?;
+
+        if signing.pass_type_identifier != pass_type {
+            return Err(AuthError::NotFound("pass_type_unknown"));
+        }
+
+        Ok(signing)
+    }
+
+    /// The pass an `ApplePass` authorization header unlocks.
+    async fn authorize(
+        &self,
+        serial: &str,
+        authorization: Option<&str>,
+    ) -> Result<wallet_pass::Model, AuthError> {
+        let presented = presented(authorization)?;
+
+        let row = wallet_pass::Entity::find()
+            .filter(wallet_pass::Column::Serial.eq(serial))
+            .one(&self.db)
+            .await
+            .map_err(db_down)?
+            .ok_or(AuthError::Unauthorized("pass_auth"))?;
+
+        if !bool::from(row.auth_token.as_bytes().ct_eq(presented.as_bytes())) {
+            return Err(AuthError::Unauthorized("pass_auth"));
+        }
+
+        Ok(row)
+    }
+
+    /// Records that a device holds the pass; true when it is new there.
+    pub async fn register(
+        &self,
+        device: &str,
+        pass_type: &str,
+        serial: &str,
+        push_token: &str,
+        authorization: Option<&str>,
+    ) -> Result<bool, AuthError> {
+        self.web_signing(pass_type)?;
+        let row = self.authorize(serial, authorization).await?;
+
+        let push_token = push_token.trim();
+        if push_token.is_empty() {
+            return Err(AuthError::BadRequest("push_token_missing"));
+        }
+
+        let known = pass_registration::Entity::find_by_id((device.to_owned(), row.user_id))
+            .one(&self.db)
+            .await
+            .map_err(db_down)?;
+
+        let registration = pass_registration::ActiveModel {
+            device_library_id: ActiveValue::Set(device.to_owned()),
+            user_id: ActiveValue::Set(row.user_id),
+            push_token: ActiveValue::Set(push_token.to_owned()),
+            ..Default::default()
+        };
+
+        pass_registration::Entity::insert(registration)
+            .on_conflict(
+                sea_query::OnConflict::columns([
+                    pass_registration::Column::DeviceLibraryId,
+                    pass_registration::Column::UserId,
+                ])
+                .update_column(pass_registration::Column::PushToken)
+                .to_owned(),
+            )
+            .exec_without_returning(&self.db)
+            .await
+            .map_err(db_down)?;
+
+        Ok(known.is_none())
+    }
+
+    pub async fn unregister(
+        &self,
+        device: &str,
+        pass_type: &str,
+        serial: &str,
+        authorization: Option<&str>,
+    ) -> Result<(), AuthError> {
+        self.web_signing(pass_type)?;
+        let row = self.authorize(serial, authorization).await?;
+
+        pass_registration::Entity::delete_by_id((device.to_owned(), row.user_id))
+            .exec(&self.db)
+            .await
+            .map_err(db_down)?;
+
+        Ok(())
+    }
+
+    /// Passes registered on `device` that changed after `since`, or `None`
+    /// when nothing did.
+    pub async fn changed_since(
+        &self,
+        device: &str,
+        pass_type: &str,
+        since: Option<&str>,
+    ) -> Result<Option<Changed>, AuthError> {
+        self.web_signing(pass_type)?;
+
+        let held = wallet_pass::Entity::find()
+            .join(
+                JoinType::InnerJoin,
+                wallet_pass::Relation::PassRegistration.def(),
+            )
+            .filter(pass_registration::Column::DeviceLibraryId.eq(device))
+            .all(&self.db)
+            .await
+            .map_err(db_down)?;
+
+        Ok(changed(held, since))
+    }
+
+    /// The current build of a pass. Balances and rank move without
+    /// touching the row, so it is rebuilt on every fetch. A revoked pass
+    /// comes back voided, with no code to scan.
+    pub async fn latest(
+        &self,
+        pass_type: &str,
+        serial: &str,
+        authorization: Option<&str>,
+    ) -> Result<Issued, AuthError> {
+        let signing = self.web_signing(pass_type)?;
+        let row = self.authorize(serial, authorization).await?;
+        let face = self.face(&row).await?;
+        let issued = rebuild(signing, &row, &face)?;
+        self.shown(&row, &face).await?;
+
+        Ok(issued)
+    }
+
+    /// Marks `user`'s pass changed and asks every device holding it to
+    /// fetch it again.
+    pub async fn push(&self, user: Uuid) -> Result<(), AuthError> {
+        wallet_pass::Entity::update_many()
+            .col_expr(
+                wallet_pass::Column::UpdatedAt,
+                sea_query::Expr::current_timestamp(),
+            )
+            .filter(wallet_pass::Column::UserId.eq(user))
+            .exec(&self.db)
+            .await
+            .map_err(db_down)?;
+
+        let tokens = pass_registration::Entity::find()
+            .select_only()
+            .column(pass_registration::Column::PushToken)
+            .filter(pass_registration::Column::UserId.eq(user))
+            .into_tuple::<String>()
+            .all(&self.db)
+            .await
+            .map_err(db_down)?;
+
+        if !tokens.is_empty() {
+            self.notifier.notify(tokens);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod web_tests {
+    use chrono::{Duration, Utc};
+
+    use super::*;
+
+    fn pass(serial: &str, updated_at: DateTimeWithTimeZone) -> wallet_pass::Model {
+        wallet_pass::Model {
+            user_id: Uuid::new_v4(),
+            serial: serial.to_owned(),
+            andrew_id: "scotty".to_owned(),
+            name: "Scotty Dog".to_owned(),
+            issued_at: 0,
+            public_key: String::new(),
+            signature: String::new(),
+            created_at: updated_at,
+            auth_token: "token".to_owned(),
+            updated_at,
+            shown_scottycoins: None,
+            shown_thistlestones: None,
+            revoked_at: None,
+        }
+    }
+
+    #[test]
+    fn the_applepass_scheme_is_required() {
+        assert_eq!(presented(Some("ApplePass  abc ")).unwrap(), "abc");
+        for header in [None, Some("Bearer abc"), Some("applepass abc")] {
+            assert!(matches!(
+                presented(header),
+                Err(AuthError::Unauthorized("pass_auth_missing"))
+            ));
+        }
+    }
+
+    #[test]
+    fn tags_keep_microseconds() {
+        let at: DateTimeWithTimeZone = "2026-09-01T12:00:00.000001+00:00".parse().unwrap();
+        let later = at + Duration::microseconds(1);
+        assert_eq!(tag_of(later) - tag_of(at), 1);
+    }
+
+    #[test]
+    fn only_passes_newer_than_the_tag_are_listed() {
+        let old: DateTimeWithTimeZone = (Utc::now() - Duration::hours(1)).into();
+        let new: DateTimeWithTimeZone = Utc::now().into();
+        let held = || vec![pass("a", old), pass("b", new)];
+
+        let since = tag_of(old).to_string();
+        let found = changed(held(), Some(&since)).expect("b changed");
+        assert_eq!(found.serials, ["b"]);
+        assert_eq!(found.tag, tag_of(new).to_string());
+
+        assert!(changed(held(), Some(&tag_of(new).to_string())).is_none());
+    }
+
+    #[test]
+    fn a_missing_or_unreadable_tag_lists_everything() {
+        let at: DateTimeWithTimeZone = Utc::now().into();
+        for since in [None, Some("yesterday")] {
+            let found = changed(vec![pass("a", at), pass("b", at)], since).unwrap();
+            assert_eq!(found.serials, ["a", "b"]);
+        }
+        assert!(changed(Vec::new(), None).is_none());
+    }
+}
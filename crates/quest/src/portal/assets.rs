@@ -18,6 +18,10 @@ const ALGORITHM: &str = "AWS4-HMAC-SHA256";
 
 const KINDS: &[&str] = &["uploads", "items", "challenges", "mascots"];
 
+/// Player photos live under this prefix in the private bucket, apart from
+/// every public kind, and are never recorded in the asset listing.
+pub const PRIVATE_PREFIX: &str = "submissions";
+
 const TYPES: &[(&str, &str)] = &[
     ("image/png", "png"),
     ("image/jpeg", "jpg"),
@@ -82,10 +86,18 @@ pub fn known_kind(kind: &str) -> bool {
 #[derive(Clone)]
 pub struct Assets {
     db: DatabaseConnection,
-    http: reqwest::Client,
-    endpoint: String,
+    s3: S3,
     bucket: String,
+    /// Where player photos go; absent unless a bucket apart from the
+    /// public one is set, which leaves photo uploads unconfigured.
+    private: Option<String>,
     public: String,
+}
+
+#[derive(Clone)]
+struct S3 {
+    http: reqwest::Client,
+    endpoint: String,
     key_id: String,
     secret: String,
 }
@@ -114,33 +126,29 @@ impl Assets {
             return Err(ConfigError(format!("{name} must be set")));
         }
 
-        let http = reqwest::Client::builder()
-            .timeout(Duration::from_secs(30))
-            .build()
-            .map_err(|err| ConfigError(format!("failed to build the CDN HTTP client: {err}")))?;
+        let bucket = env_required("CDN_S3_BUCKET")?;
 
         Ok(Self {
             db,
-            http,
-            endpoint: env_required("CDN_S3_ENDPOINT")?
-                .trim_end_matches('/')
-                .to_owned(),
-            bucket: env_required("CDN_S3_BUCKET")?,
+            s3: S3::from_env()?,
+            private: private_bucket(&bucket, env_opt("CDN_PRIVATE_BUCKET")),
+            bucket,
             public: env_required("CDN_PUBLIC_URL")?,
-            key_id: env_required("CDN_ACCESS_KEY_ID")?,
-            secret: env_required("CDN_SECRET_ACCESS_KEY")?,
         })
     }
 
     pub fn unconfigured(db: DatabaseConnection) -> Self {
         Self {
             db,
-            http: reqwest::Client::new(),
-            endpoint: String::new(),
+            s3: S3 {
+                http: reqwest::Client::new(),
+                endpoint: String::new(),
+                key_id: String::new(),
+                secret: String::new(),
+            },
             bucket: String::new(),
+            private: None,
             public: String::new(),
-            key_id: String::new(),
-            secret: String::new(),
         }
     }
 
@@ -152,91 +160,6 @@ impl Assets {
         format!("{}{key}", self.public)
     }
 
-    async fn signed(
-        &self,
-        method: reqwest::Method,
-        key: &str,
-        mime: Option<&str>,
-        body: Bytes,
-    ) -> Result<(), AssetError> {
-        let url = format!("{}/{}/{key}", self.endpoint, self.bucket);
-        let host = reqwest::Url::parse(&url)
-            .ok()
-            .and_then(|parsed| {
-                let host = parsed.host_str()?.to_owned();
-                Some(match parsed.port() {
-                    Some(port) => format!("{host}:{port}"),
-                    None => host,
-                })
-            })
-            .ok_or(AssetError::Rejected("cdn_endpoint_invalid"))?;
-
-        let payload = hex::encode(Sha256::digest(&body));
-        let now = chrono::Utc::now();
-        let stamp = now.format("%Y%m%dT%H%M%SZ").to_string();
-        let day = now.format("%Y%m%d").to_string();
-
-        let (signed, typed) = match mime {
-            Some(mime) => (
-                "content-type;host;x-amz-content-sha256;x-amz-date",
-                format!("content-type:{mime}\n"),
-            ),
-            None => ("host;x-amz-content-sha256;x-amz-date", String::new()),
-        };
-
-        let canonical = format!(
-            "{method}\n/{bucket}/{key}\n\n{typed}host:{host}\n\
-             x-amz-content-sha256:{payload}\nx-amz-date:{stamp}\n\n{signed}\n{payload}",
-            bucket = self.bucket,
-        );
-
-        let scope = format!("{day}/{REGION}/{SERVICE}/aws4_request");
-        let to_sign = format!(
-            "{ALGORITHM}\n{stamp}\n{scope}\n{}",
-            hex::encode(Sha256::digest(canonical.as_bytes()))
-        );
-
-        let mut signing = sign(format!("AWS4{}", self.secret).as_bytes(), day.as_bytes());
-        signing = sign(&signing, REGION.as_bytes());
-        signing = sign(&signing, SERVICE.as_bytes());
-        signing = sign(&signing, b"aws4_request");
-
-        let signature = hex::encode(sign(&signing, to_sign.as_bytes()));
-        let authorization = format!(
-            "{ALGORITHM} Credential={}/{scope}, SignedHeaders={signed}, Signature={signature}",
-            self.key_id,
-        );
-
-        let mut request = self
-            .http
-            .request(method, &url)
-            .header("x-amz-content-sha256", &payload)
-            .header("x-amz-date", &stamp)
-            .header(reqwest::header::AUTHORIZATION, authorization);
-
-        if let Some(mime) = mime {
-            request = request.header(reqwest::header::CONTENT_TYPE, mime);
-        }
-
-        let response = request
-            .body(body)
-            .send()
-            .await
-            .map_err(|err| AssetError::Upstream(format!("CDN unreachable: {err}")))?;
-
-        if !response.status().is_success() && response.status() != reqwest::StatusCode::NOT_FOUND {
-            let status = response.status();
-            let detail = response.text().await.unwrap_or_default();
-
-            return Err(AssetError::Upstream(format!(
-                "CDN refused the request ({status}): {}",
-                detail.trim()
-            )));
-        }
-
-        Ok(())
-    }
-
     pub async fn put(
         &self,
         kind: &str,
@@ -264,7 +187,8 @@ impl Assets {
         let key = format!("{kind}/{}.{extension}", uuid::Uuid::new_v4());
         let bytes = body.len() as i64;
 
-        self.signed(reqwest::Method::PUT, &key, Some(mime), body)
+        self.s3
+            .signed(reqwest::Method::PUT, &self.bucket, &key, Some(mime), body)
             .await?;
 
         let stored = Stored {
@@ -325,7 +249,14 @@ impl Assets {
             .map_err(|err| AssetError::Upstream(format!("could not read the asset: {err}")))?
             .ok_or(AssetError::Rejected("asset_unknown"))?;
 
-        self.signed(reqwest::Method::DELETE, &known.key, None, Bytes::new())
+        self.s3
+            .signed(
+                reqwest::Method::DELETE,
+                &self.bucket,
+                &known.key,
+                None,
+                Bytes::new(),
+            )
             .await?;
 
         entity::asset::Entity::delete_by_id(&known.key)
@@ -335,6 +266,183 @@ impl Assets {
 
         Ok(())
     }
+
+    /// Stores a player photo in the private bucket under a random key and
+    /// returns the key. Nothing is written to the asset table, so the photo
+    /// never shows up in the portal library or under `CDN_PUBLIC_URL` links.
+    pub async fn put_private(&self, content_type: &str, body: Bytes) -> Result<String, AssetError> {
+        if body.is_empty() {
+            return Err(AssetError::Rejected("asset_empty"));
+        }
+        if body.len() > MAX_BYTES {
+            return Err(AssetError::Rejected("asset_too_large"));
+        }
+
+        let (mime, extension) = classify(content_type, None);
+
+        let Some(bucket) = self.private.as_deref() else {
+            return Err(AssetError::Unconfigured);
+        };
+
+        let key = private_key(&extension);
+        self.s3
+            .signed(reqwest::Method::PUT, bucket, &key, Some(mime), body)
+            .await?;
+
+        Ok(key)
+    }
+
+    /// Reads a photo stored by [`Assets::put_private`], with its content type.
+    pub async fn get_private(&self, key: &str) -> Result<(String, Bytes), AssetError> {
+        let Some(bucket) = self.private.as_deref() else {
+            return Err(AssetError::Unconfigured);
+        };
+        if !key.starts_with(PRIVATE_PREFIX) {
+            return Err(AssetError::Rejected("asset_unknown"));
+        }
+
+        self.s3.fetch(bucket, key).await
+    }
+}
+
+impl S3 {
+    fn from_env() -> Result<Self, ConfigError> {
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()
+            .map_err(|err| ConfigError(format!("failed to build the CDN HTTP client: {err}")))?;
+
+        Ok(Self {
+            http,
+            endpoint: env_required("CDN_S3_ENDPOINT")?
+                .trim_end_matches('/')
+                .to_owned(),
+            key_id: env_required("CDN_ACCESS_KEY_ID")?,
+            secret: env_required("CDN_SECRET_ACCESS_KEY")?,
+        })
+    }
+
+    async fn fetch(&self, bucket: &str, key: &str) -> Result<(String, Bytes), AssetError> {
+        let response = self
+            .signed(reqwest::Method::GET, bucket, key, None, Bytes::new())
+            .await?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(AssetError::Rejected("asset_unknown"));
+        }
+
+        let mime = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or(GENERIC)
+            .to_owned();
+
+        let bytes = response
+            .bytes()
+            .await
+            .map_err(|err| AssetError::Upstream(format!("CDN read failed: {err}")))?;
+
+        Ok((mime, bytes))
+    }
+
+    async fn signed(
+        &self,
+        method: reqwest::Method,
+        bucket: &str,
+        key: &str,
+        mime: Option<&str>,
+        body: Bytes,
+    ) -> Result<reqwest::Response, AssetError> {
+        let url = format!("{}/{bucket}/{key}", self.endpoint);
+        let host = reqwest::Url::parse(&url)
+            .ok()
+            .and_then(|parsed| {
+                let host = parsed.host_str()?.to_owned();
+                Some(match parsed.port() {
+                    Some(port) => format!("{host}:{port}"),
+                    None => host,
+                })
+            })
+            .ok_or(AssetError::Rejected("cdn_endpoint_invalid"))?;
+
+        let payload = hex::encode(Sha256::digest(&body));
+        let now = chrono::Utc::now();
+        let stamp = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let day = now.format("%Y%m%d").to_string();
+
+        let (signed, typed) = match mime {
+            Some(mime) => (
+                "content-type;host;x-amz-content-sha256;x-amz-date",
+                format!("content-type:{mime}\n"),
+            ),
+            None => ("host;x-amz-content-sha256;x-amz-date", String::new()),
+        };
+
+        let canonical = format!(
+            "{method}\n/{bucket}/{key}\n\n{typed}host:{host}\n\
+             x-amz-content-sha256:{payload}\nx-amz-date:{stamp}\n\n{signed}\n{payload}",
+        );
+
+        let scope = format!("{day}/{REGION}/{SERVICE}/aws4_request");
+        let to_sign = format!(
+            "{ALGORITHM}\n{stamp}\n{scope}\n{}",
+            hex::encode(Sha256::digest(canonical.as_bytes()))
+        );
+
+        let mut signing = sign(format!("AWS4{}", self.secret).as_bytes(), day.as_bytes());
+        signing = sign(&signing, REGION.as_bytes());
+        signing = sign(&signing, SERVICE.as_bytes());
+        signing = sign(&signing, b"aws4_request");
+
+        let signature = hex::encode(sign(&signing, to_sign.as_bytes()));
+        let authorization = format!(
+            "{ALGORITHM} Credential={}/{scope}, SignedHeaders={signed}, Signature={signature}",
+            self.key_id,
+        );
+
+        let mut request = self
+            .http
+            .request(method, &url)
+            .header("x-amz-content-sha256", &payload)
+            .header("x-amz-date", &stamp)
+            .header(reqwest::header::AUTHORIZATION, authorization);
+
+        if let Some(mime) = mime {
+            request = request.header(reqwest::header::CONTENT_TYPE, mime);
+        }
+
+        let response = request
+            .body(body)
+            .send()
+            .await
+            .map_err(|err| AssetError::Upstream(format!("CDN unreachable: {err}")))?;
+
+        if !response.status().is_success() && response.status() != reqwest::StatusCode::NOT_FOUND {
+            let status = response.status();
+            let detail = response.text().await.unwrap_or_default();
+
+            return Err(AssetError::Upstream(format!(
+                "CDN refused the request ({status}): {}",
+                detail.trim()
+            )));
+        }
+
+        Ok(response)
+    }
+}
+
+// 256 random bits, so a key can't be guessed from another one.
+fn private_key(extension: &str) -> String {
+    format!(
+        "{PRIVATE_PREFIX}/{}.{extension}",
+        hex::encode(rand::random::<[u8; 32]>())
+    )
+}
+
+/// The photo bucket, refused when unset or when it is the public bucket.
+fn private_bucket(public: &str, private: Option<String>) -> Option<String> {
+    private.filter(|bucket| bucket != public)
 }
 
 fn sign(key: &[u8], message: &[u8]) -> Vec<u8> {
@@ -342,3 +450,70 @@ fn sign(key: &[u8], message: &[u8]) -> Vec<u8> {
     mac.update(message);
     mac.finalize().into_bytes().to_vec()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn submissions_are_not_a_public_kind() {
+        assert!(!known_kind(PRIVATE_PREFIX));
+        assert!(!KIND_LIST.contains(&PRIVATE_PREFIX));
+    }
+
+    #[test]
+    fn private_keys_are_random_and_prefixed() {
+        let first = private_key("jpg");
+        let second = private_key("jpg");
+
+        assert_ne!(first, second);
+        assert!(first.starts_with("submissions/"));
+        assert!(first.ends_with(".jpg"));
+        assert_eq!(first.len(), "submissions/".len() + 64 + ".jpg".len());
+    }
+
+    #[test]
+    fn photos_never_share_the_public_bucket() {
+        assert_eq!(private_bucket("cdn", None), None);
+        assert_eq!(private_bucket("cdn", Some("cdn".to_owned())), None);
+        assert_eq!(
+            private_bucket("cdn", Some("cdn-private".to_owned())),
+            Some("cdn-private".to_owned())
+        );
+    }
+
+    /// Round-trips a photo through a local Garage or MinIO stand-in. Needs the
+    /// `CDN_*` variables pointing at it, so it only runs with `--ignored`.
+    #[tokio::test]
+    #[ignore = "needs an S3 stand-in configured through CDN_*"]
+    async fn private_photos_round_trip() {
+        let s3 = S3::from_env().expect("CDN_* is set");
+        let bucket = env_opt("CDN_PRIVATE_BUCKET").expect("CDN_PRIVATE_BUCKET is set");
+
+        let key = private_key("jpg");
+        let photo = Bytes::from_static(b"\xff\xd8\xff\xe0 not really a jpeg");
+
+        s3.signed(
+            reqwest::Method::PUT,
+            &bucket,
+            &key,
+            Some("image/jpeg"),
+            photo.clone(),
+        )
+        .await
+        .expect("upload succeeds");
+
+        let (mime, read) = s3.fetch(&bucket, &key).await.expect("photo reads back");
+        assert_eq!(mime, "image/jpeg");
+        assert_eq!(read, photo);
+
+        s3.signed(reqwest::Method::DELETE, &bucket, &key, None, Bytes::new())
+            .await
+            .expect("cleanup succeeds");
+
+        assert!(matches!(
+            s3.fetch(&bucket, &key).await,
+            Err(AssetError::Rejected("asset_unknown"))
+        ));
+    }
+}
@@ -0,0 +1,224 @@
+use sea_orm::prelude::{DateTimeWithTimeZone, Uuid};
+use sea_orm::{DbBackend, FromQueryResult, Statement};
+use serde_json::{Value as Json, json};
+
+use super::{Portal, PortalError, db_down};
+
+const PLACEMENTS: &str = r#"
+SELECT
+    c."id" AS "id",
+    c."name" AS "name",
+    c."category" AS "category",
+    c."secret" AS "secret",
+    c."open_from" AS "open_from",
+    ST_Y(c."location"::geometry) AS "lat",
+    ST_X(c."location"::geometry) AS "lon",
+    string_agg(cc."card_id", ',' ORDER BY cc."card_id") AS "cards"
+FROM "challenge" c
+LEFT JOIN "challenge_card" cc
+    ON cc."challenge_id" = c."id"
+   AND cc."retired_at" IS NULL
+WHERE $1 OR NOT c."secret"
+GROUP BY c."id"
+ORDER BY c."category", c."name"
+"#;
+
+#[derive(FromQueryResult)]
+pub struct Placed {
+    pub id: Uuid,
+    pub name: String,
+    pub category: String,
+    pub secret: bool,
+    pub open_from: DateTimeWithTimeZone,
+    pub lat: Option<f64>,
+    pub lon: Option<f64>,
+    cards: Option<String>,
+}
+
+impl Placed {
+    pub fn cards(&self) -> Vec<&str> {
+        self.cards
+            .as_deref()
+            .map(|cards| cards.split(',').collect())
+            .unwrap_or_default()
+    }
+
+    fn at(&self) -> Option<(f64, f64)> {
+        self.lon.zip(self.lat)
+    }
+}
+
+impl Portal {
+    /// Every challenge with its active cards, secret ones only when asked.
+    pub async fn placements(&self, secrets: bool) -> Result<Vec<Placed>, PortalError> {
+        Placed::find_by_statement(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            PLACEMENTS,
+            [secrets.into()],
+        ))
+        .all(&self.db)
+        .await
+        .map_err(db_down)
+    }
+}
+
+/// A FeatureCollection; unplaced challenges carry a null geometry.
+pub fn geojson(placed: &[Placed]) -> Json {
+    let features: Vec<Json> = placed
+        .iter()
+        .map(|row| {
+            json!({
+                "type": "Feature",
+                "id": row.id.to_string(),
+                "geometry": row.at().map(|(lon, lat)| json!({
+                    "type": "Point",
+                    "coordinates": [lon, lat],
+                })),
+                "properties": {
+                    "name": row.name,
+                    "category": row.category,
+                    "secret": row.secret,
+                    "open_from": row.open_from.to_rfc3339(),
+                    "cards": row.cards(),
+                },
+            })
+        })
+        .collect();
+
+    json!({
+        "type": "FeatureCollection",
+        "features": features,
+    })
+}
+
+/// A KML document with one folder per category, for Google My Maps.
+pub fn kml(placed: &[Placed]) -> String {
+    let mut out = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+        "<kml xmlns=\"http://www.opengis.net/kml/2.2\">\n",
+        "<Document>\n",
+        "<name>Quest challenges</name>\n",
+    ));
+
+    let mut folder: Option<&str> = None;
+    for row in placed {
+        if folder != Some(row.category.as_str()) {
+            if folder.is_some() {
+                out.push_str("</Folder>\n");
+            }
+            out.push_str(&format!(
+                "<Folder>\n<name>{}</name>\n",
+                escape(&row.category)
+            ));
+            folder = Some(&row.category);
+        }
+
+        out.push_str(&format!(
+            "<Placemark id=\"{}\">\n<name>{}</name>\n<ExtendedData>\n",
+            row.id,
+            escape(&row.name),
+        ));
+        for (key, value) in [
+            ("category", row.category.clone()),
+            ("secret", row.secret.to_string()),
+            ("open_from", row.open_from.to_rfc3339()),
+            ("cards", row.cards().join(" ")),
+        ] {
+            out.push_str(&format!(
+                "<Data name=\"{key}\"><value>{}</value></Data>\n",
+                escape(&value)
+            ));
+        }
+        out.push_str("</ExtendedData>\n");
+
+        if let Some((lon, lat)) = row.at() {
+            out.push_str(&format!(
+                "<Point><coordinates>{lon},{lat}</coordinates></Point>\n"
+            ));
+        }
+        out.push_str("</Placemark>\n");
+    }
+
+    if folder.is_some() {
+        out.push_str("</Folder>\n");
+    }
+    out.push_str("</Document>\n</kml>\n");
+    out
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            _ => out.push(ch),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn placed(name: &str, category: &str, at: Option<(f64, f64)>, cards: Option<&str>) -> Placed {
+        Placed {
+            id: Uuid::from_u128(7),
+            name: name.to_owned(),
+            category: category.to_owned(),
+            secret: false,
+            open_from: "2026-08-20T12:00:00+00:00".parse().unwrap(),
+            lat: at.map(|(lat, _)| lat),
+            lon: at.map(|(_, lon)| lon),
+            cards: cards.map(str::to_owned),
+        }
+    }
+
+    #[test]
+    fn geojson_puts_longitude_first() {
+        let rows = [
+            placed(
+                "Fence",
+                "landmarks",
+                Some((40.4433, -79.9436)),
+                Some("04A1,04B2"),
+            ),
+            placed("Nowhere", "landmarks", None, None),
+        ];
+        let out = geojson(&rows);
+
+        assert_eq!(out["type"], "FeatureCollection");
+        let placed = &out["features"][0];
+        assert_eq!(
+            placed["geometry"]["coordinates"],
+            json!([-79.9436, 40.4433])
+        );
+        assert_eq!(placed["properties"]["cards"], json!(["04A1", "04B2"]));
+        assert!(out["features"][1]["geometry"].is_null());
+    }
+
+    #[test]
+    fn kml_groups_by_category_and_escapes_names() {
+        let rows = [
+            placed(
+                "Tom & Jerry's <stop>",
+                "landmarks",
+                Some((40.0, -80.0)),
+                None,
+            ),
+            placed("Gym", "landmarks", None, None),
+            placed("Quad", "outdoors", None, None),
+        ];
+        let out = kml(&rows);
+
+        assert_eq!(out.matches("<Folder>").count(), 2);
+        assert_eq!(out.matches("</Folder>").count(), 2);
+        assert!(out.contains("<name>Tom &amp; Jerry&apos;s &lt;stop&gt;</name>"));
+        assert!(out.contains("<coordinates>-80,40</coordinates>"));
+        assert_eq!(out.matches("<Point>").count(), 1);
+    }
+}
@@ -1,5 +1,7 @@
 pub mod activity;
 pub mod assets;
+pub mod export;
+pub mod reports;
 pub mod routes;
 pub mod script;
 pub mod serve;
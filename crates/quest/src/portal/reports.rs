@@ -0,0 +1,394 @@
+use std::io;
+
+use axum::body::{Body, Bytes};
+use futures_util::StreamExt;
+use sea_orm::prelude::{Date, DateTimeWithTimeZone};
+use sea_orm::{DatabaseConnection, DbBackend, FromQueryResult, Statement};
+use tokio::sync::mpsc;
+
+use super::Portal;
+
+/// Rows written between sends to the client.
+const CHUNK_ROWS: usize = 500;
+
+// Every purchase, one line each, with its picks folded into one cell.
+const ORDERS: &str = r#"
+SELECT p."purchase_id",
+       p."purchased_at",
+       u."andrew_id",
+       COALESCE(w."name", u."andrew_id") AS "name",
+       u."dorm"::text                    AS "dorm",
+       i."name"                          AS "item",
+       COALESCE(picks."summary", '')     AS "options",
+       p."quantity",
+       p."unit_cost",
+       (p."quantity" * p."unit_cost")::BIGINT AS "spent",
+       cr."code"                         AS "coupon",
+       p."ticket"::text                  AS "ticket",
+       l."name"                          AS "pickup_location",
+       s."starts_at"                     AS "pickup_starts_at",
+       p."delivered_at",
+       p."delivered_by"
+FROM "purchases" p
+JOIN "users" u ON u."id" = p."user_id"
+JOIN "items" i ON i."id" = p."item_id"
+LEFT JOIN "wallet_pass" w ON w."user_id" = u."id"
+LEFT JOIN "coupon_redemption" cr ON cr."purchase_id" = p."purchase_id"
+LEFT JOIN "pickup_slot" s ON s."id" = p."pickup_slot_id"
+LEFT JOIN "pickup_location" l ON l."id" = s."location_id"
+LEFT JOIN LATERAL (
+    SELECT string_agg(po."label" || ': ' || po."value", '; ' ORDER BY po."position") AS "summary"
+    FROM "purchase_option" po
+    WHERE po."purchase_id" = p."purchase_id"
+) picks ON true
+WHERE ($1::date IS NULL OR (p."purchased_at" AT TIME ZONE 'America/New_York')::date >= $1)
+  AND ($2::date IS NULL OR (p."purchased_at" AT TIME ZONE 'America/New_York')::date <= $2)
+  AND ($3::bool IS NULL OR (p."received_item_date" IS NOT NULL) = $3)
+ORDER BY p."purchase_id"
+"#;
+
+// Item totals first, then a line per chosen value under each item.
+const SALES: &str = r#"
+WITH "sold" AS (
+    SELECT p.*
+    FROM "purchases" p
+    WHERE ($1::date IS NULL OR (p."purchased_at" AT TIME ZONE 'America/New_York')::date >= $1)
+      AND ($2::date IS NULL OR (p."purchased_at" AT TIME ZONE 'America/New_York')::date <= $2)
+      AND ($3::bool IS NULL OR (p."received_item_date" IS NOT NULL) = $3)
+)
+SELECT "item", "option", "choice", "orders", "units", "coins"
+FROM (
+    SELECT i."name" AS "item",
+           i."id"   AS "item_id",
+           0        AS "depth",
+           ''       AS "option",
+           ''       AS "choice",
+           COUNT(*)::BIGINT                              AS "orders",
+           SUM(s."quantity")::BIGINT                     AS "units",
+           SUM(s."quantity" * s."unit_cost")::BIGINT     AS "coins"
+    FROM "sold" s
+    JOIN "items" i ON i."id" = s."item_id"
+    GROUP BY i."id", i."name"
+    UNION ALL
+    SELECT i."name",
+           i."id",
+           1,
+           po."label",
+           po."value",
+           COUNT(*)::BIGINT,
+           SUM(s."quantity")::BIGINT,
+           SUM(s."quantity" * s."unit_cost")::BIGINT
+    FROM "sold" s
+    JOIN "items" i ON i."id" = s."item_id"
+    JOIN "purchase_option" po ON po."purchase_id" = s."purchase_id"
+    GROUP BY i."id", i."name", po."label", po."value"
+) lines
+ORDER BY "item", "item_id", "depth", "option", "choice"
+"#;
+
+/// Narrows an export to purchases made on campus-local dates between
+/// `from` and `to`, inclusive, and to one delivery state.
+pub struct Filter {
+    pub from: Option<Date>,
+    pub to: Option<Date>,
+    pub delivered: Option<bool>,
+}
+
+impl Filter {
+    fn statement(&self, sql: &str) -> Statement {
+        Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            sql,
+            [self.from.into(), self.to.into(), self.delivered.into()],
+        )
+    }
+}
+
+#[derive(FromQueryResult)]
+struct OrderLine {
+    purchase_id: i64,
+    purchased_at: Option<DateTimeWithTimeZone>,
+    andrew_id: String,
+    name: String,
+    dorm: Option<String>,
+    item: String,
+    options: String,
+    quantity: i64,
+    unit_cost: i64,
+    spent: i64,
+    coupon: Option<String>,
+    ticket: Option<String>,
+    pickup_location: Option<String>,
+    pickup_starts_at: Option<DateTimeWithTimeZone>,
+    delivered_at: Option<DateTimeWithTimeZone>,
+    delivered_by: Option<String>,
+}
+
+#[derive(FromQueryResult)]
+struct SalesLine {
+    item: String,
+    option: String,
+    choice: String,
+    orders: i64,
+    units: i64,
+    coins: i64,
+}
+
+fn stamp(at: Option<DateTimeWithTimeZone>) -> String {
+    at.map(|at| at.to_rfc3339()).unwrap_or_default()
+}
+
+/// A free-text cell, quoted with a leading `'` when it starts like a
+/// formula so a spreadsheet opening the export shows it instead of
+/// evaluating it. Numbers and times are written as-is.
+fn text(value: String) -> String {
+    if value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{value}")
+    } else {
+        value
+    }
+}
+
+const ORDER_HEADER: &[&str] = &[
+    "purchase_id",
+    "purchased_at",
+    "andrew_id",
+    "name",
+    "dorm",
+    "item",
+    "options",
+    "quantity",
+    "unit_cost",
+    "spent",
+    "coupon",
+    "ticket",
+    "pickup_location",
+    "pickup_starts_at",
+    "delivered_at",
+    "delivered_by",
+];
+
+fn order_record(row: OrderLine) -> Vec<String> {
+    vec![
+        row.purchase_id.to_string(),
+        stamp(row.purchased_at),
+        text(row.andrew_id),
+        text(row.name),
+        text(row.dorm.unwrap_or_default()),
+        text(row.item),
+        text(row.options),
+        row.quantity.to_string(),
+        row.unit_cost.to_string(),
+        row.spent.to_string(),
+        text(row.coupon.unwrap_or_default()),
+        text(row.ticket.unwrap_or_default()),
+        text(row.pickup_location.unwrap_or_default()),
+        stamp(row.pickup_starts_at),
+        stamp(row.delivered_at),
+        text(row.delivered_by.unwrap_or_default()),
+    ]
+}
+
+const SALES_HEADER: &[&str] = &["item", "option", "choice", "orders", "units", "coins"];
+
+fn sales_record(row: SalesLine) -> Vec<String> {
+    vec![
+        text(row.item),
+        text(row.option),
+        text(row.choice),
+        row.orders.to_string(),
+        row.units.to_string(),
+        row.coins.to_string(),
+    ]
+}
+
+/// Streams the rows `statement` yields as CSV, a chunk at a time, so an
+/// export of any size never sits in memory whole. A database error midway
+/// aborts the response rather than handing back a short file.
+fn stream<R, F>(
+    db: DatabaseConnection,
+    statement: Statement,
+    header: &'static [&'static str],
+    mut record: F,
+) -> Body
+where
+    R: FromQueryResult + Send + 'static,
+    F: FnMut(R) -> Vec<String> + Send + 'static,
+{
+    let (tx, rx) = mpsc::channel::<io::Result<Bytes>>(4);
+
+    tokio::spawn(async move {
+        let fresh = || csv::Writer::from_writer(Vec::new());
+        let mut out = fresh();
+
+        if out.write_record(header).is_err() {
+            return;
+        }
+
+        let mut rows = match R::find_by_statement(statement).stream(&db).await {
+            Ok(rows) => rows,
+            Err(err) => {
+                let _ = tx.send(Err(io::Error::other(err))).await;
+                return;
+            }
+        };
+
+        let mut pending = 0;
+        while let Some(row) = rows.next().await {
+            let row = match row {
+                Ok(row) => row,
+                Err(err) => {
+                    let _ = tx.send(Err(io::Error::other(err))).await;
+                    return;
+                }
+            };
+
+            if let Err(err) = out.write_record(record(row)) {
+                let _ = tx.send(Err(err.into())).await;
+                return;
+            }
+
+            pending += 1;
+            if pending < CHUNK_ROWS {
+                continue;
+            }
+            pending = 0;
+
+            let chunk = match std::mem::replace(&mut out, fresh()).into_inner() {
+                Ok(chunk) => chunk,
+                Err(err) => {
+                    let _ = tx.send(Err(err.into_error())).await;
+                    return;
+                }
+            };
+
+            // The client hung up; stop reading rows nobody will see.
+            if tx.send(Ok(Bytes::from(chunk))).await.is_err() {
+                return;
+            }
+        }
+
+        match out.into_inner() {
+            Ok(rest) => {
+                let _ = tx.send(Ok(Bytes::from(rest))).await;
+            }
+            Err(err) => {
+                let _ = tx.send(Err(err.into_error())).await;
+            }
+        }
+    });
+
+    Body::from_stream(futures_util::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|chunk| (chunk, rx))
+    }))
+}
+
+impl Portal {
+    pub fn orders_csv(&self, filter: &Filter) -> Body {
+        stream(
+            self.db.clone(),
+            filter.statement(ORDERS),
+            ORDER_HEADER,
+            order_record,
+        )
+    }
+
+    pub fn sales_csv(&self, filter: &Filter) -> Body {
+        stream(
+            self.db.clone(),
+            filter.statement(SALES),
+            SALES_HEADER,
+            sales_record,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order() -> OrderLine {
+        OrderLine {
+            purchase_id: 12,
+            purchased_at: None,
+            andrew_id: "scotty".to_owned(),
+            name: "Scotty Dog".to_owned(),
+            dorm: None,
+            item: "Tartan scarf".to_owned(),
+            options: "Size: M".to_owned(),
+            quantity: 2,
+            unit_cost: 30,
+            spent: 60,
+            coupon: Some("ORIENT20".to_owned()),
+            ticket: None,
+            pickup_location: None,
+            pickup_starts_at: None,
+            delivered_at: Some("2026-09-01T15:30:00-04:00".parse().unwrap()),
+            delivered_by: Some("desk".to_owned()),
+        }
+    }
+
+    #[test]
+    fn order_records_line_up_with_the_header() {
+        let record = order_record(order());
+        assert_eq!(record.len(), ORDER_HEADER.len());
+
+        let cell = |name: &str| {
+            let at = ORDER_HEADER.iter().position(|head| *head == name).unwrap();
+            record[at].as_str()
+        };
+        assert_eq!(cell("purchase_id"), "12");
+        assert_eq!(cell("spent"), "60");
+        assert_eq!(cell("coupon"), "ORIENT20");
+        assert_eq!(cell("delivered_at"), "2026-09-01T15:30:00-04:00");
+    }
+
+    #[test]
+    fn unknown_times_and_missing_fields_are_blank() {
+        let record = order_record(order());
+        assert_eq!(record[1], "");
+        assert_eq!(record[4], "");
+        assert_eq!(stamp(None), "");
+    }
+
+    #[test]
+    fn sales_records_line_up_with_the_header() {
+        let record = sales_record(SalesLine {
+            item: "Tartan scarf".to_owned(),
+            option: "Size".to_owned(),
+            choice: "M".to_owned(),
+            orders: 3,
+            units: 4,
+            coins: 120,
+        });
+        assert_eq!(record.len(), SALES_HEADER.len());
+        assert_eq!(record[3..], ["3", "4", "120"]);
+    }
+
+    #[test]
+    fn formula_like_text_is_quoted() {
+        let mut line = order();
+        line.name = "=HYPERLINK(\"http://evil\")".to_owned();
+        line.item = "@SUM(A1)".to_owned();
+        line.options = "\tSize: M".to_owned();
+        line.delivered_by = Some("-desk".to_owned());
+        let record = order_record(line);
+
+        assert_eq!(record[3], "'=HYPERLINK(\"http://evil\")");
+        assert_eq!(record[5], "'@SUM(A1)");
+        assert_eq!(record[6], "'\tSize: M");
+        assert_eq!(record[15], "'-desk");
+        assert_eq!(record[2], "scotty");
+
+        let record = sales_record(SalesLine {
+            item: "+1 scarf".to_owned(),
+            option: "Size".to_owned(),
+            choice: "\rM".to_owned(),
+            orders: 1,
+            units: -1,
+            coins: -30,
+        });
+        assert_eq!(record[..3], ["'+1 scarf", "Size", "'\rM"]);
+        assert_eq!(record[4..], ["-1", "-30"]);
+    }
+}
@@ -1,6 +1,8 @@
 use axum::Json;
 use axum::extract::rejection::JsonRejection;
 use axum::extract::{Path, Query, State};
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
 use sea_orm::prelude::{Date, Uuid};
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
@@ -10,13 +12,24 @@ use utoipa_axum::routes;
 
 use super::activity::{ActivityDay, ActivityTap, GemstoneCorrectionView};
 use super::assets::{AssetError, Assets};
-use super::trade::{Desk, DeskPickView, Fulfilled, OrderView, PassHolder, SalesItemView};
+use super::export;
+use super::reports::Filter;
+use super::trade::{
+    AuctionDeskView, Desk, DeskPickView, Fulfilled, HandedOver, OrderView, PassHolder,
+    PickSlotView, SalesItemView,
+};
 use super::{Browse, Column, Outcome, Page, Portal, PortalErrBody, PortalError, Script};
 use crate::access::{Access, CAPABILITIES, Capability, Level, Role};
 use crate::auth::AuthError;
+use crate::devices::Devices;
+use crate::devices::enrollment::{Decider, EnrollmentView};
+use crate::items::auction::Settled;
 use crate::items::options::{self, Choice, Spec};
+use crate::items::raffle::Drawn;
+use crate::items::refunds::Refund;
 use crate::items::{Items, Receipt, Refunded, Stocked};
 use crate::passes::Passes;
+use crate::submissions::{Queued, Reviewed, Submissions};
 
 #[derive(Clone)]
 pub struct Console {
@@ -25,6 +38,8 @@ pub struct Console {
     desk: Desk,
     passes: Passes,
     assets: Assets,
+    submissions: Submissions,
+    devices: Devices,
 }
 #[derive(Deserialize, ToSchema)]
 pub struct GemstoneCorrectionBody {
@@ -43,6 +58,8 @@ pub fn router(
     desk: Desk,
     passes: Passes,
     assets: Assets,
+    submissions: Submissions,
+    devices: Devices,
 ) -> OpenApiRouter {
     OpenApiRouter::new()
         .routes(routes!(me))
@@ -57,15 +74,32 @@ pub fn router(
         .routes(routes!(trade_items))
         .routes(routes!(trade_options))
         .routes(routes!(trade_pass))
+        .routes(routes!(handover_scan))
+        .routes(routes!(handover))
         .routes(routes!(trade_orders, trade_buy))
+        .routes(routes!(trade_pick_list))
         .routes(routes!(trade_fulfil))
         .routes(routes!(trade_refund))
+        .routes(routes!(raffle_commit))
+        .routes(routes!(raffle_draw))
+        .routes(routes!(trade_auctions))
+        .routes(routes!(auction_settle))
         .routes(routes!(trade_sales))
+        .routes(routes!(export_orders))
+        .routes(routes!(export_sales))
         .routes(routes!(user_activity))
         .routes(routes!(user_activity_taps))
         .routes(routes!(move_activity_taps))
         .routes(routes!(set_activity_gemstones, clear_activity_gemstones))
         .routes(routes!(trade_balance))
+        .routes(routes!(review_queue))
+        .routes(routes!(review_photo))
+        .routes(routes!(review_approve))
+        .routes(routes!(review_reject))
+        .routes(routes!(enrollment_queue))
+        .routes(routes!(enrollment_approve))
+        .routes(routes!(enrollment_deny))
+        .routes(routes!(challenge_export))
         .layer(axum::extract::DefaultBodyLimit::max(
             crate::portal::assets::MAX_BYTES + 4096,
         ))
@@ -75,6 +109,8 @@ pub fn router(
             desk,
             passes,
             assets,
+            submissions,
+            devices,
         })
 }
 
@@ -464,6 +500,92 @@ async fn trade_sales(
     Ok(Json(console.desk.sales().await?))
 }
 
+#[derive(Deserialize, ToSchema, utoipa::IntoParams)]
+pub struct ReportQuery {
+    /// First campus-local purchase date to include.
+    pub from: Option<Date>,
+    /// Last campus-local purchase date to include.
+    pub to: Option<Date>,
+    pub delivered: Option<bool>,
+}
+
+impl ReportQuery {
+    fn filter(self) -> Result<Filter, PortalError> {
+        if self.from.zip(self.to).is_some_and(|(from, to)| from > to) {
+            return Err(AuthError::BadRequest("date_range_invalid").into());
+        }
+
+        Ok(Filter {
+            from: self.from,
+            to: self.to,
+            delivered: self.delivered,
+        })
+    }
+}
+
+fn csv_download(file: &str, body: axum::body::Body) -> Response {
+    (
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_owned()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{file}\""),
+            ),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+#[utoipa::path(
+    get,
+    path = "/portal/trade/export/orders",
+    tag = "portal",
+    params(ReportQuery),
+    responses(
+        (status = OK, description = "Every matching order as CSV", content_type = "text/csv"),
+        (status = BAD_REQUEST, body = PortalErrBody),
+        (status = FORBIDDEN, body = PortalErrBody),
+    ),
+)]
+async fn export_orders(
+    State(console): State<Console>,
+    access: Access,
+    Query(query): Query<ReportQuery>,
+) -> Result<Response, PortalError> {
+    access.require(Capability::TradeDesk)?;
+
+    let filter = query.filter()?;
+
+    Ok(csv_download(
+        "orders.csv",
+        console.portal.orders_csv(&filter),
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/portal/trade/export/sales",
+    tag = "portal",
+    params(ReportQuery),
+    responses(
+        (status = OK, description = "Per-item and per-choice totals as CSV", content_type = "text/csv"),
+        (status = BAD_REQUEST, body = PortalErrBody),
+        (status = FORBIDDEN, body = PortalErrBody),
+    ),
+)]
+async fn export_sales(
+    State(console): State<Console>,
+    access: Access,
+    Query(query): Query<ReportQuery>,
+) -> Result<Response, PortalError> {
+    access.require(Capability::TradeDesk)?;
+
+    let filter = query.filter()?;
+
+    Ok(csv_download("sales.csv", console.portal.sales_csv(&filter)))
+}
+
 #[derive(Deserialize, ToSchema)]
 pub struct RowBody {
     #[schema(value_type = HashMap<String, serde_json::Value>)]
@@ -699,6 +821,13 @@ pub struct ShopItem {
     pub background_url: Option<String>,
     pub icon_shade: Option<String>,
     pub stock: i64,
+    pub max_per_order: i64,
+    pub max_per_user: Option<i64>,
+    pub window_limit: Option<i64>,
+    pub window_hours: Option<i32>,
+    pub refund_window_hours: Option<i32>,
+    pub available_from: Option<chrono::DateTime<chrono::FixedOffset>>,
+    pub available_until: Option<chrono::DateTime<chrono::FixedOffset>>,
     pub options: Vec<ShopOption>,
 }
 
@@ -713,6 +842,13 @@ impl ShopItem {
             background_url: item.background_url,
             icon_shade: item.icon_shade,
             stock: item.stock,
+            max_per_order: item.max_per_order,
+            max_per_user: item.max_per_user,
+            window_limit: item.window_limit,
+            window_hours: item.window_hours,
+            refund_window_hours: item.refund_window_hours,
+            available_from: item.available_from,
+            available_until: item.available_until,
             options,
         }
     }
@@ -836,7 +972,16 @@ async fn trade_pass(
 ) -> Result<Json<PassHolder>, PortalError> {
     access.require(Capability::TradeDesk)?;
 
-    let payload = body(payload)?;
+    let (andrew_id, issued_at) = scan(&console, body(payload)?).await?;
+
+    Ok(Json(console.desk.holder(&andrew_id, issued_at).await?))
+}
+
+/// Resolves a scanned pass token, or a typed Andrew ID as the fallback.
+async fn scan(
+    console: &Console,
+    payload: PassLookup,
+) -> Result<(String, Option<i64>), PortalError> {
     let scanned = payload
         .token
         .as_deref()
@@ -865,7 +1010,71 @@ async fn trade_pass(
         }
     };
 
-    Ok(Json(console.desk.holder(&andrew_id, issued_at).await?))
+    Ok((andrew_id, issued_at))
+}
+
+#[utoipa::path(
+    post,
+    path = "/portal/trade/handover/scan",
+    tag = "portal",
+    request_body = PassLookup,
+    responses(
+        (status = OK, body = PassHolder),
+        (status = BAD_REQUEST, body = PortalErrBody),
+        (status = FORBIDDEN, body = PortalErrBody),
+        (status = NOT_FOUND, body = PortalErrBody),
+    ),
+)]
+async fn handover_scan(
+    State(console): State<Console>,
+    access: Access,
+    payload: Result<Json<PassLookup>, JsonRejection>,
+) -> Result<Json<PassHolder>, PortalError> {
+    access.require(Capability::TradeDesk)?;
+
+    let (andrew_id, issued_at) = scan(&console, body(payload)?).await?;
+
+    let mut holder = console.desk.holder(&andrew_id, issued_at).await?;
+    holder.orders = console.desk.collectable(&holder.andrew_id).await?;
+
+    Ok(Json(holder))
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct HandoverBody {
+    pub andrew_id: String,
+    pub purchase_ids: Vec<i64>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/portal/trade/handover",
+    tag = "portal",
+    request_body = HandoverBody,
+    responses(
+        (status = OK, body = HandedOver),
+        (status = BAD_REQUEST, body = PortalErrBody),
+        (status = FORBIDDEN, body = PortalErrBody),
+        (status = NOT_FOUND, body = PortalErrBody),
+        (status = CONFLICT, body = PortalErrBody),
+    ),
+)]
+async fn handover(
+    State(console): State<Console>,
+    access: Access,
+    payload: Result<Json<HandoverBody>, JsonRejection>,
+) -> Result<Json<HandedOver>, PortalError> {
+    access.require(Capability::TradeDesk)?;
+
+    let payload = body(payload)?;
+    let andrew_id = payload.andrew_id.trim().to_ascii_lowercase();
+
+    Ok(Json(
+        console
+            .desk
+            .handover(&andrew_id, &payload.purchase_ids, &access.user.andrew_id)
+            .await?,
+    ))
 }
 
 #[derive(Deserialize, ToSchema, utoipa::IntoParams)]
@@ -1049,6 +1258,8 @@ async fn drop_asset(
 pub struct OrderQuery {
     pub andrew_id: Option<String>,
     pub delivered: Option<bool>,
+    /// Only orders booked into this pickup slot.
+    pub slot: Option<Uuid>,
     pub limit: Option<u64>,
 }
 
@@ -1071,7 +1282,12 @@ async fn trade_orders(
 
     let orders = console
         .desk
-        .orders(query.andrew_id.as_deref(), query.delivered, query.limit)
+        .orders(
+            query.andrew_id.as_deref(),
+            query.delivered,
+            query.slot,
+            query.limit,
+        )
         .await?;
 
     let ids: Vec<i64> = orders.iter().map(|order| order.purchase_id).collect();
@@ -1091,6 +1307,10 @@ async fn trade_orders(
                     cost: order.cost,
                     quantity: order.quantity,
                     received_item_date: order.received_item_date,
+                    delivered_at: order.delivered_at,
+                    delivered_by: order.delivered_by,
+                    ticket: order.ticket,
+                    pickup_slot_id: order.pickup_slot_id,
                     options: mine
                         .into_iter()
                         .map(|pick| DeskPickView {
@@ -1111,6 +1331,8 @@ pub struct DeskSaleBody {
     pub quantity: i64,
     #[serde(default)]
     pub options: Vec<DeskPickBody>,
+    #[serde(default)]
+    pub coupon: Option<String>,
 }
 
 #[derive(Deserialize, ToSchema)]
@@ -1125,6 +1347,7 @@ pub struct Bought {
     pub item_id: Uuid,
     pub item: String,
     pub cost: i64,
+    pub discount: i64,
     pub quantity: i64,
     pub spent: i64,
     pub stock: i64,
@@ -1132,6 +1355,14 @@ pub struct Bought {
     pub options: Vec<DeskPickView>,
 }
 
+/// A desk sale; a coupon unit is its own purchase.
+#[derive(Serialize, ToSchema)]
+pub struct DeskSold {
+    pub purchases: Vec<Bought>,
+    pub spent: i64,
+    pub scottycoins: i64,
+}
+
 impl From<Receipt> for Bought {
     fn from(receipt: Receipt) -> Self {
         Self {
@@ -1139,6 +1370,7 @@ impl From<Receipt> for Bought {
             item_id: receipt.item,
             item: receipt.name,
             cost: receipt.cost,
+            discount: receipt.discount,
             quantity: receipt.quantity,
             spent: receipt.spent,
             stock: receipt.stock,
@@ -1161,7 +1393,7 @@ impl From<Receipt> for Bought {
     tag = "portal",
     request_body = DeskSaleBody,
     responses(
-        (status = OK, body = Bought),
+        (status = OK, body = DeskSold),
         (status = CONFLICT, body = PortalErrBody),
         (status = FORBIDDEN, body = PortalErrBody),
         (status = NOT_FOUND, body = PortalErrBody),
@@ -1171,7 +1403,7 @@ async fn trade_buy(
     State(console): State<Console>,
     access: Access,
     payload: Result<Json<DeskSaleBody>, JsonRejection>,
-) -> Result<Json<Bought>, PortalError> {
+) -> Result<Json<DeskSold>, PortalError> {
     access.require(Capability::TradeDesk)?;
 
     let payload = body(payload)?;
@@ -1185,12 +1417,22 @@ async fn trade_buy(
         })
         .collect();
 
-    let receipt = console
+    let done = console
         .items
-        .purchase(user, payload.item_id, payload.quantity, &chosen)
+        .purchase(
+            user,
+            payload.item_id,
+            payload.quantity,
+            &chosen,
+            payload.coupon.as_deref(),
+        )
         .await?;
 
-    Ok(Json(receipt.into()))
+    Ok(Json(DeskSold {
+        purchases: done.receipts.into_iter().map(Bought::from).collect(),
+        spent: done.spent,
+        scottycoins: done.scottycoins,
+    }))
 }
 
 #[derive(Deserialize, ToSchema)]
@@ -1198,6 +1440,33 @@ pub struct FulfilBody {
     pub delivered: bool,
 }
 
+#[derive(Deserialize, ToSchema, utoipa::IntoParams)]
+pub struct PickQuery {
+    pub slot: Option<Uuid>,
+    /// Slots starting on this campus-local date.
+    pub date: Option<Date>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/portal/trade/pickups",
+    tag = "portal",
+    params(PickQuery),
+    responses(
+        (status = OK, body = Vec<PickSlotView>),
+        (status = FORBIDDEN, body = PortalErrBody),
+    ),
+)]
+async fn trade_pick_list(
+    State(console): State<Console>,
+    access: Access,
+    Query(query): Query<PickQuery>,
+) -> Result<Json<Vec<PickSlotView>>, PortalError> {
+    access.require(Capability::TradeDesk)?;
+
+    Ok(Json(console.desk.pick_list(query.slot, query.date).await?))
+}
+
 #[utoipa::path(
     put,
     path = "/portal/trade/orders/{purchase_id}/delivery",
@@ -1220,7 +1489,10 @@ async fn trade_fulfil(
 
     let payload = body(payload)?;
     Ok(Json(
-        console.desk.fulfil(purchase_id, payload.delivered).await?,
+        console
+            .desk
+            .fulfil(purchase_id, payload.delivered, &access.user.andrew_id)
+            .await?,
     ))
 }
 
@@ -1228,6 +1500,17 @@ async fn trade_fulfil(
 pub struct DeskRefundBody {
     pub andrew_id: String,
     pub quantity: i64,
+    /// Required; kept in the refund audit.
+    pub reason: String,
+    /// Coins to give back, when not the full price paid.
+    #[serde(default)]
+    pub amount: Option<i64>,
+    /// The delivered item was handed back to the desk.
+    #[serde(default)]
+    pub returned: bool,
+    /// Returned or reported damaged; damaged units are not put back on sale.
+    #[serde(default)]
+    pub damaged: bool,
 }
 
 #[derive(Serialize, ToSchema)]
@@ -1253,6 +1536,7 @@ impl From<Refunded> for GaveBack {
     request_body = DeskRefundBody,
     responses(
         (status = OK, body = GaveBack),
+        (status = BAD_REQUEST, body = PortalErrBody),
         (status = CONFLICT, body = PortalErrBody),
         (status = FORBIDDEN, body = PortalErrBody),
         (status = NOT_FOUND, body = PortalErrBody),
@@ -1270,8 +1554,542 @@ async fn trade_refund(
     let user = console.portal.user_id(&payload.andrew_id).await?;
     let refund = console
         .items
-        .refund(user, purchase_id, payload.quantity)
+        .refund_as(
+            user,
+            purchase_id,
+            Refund {
+                quantity: payload.quantity,
+                amount: payload.amount,
+                reason: &payload.reason,
+                returned: payload.returned,
+                damaged: payload.damaged,
+            },
+            &access.user.andrew_id,
+        )
         .await?;
 
     Ok(Json(refund.into()))
 }
+
+#[utoipa::path(
+    get,
+    path = "/portal/trade/auctions",
+    tag = "portal",
+    responses(
+        (status = OK, body = Vec<AuctionDeskView>),
+        (status = FORBIDDEN, body = PortalErrBody),
+    ),
+)]
+async fn trade_auctions(
+    State(console): State<Console>,
+    access: Access,
+) -> Result<Json<Vec<AuctionDeskView>>, PortalError> {
+    access.require(Capability::TradeDesk)?;
+    access.require_table("auction", Level::Read)?;
+    access.require_table("bid", Level::Read)?;
+
+    Ok(Json(console.desk.auctions().await?))
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SettledView {
+    pub item_id: Uuid,
+    /// The winner's purchase, absent when nobody bid or the item sold out.
+    pub purchase_id: Option<i64>,
+    pub winner: Option<Uuid>,
+    pub amount: Option<i64>,
+    /// Closed without a winner because no stock was left; every bid was
+    /// released.
+    pub out_of_stock: bool,
+    pub settled_by: Option<String>,
+    pub settled_at: Option<chrono::DateTime<chrono::FixedOffset>>,
+}
+
+impl From<Settled> for SettledView {
+    fn from(settled: Settled) -> Self {
+        Self {
+            item_id: settled.auction.item_id,
+            purchase_id: settled.auction.purchase_id,
+            winner: settled.winner.as_ref().map(|top| top.user_id),
+            amount: settled.winner.as_ref().map(|top| top.amount),
+            out_of_stock: settled.out_of_stock,
+            settled_by: settled.auction.settled_by,
+            settled_at: settled.auction.settled_at,
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/portal/trade/auctions/{item_id}/settle",
+    tag = "portal",
+    params(("item_id" = Uuid, Path, description = "Auctioned item id")),
+    responses(
+        (status = OK, body = SettledView),
+        (status = FORBIDDEN, body = PortalErrBody),
+        (status = NOT_FOUND, body = PortalErrBody),
+        (status = CONFLICT, body = PortalErrBody),
+    ),
+)]
+async fn auction_settle(
+    State(console): State<Console>,
+    access: Access,
+    Path(item_id): Path<Uuid>,
+) -> Result<Json<SettledView>, PortalError> {
+    access.require(Capability::TradeDesk)?;
+    access.require_table("auction", Level::Edit)?;
+    access.require_table("purchases", Level::Edit)?;
+
+    let settled = console
+        .items
+        .settle(item_id, &access.user.andrew_id)
+        .await?;
+
+    Ok(Json(settled.into()))
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CommitBody {
+    /// Hex SHA-256 of the seed that will be revealed at the draw.
+    pub commitment: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Committed {
+    pub item_id: Uuid,
+    pub commitment: String,
+    pub committed_by: String,
+    pub committed_at: chrono::DateTime<chrono::FixedOffset>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/portal/trade/raffles/{item_id}/commit",
+    tag = "portal",
+    params(("item_id" = Uuid, Path, description = "Raffle item id")),
+    request_body = CommitBody,
+    responses(
+        (status = OK, body = Committed),
+        (status = BAD_REQUEST, body = PortalErrBody),
+        (status = FORBIDDEN, body = PortalErrBody),
+        (status = NOT_FOUND, body = PortalErrBody),
+        (status = CONFLICT, body = PortalErrBody),
+    ),
+)]
+async fn raffle_commit(
+    State(console): State<Console>,
+    access: Access,
+    Path(item_id): Path<Uuid>,
+    payload: Result<Json<CommitBody>, JsonRejection>,
+) -> Result<Json<Committed>, PortalError> {
+    access.require(Capability::TradeDesk)?;
+    access.require_table("raffle", Level::Edit)?;
+
+    let payload = body(payload)?;
+    let saved = console
+        .items
+        .commit(item_id, &payload.commitment, &access.user.andrew_id)
+        .await?;
+
+    Ok(Json(Committed {
+        item_id: saved.item_id,
+        commitment: saved.commitment,
+        committed_by: saved.committed_by,
+        committed_at: saved.committed_at,
+    }))
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct DrawBody {
+    pub seed: String,
+    pub winners: i32,
+    #[serde(default)]
+    pub refund_losers: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct DrawnWinner {
+    pub purchase_id: i64,
+    pub user_id: Uuid,
+    pub ticket: usize,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct DrawView {
+    pub item_id: Uuid,
+    pub commitment: String,
+    pub seed: String,
+    pub tickets: usize,
+    pub winners: Vec<DrawnWinner>,
+    pub refunded: i64,
+}
+
+impl From<Drawn> for DrawView {
+    fn from(drawn: Drawn) -> Self {
+        Self {
+            item_id: drawn.item,
+            commitment: drawn.commitment,
+            seed: drawn.seed,
+            tickets: drawn.tickets,
+            winners: drawn
+                .winners
+                .into_iter()
+                .map(|winner| DrawnWinner {
+                    purchase_id: winner.purchase_id,
+                    user_id: winner.user_id,
+                    ticket: winner.ticket,
+                })
+                .collect(),
+            refunded: drawn.refunded,
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/portal/trade/raffles/{item_id}/draw",
+    tag = "portal",
+    params(("item_id" = Uuid, Path, description = "Raffle item id")),
+    request_body = DrawBody,
+    responses(
+        (status = OK, body = DrawView),
+        (status = BAD_REQUEST, body = PortalErrBody),
+        (status = FORBIDDEN, body = PortalErrBody),
+        (status = NOT_FOUND, body = PortalErrBody),
+        (status = CONFLICT, body = PortalErrBody),
+    ),
+)]
+async fn raffle_draw(
+    State(console): State<Console>,
+    access: Access,
+    Path(item_id): Path<Uuid>,
+    payload: Result<Json<DrawBody>, JsonRejection>,
+) -> Result<Json<DrawView>, PortalError> {
+    access.require(Capability::TradeDesk)?;
+    access.require_table("raffle", Level::Edit)?;
+    access.require_table("purchases", Level::Edit)?;
+
+    let payload = body(payload)?;
+    let drawn = console
+        .items
+        .draw(
+            item_id,
+            &payload.seed,
+            payload.winners,
+            payload.refund_losers,
+            &access.user.andrew_id,
+        )
+        .await?;
+
+    Ok(Json(drawn.into()))
+}
+
+#[derive(Deserialize, ToSchema, utoipa::IntoParams)]
+pub struct ReviewQuery {
+    pub limit: Option<u64>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct RejectBody {
+    pub reason: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/portal/submissions",
+    tag = "portal",
+    params(ReviewQuery),
+    responses(
+        (status = OK, body = Vec<Queued>),
+        (status = FORBIDDEN, body = PortalErrBody),
+    ),
+)]
+async fn review_queue(
+    State(console): State<Console>,
+    access: Access,
+    Query(query): Query<ReviewQuery>,
+) -> Result<Json<Vec<Queued>>, PortalError> {
+    access.require(Capability::DataConsole)?;
+    access.require_table("submission", Level::Read)?;
+
+    Ok(Json(console.submissions.queue(query.limit).await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/portal/submissions/{id}/photo",
+    tag = "portal",
+    params(("id" = i64, Path, description = "Submission id")),
+    responses(
+        (status = OK, description = "The submitted photo", content_type = "image/*"),
+        (status = FORBIDDEN, body = PortalErrBody),
+        (status = NOT_FOUND, body = PortalErrBody),
+        (status = BAD_GATEWAY, body = PortalErrBody),
+    ),
+)]
+async fn review_photo(
+    State(console): State<Console>,
+    access: Access,
+    Path(id): Path<i64>,
+) -> Result<Response, PortalError> {
+    access.require(Capability::DataConsole)?;
+    access.require_table("submission", Level::Read)?;
+
+    let key = console.submissions.photo_key(id).await?;
+    let (mime, bytes) = match console.assets.get_private(&key).await {
+        Ok(found) => found,
+        Err(AssetError::Rejected("asset_unknown")) => {
+            return Err(AuthError::NotFound("photo_unknown").into());
+        }
+        Err(err) => return Err(asset_failed(err)),
+    };
+
+    Ok(crate::submissions::routes::private_image(mime, bytes))
+}
+
+#[utoipa::path(
+    post,
+    path = "/portal/submissions/{id}/approve",
+    tag = "portal",
+    params(("id" = i64, Path, description = "Submission id")),
+    responses(
+        (status = OK, body = Reviewed),
+        (status = CONFLICT, body = PortalErrBody),
+        (status = FORBIDDEN, body = PortalErrBody),
+        (status = NOT_FOUND, body = PortalErrBody),
+    ),
+)]
+async fn review_approve(
+    State(console): State<Console>,
+    access: Access,
+    Path(id): Path<i64>,
+) -> Result<Json<Reviewed>, PortalError> {
+    access.require(Capability::DataConsole)?;
+    access.require_table("submission", Level::Edit)?;
+
+    Ok(Json(
+        console
+            .submissions
+            .approve(id, &access.user.andrew_id)
+            .await?,
+    ))
+}
+
+#[utoipa::path(
+    post,
+    path = "/portal/submissions/{id}/reject",
+    tag = "portal",
+    params(("id" = i64, Path, description = "Submission id")),
+    request_body = RejectBody,
+    responses(
+        (status = OK, body = Reviewed),
+        (status = BAD_REQUEST, body = PortalErrBody),
+        (status = CONFLICT, body = PortalErrBody),
+        (status = FORBIDDEN, body = PortalErrBody),
+        (status = NOT_FOUND, body = PortalErrBody),
+    ),
+)]
+async fn review_reject(
+    State(console): State<Console>,
+    access: Access,
+    Path(id): Path<i64>,
+    payload: Result<Json<RejectBody>, JsonRejection>,
+) -> Result<Json<Reviewed>, PortalError> {
+    access.require(Capability::DataConsole)?;
+    access.require_table("submission", Level::Edit)?;
+
+    let payload = body(payload)?;
+
+    Ok(Json(
+        console
+            .submissions
+            .reject(id, &access.user.andrew_id, &payload.reason)
+            .await?,
+    ))
+}
+
+#[derive(Deserialize, utoipa::IntoParams)]
+pub struct EnrollmentQuery {
+    pub andrew_id: Option<String>,
+    /// Only enrollments still waiting for a decision.
+    pub pending: Option<bool>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/portal/devices/enrollments",
+    tag = "portal",
+    params(EnrollmentQuery),
+    responses(
+        (status = OK, body = Vec<EnrollmentView>),
+        (status = FORBIDDEN, body = PortalErrBody),
+    ),
+)]
+async fn enrollment_queue(
+    State(console): State<Console>,
+    access: Access,
+    Query(query): Query<EnrollmentQuery>,
+) -> Result<Json<Vec<EnrollmentView>>, PortalError> {
+    access.require(Capability::DataConsole)?;
+    access.require_table("device_enrollment", Level::Read)?;
+
+    let andrew_id = query
+        .andrew_id
+        .as_deref()
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map(str::to_ascii_lowercase);
+
+    Ok(Json(
+        console
+            .devices
+            .enrollment_queue(andrew_id.as_deref(), query.pending.unwrap_or(false))
+            .await?,
+    ))
+}
+
+#[utoipa::path(
+    post,
+    path = "/portal/devices/enrollments/{id}/approve",
+    tag = "portal",
+    params(("id" = i64, Path, description = "Enrollment id")),
+    responses(
+        (status = OK, body = EnrollmentView),
+        (status = CONFLICT, body = PortalErrBody),
+        (status = FORBIDDEN, body = PortalErrBody),
+        (status = NOT_FOUND, body = PortalErrBody),
+    ),
+)]
+async fn enrollment_approve(
+    State(console): State<Console>,
+    access: Access,
+    Path(id): Path<i64>,
+) -> Result<Json<EnrollmentView>, PortalError> {
+    access.require(Capability::DataConsole)?;
+    access.require_table("device_enrollment", Level::Edit)?;
+
+    Ok(Json(
+        console
+            .devices
+            .decide(id, true, Decider::Staff(&access.user.andrew_id))
+            .await?,
+    ))
+}
+
+#[utoipa::path(
+    post,
+    path = "/portal/devices/enrollments/{id}/deny",
+    tag = "portal",
+    params(("id" = i64, Path, description = "Enrollment id")),
+    responses(
+        (status = OK, body = EnrollmentView),
+        (status = CONFLICT, body = PortalErrBody),
+        (status = FORBIDDEN, body = PortalErrBody),
+        (status = NOT_FOUND, body = PortalErrBody),
+    ),
+)]
+async fn enrollment_deny(
+    State(console): State<Console>,
+    access: Access,
+    Path(id): Path<i64>,
+) -> Result<Json<EnrollmentView>, PortalError> {
+    access.require(Capability::DataConsole)?;
+    access.require_table("device_enrollment", Level::Edit)?;
+
+    Ok(Json(
+        console
+            .devices
+            .decide(id, false, Decider::Staff(&access.user.andrew_id))
+            .await?,
+    ))
+}
+
+#[derive(Deserialize, ToSchema, utoipa::IntoParams)]
+pub struct ExportQuery {
+    /// `geojson` (the default) or `kml`.
+    pub format: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/portal/challenges/export",
+    tag = "portal",
+    params(ExportQuery),
+    responses(
+        (status = OK, description = "GeoJSON FeatureCollection or KML document"),
+        (status = BAD_REQUEST, body = PortalErrBody),
+        (status = FORBIDDEN, body = PortalErrBody),
+    ),
+)]
+async fn challenge_export(
+    State(console): State<Console>,
+    access: Access,
+    Query(query): Query<ExportQuery>,
+) -> Result<Response, PortalError> {
+    access.require(Capability::DataConsole)?;
+    let level = access.require_table("challenge", Level::Read)?;
+    access.require_table("challenge_card", Level::Read)?;
+
+    // Read-only placers walk the public map; secrets need edit rights.
+    let placed = console.portal.placements(level >= Level::Edit).await?;
+
+    let (mime, file, body) = match query.format.as_deref().unwrap_or("geojson") {
+        "geojson" => (
+            "application/geo+json",
+            "challenges.geojson",
+            export::geojson(&placed).to_string(),
+        ),
+        "kml" => (
+            "application/vnd.google-earth.kml+xml",
+            "challenges.kml",
+            export::kml(&placed),
+        ),
+        _ => return Err(AuthError::BadRequest("export_format_invalid").into()),
+    };
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, mime.to_owned()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{file}\""),
+            ),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod report_tests {
+    use super::*;
+
+    fn query(from: Option<&str>, to: Option<&str>) -> ReportQuery {
+        ReportQuery {
+            from: from.map(|day| day.parse().unwrap()),
+            to: to.map(|day| day.parse().unwrap()),
+            delivered: None,
+        }
+    }
+
+    #[test]
+    fn a_date_range_may_be_open_or_a_single_day() {
+        assert!(query(None, None).filter().is_ok());
+        assert!(query(Some("2026-09-01"), None).filter().is_ok());
+        assert!(
+            query(Some("2026-09-01"), Some("2026-09-01"))
+                .filter()
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn a_backwards_date_range_is_refused() {
+        assert!(matches!(
+            query(Some("2026-09-02"), Some("2026-09-01")).filter(),
+            Err(PortalError::Auth(AuthError::BadRequest(
+                "date_range_invalid"
+            )))
+        ));
+    }
+}
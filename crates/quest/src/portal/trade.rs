@@ -1,7 +1,12 @@
 use std::collections::HashMap;
 
+use entity::enums::TicketState;
+use entity::{purchases, users};
 use sea_orm::prelude::{Date, Uuid};
-use sea_orm::{ConnectionTrait, DatabaseConnection, DbBackend, FromQueryResult, Statement};
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, DatabaseConnection, DbBackend, EntityTrait, FromQueryResult,
+    QueryFilter, QuerySelect, Statement, TransactionTrait, sea_query,
+};
 use serde::Serialize;
 use utoipa::ToSchema;
 
@@ -19,16 +24,51 @@ SELECT p."purchase_id",
        i."name"              AS "item",
        p."unit_cost"         AS "cost",
        p."quantity",
-       p."received_item_date"
+       p."received_item_date",
+       p."delivered_at",
+       p."delivered_by",
+       p."ticket",
+       p."pickup_slot_id"
 FROM "purchases" p
 JOIN "users" u ON u."id" = p."user_id"
 JOIN "items" i ON i."id" = p."item_id"
 WHERE ($1::text IS NULL OR u."andrew_id" = $1)
   AND ($2::bool IS NULL OR (p."received_item_date" IS NOT NULL) = $2)
+  AND ($4::uuid IS NULL OR p."pickup_slot_id" = $4)
 ORDER BY p."purchase_id" DESC
 LIMIT $3
 "#;
 
+// One line per slot, item and option combination, for whoever packs the bags.
+const PICK_LIST: &str = r#"
+SELECT s."id"          AS "slot_id",
+       l."name"        AS "location",
+       s."starts_at",
+       s."ends_at",
+       s."capacity",
+       i."id"          AS "item_id",
+       i."name"        AS "item",
+       COALESCE(picks."summary", '') AS "options",
+       SUM(p."quantity")::BIGINT     AS "quantity",
+       COUNT(*)::BIGINT              AS "orders",
+       COUNT(*) FILTER (
+           WHERE p."received_item_date" IS NOT NULL
+       )::BIGINT                     AS "delivered"
+FROM "purchases" p
+JOIN "pickup_slot" s ON s."id" = p."pickup_slot_id"
+JOIN "pickup_location" l ON l."id" = s."location_id"
+JOIN "items" i ON i."id" = p."item_id"
+LEFT JOIN LATERAL (
+    SELECT string_agg(po."label" || ': ' || po."value", ', ' ORDER BY po."position") AS "summary"
+    FROM "purchase_option" po
+    WHERE po."purchase_id" = p."purchase_id"
+) picks ON true
+WHERE ($1::uuid IS NULL OR s."id" = $1)
+  AND ($2::date IS NULL OR (s."starts_at" AT TIME ZONE 'America/New_York')::date = $2)
+GROUP BY s."id", l."name", i."id", i."name", picks."summary"
+ORDER BY s."starts_at", l."name", s."id", i."name", picks."summary"
+"#;
+
 const SALES_TOTALS: &str = r#"
 SELECT
     i."id" AS "item_id",
@@ -40,6 +80,37 @@ GROUP BY i."id", i."name"
 ORDER BY i."name"
 "#;
 
+const SALES_COUPONS: &str = r#"
+SELECT r."item_id",
+       r."code",
+       COUNT(*)::BIGINT                  AS "redemptions",
+       COUNT(DISTINCT r."user_id")::BIGINT AS "players",
+       SUM(r."quantity")::BIGINT         AS "units",
+       SUM((r."list_cost" - r."unit_cost") * r."quantity")::BIGINT AS "discounted"
+FROM "coupon_redemption" r
+GROUP BY r."item_id", r."code"
+ORDER BY r."code"
+"#;
+
+const SALES_REFUNDS: &str = r#"
+SELECT r."id",
+       r."item_id",
+       r."purchase_id",
+       u."andrew_id",
+       r."quantity",
+       r."unit_cost",
+       r."amount",
+       r."reason",
+       r."delivered",
+       r."returned",
+       r."damaged",
+       r."refunded_by",
+       r."refunded_at"
+FROM "refund" r
+JOIN "users" u ON u."id" = r."user_id"
+ORDER BY r."refunded_at" DESC, r."id" DESC
+"#;
+
 const SALES_OPTIONS: &str = r#"
 SELECT
     p."item_id" AS "item_id",
@@ -69,11 +140,56 @@ LEFT JOIN "wallet_pass" w ON w."user_id" = u."id"
 WHERE u."andrew_id" = $1
 "#;
 
+// Raffle tickets only become deliverable once they have won.
 const FULFIL: &str = r#"
 UPDATE "purchases"
-SET "received_item_date" = CASE WHEN $2 THEN current_date ELSE NULL END
+SET "received_item_date" = CASE WHEN $2 THEN current_date ELSE NULL END,
+    "delivered_at" = CASE WHEN $2 THEN now() ELSE NULL END,
+    "delivered_by" = CASE WHEN $2 THEN $3 ELSE NULL END
 WHERE "purchase_id" = $1
-RETURNING "received_item_date"
+  AND ("ticket" IS NULL OR "ticket" = 'won')
+RETURNING "received_item_date", "delivered_at", "delivered_by"
+"#;
+
+const TICKET: &str = r#"
+SELECT "ticket" FROM "purchases" WHERE "purchase_id" = $1
+"#;
+
+// Sealed amounts stay visible here; only players are kept in the dark.
+const AUCTIONS: &str = r#"
+SELECT a."item_id",
+       i."name"              AS "item",
+       a."reserve",
+       a."increment",
+       a."sealed",
+       a."ends_at",
+       (SELECT COUNT(*) FROM "bid" b WHERE b."item_id" = a."item_id")::BIGINT AS "bids",
+       (
+           SELECT COALESCE(SUM(b."amount"), 0)
+           FROM "bid" b
+           WHERE b."item_id" = a."item_id"
+             AND b."released_at" IS NULL
+       )::BIGINT             AS "escrowed",
+       top."amount"          AS "leading",
+       top."andrew_id"       AS "leader",
+       a."purchase_id",
+       a."settled_by",
+       a."settled_at"
+FROM "auction" a
+JOIN "items" i ON i."id" = a."item_id"
+LEFT JOIN LATERAL (
+    SELECT b."amount", u."andrew_id"
+    FROM "bid" b
+    JOIN "users" u ON u."id" = b."user_id"
+    WHERE b."item_id" = a."item_id"
+      AND (
+          b."id" = a."winning_bid"
+          OR (a."settled_at" IS NULL AND b."released_at" IS NULL)
+      )
+    ORDER BY b."amount" DESC, b."id"
+    LIMIT 1
+) top ON true
+ORDER BY a."settled_at" IS NOT NULL, a."ends_at"
 "#;
 
 #[derive(Debug, FromQueryResult)]
@@ -86,6 +202,25 @@ struct OrderRow {
     pub cost: i64,
     pub quantity: i64,
     pub received_item_date: Option<Date>,
+    pub delivered_at: Option<chrono::DateTime<chrono::FixedOffset>>,
+    pub delivered_by: Option<String>,
+    pub ticket: Option<String>,
+    pub pickup_slot_id: Option<Uuid>,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct PickRow {
+    slot_id: Uuid,
+    location: String,
+    starts_at: chrono::DateTime<chrono::FixedOffset>,
+    ends_at: chrono::DateTime<chrono::FixedOffset>,
+    capacity: Option<i32>,
+    item_id: Uuid,
+    item: String,
+    options: String,
+    quantity: i64,
+    orders: i64,
+    delivered: i64,
 }
 
 #[derive(Debug, FromQueryResult)]
@@ -110,12 +245,49 @@ pub struct SalesOptionView {
     pub sold: i64,
 }
 
+#[derive(Debug, Serialize, ToSchema, FromQueryResult)]
+pub struct CouponSalesView {
+    #[serde(skip)]
+    pub item_id: Uuid,
+    pub code: String,
+    /// Purchase lines the code discounted, refunded ones included.
+    pub redemptions: i64,
+    pub players: i64,
+    pub units: i64,
+    /// Coins taken off across every redemption.
+    pub discounted: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema, FromQueryResult)]
+pub struct RefundAuditView {
+    pub id: i64,
+    #[serde(skip)]
+    pub item_id: Uuid,
+    pub purchase_id: i64,
+    pub andrew_id: String,
+    pub quantity: i64,
+    pub unit_cost: i64,
+    pub amount: i64,
+    pub reason: String,
+    pub delivered: bool,
+    pub returned: bool,
+    pub damaged: bool,
+    /// Staff member who issued it; absent for self-service refunds.
+    pub refunded_by: Option<String>,
+    pub refunded_at: chrono::DateTime<chrono::FixedOffset>,
+}
+
 #[derive(Debug, Serialize, ToSchema)]
 pub struct SalesItemView {
     pub item_id: Uuid,
     pub item: String,
     pub sold: i64,
     pub options: Vec<SalesOptionView>,
+    /// Units refunded and coins given back, over every refund below.
+    pub refunded: i64,
+    pub refunded_coins: i64,
+    pub refunds: Vec<RefundAuditView>,
+    pub coupons: Vec<CouponSalesView>,
 }
 
 #[derive(Debug, Serialize, ToSchema)]
@@ -134,13 +306,88 @@ pub struct OrderView {
     pub cost: i64,
     pub quantity: i64,
     pub received_item_date: Option<Date>,
+    pub delivered_at: Option<chrono::DateTime<chrono::FixedOffset>>,
+    /// Andrew ID of the staff member who handed the order over.
+    pub delivered_by: Option<String>,
+    pub ticket: Option<String>,
+    pub pickup_slot_id: Option<Uuid>,
     pub options: Vec<DeskPickView>,
 }
 
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PickLineView {
+    pub item_id: Uuid,
+    pub item: String,
+    /// `label: value` pairs in option order; empty for plain items.
+    pub options: String,
+    pub quantity: i64,
+    pub orders: i64,
+    pub delivered: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PickSlotView {
+    pub slot_id: Uuid,
+    pub location: String,
+    pub starts_at: chrono::DateTime<chrono::FixedOffset>,
+    pub ends_at: chrono::DateTime<chrono::FixedOffset>,
+    pub capacity: Option<i32>,
+    pub lines: Vec<PickLineView>,
+}
+
+#[derive(Debug, Serialize, ToSchema, FromQueryResult)]
+pub struct AuctionDeskView {
+    pub item_id: Uuid,
+    pub item: String,
+    pub reserve: i64,
+    pub increment: i64,
+    pub sealed: bool,
+    pub ends_at: chrono::DateTime<chrono::FixedOffset>,
+    pub bids: i64,
+    /// Coins currently held against this auction's bids.
+    pub escrowed: i64,
+    pub leading: Option<i64>,
+    pub leader: Option<String>,
+    pub purchase_id: Option<i64>,
+    pub settled_by: Option<String>,
+    pub settled_at: Option<chrono::DateTime<chrono::FixedOffset>>,
+}
+
 #[derive(Debug, Serialize, ToSchema)]
 pub struct Fulfilled {
     pub purchase_id: i64,
     pub received_item_date: Option<Date>,
+    pub delivered_at: Option<chrono::DateTime<chrono::FixedOffset>>,
+    pub delivered_by: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct HandedOver {
+    pub andrew_id: String,
+    pub delivered_by: String,
+    pub delivered_at: chrono::DateTime<chrono::FixedOffset>,
+    pub purchases: Vec<i64>,
+}
+
+/// Checks that `rows` are all `holder`'s, `wanted` of them were found and
+/// none is delivered already or a ticket that did not win.
+fn handable(rows: &[purchases::Model], holder: Uuid, wanted: usize) -> Result<(), AuthError> {
+    if rows.len() != wanted || rows.iter().any(|row| row.user_id != holder) {
+        return Err(AuthError::NotFound("purchase_unknown"));
+    }
+
+    if rows.iter().any(|row| row.received_item_date.is_some()) {
+        return Err(AuthError::Conflict("purchase_delivered"));
+    }
+
+    if rows
+        .iter()
+        .any(|row| matches!(row.ticket, Some(TicketState::Entered | TicketState::Lost)))
+    {
+        return Err(AuthError::Conflict("ticket_not_won"));
+    }
+
+    Ok(())
 }
 
 #[derive(Clone)]
@@ -170,6 +417,7 @@ impl Desk {
         &self,
         andrew_id: Option<&str>,
         delivered: Option<bool>,
+        slot: Option<Uuid>,
         limit: Option<u64>,
     ) -> Result<Vec<OrderView>, PortalError> {
         let limit = limit.unwrap_or(100).clamp(1, LEDGER_CAP) as i64;
@@ -181,6 +429,7 @@ impl Desk {
                 andrew_id.map(str::to_owned).into(),
                 delivered.into(),
                 limit.into(),
+                slot.into(),
             ],
         ))
         .all(&self.db)
@@ -215,25 +464,122 @@ impl Desk {
                 cost: row.cost,
                 quantity: row.quantity,
                 received_item_date: row.received_item_date,
+                delivered_at: row.delivered_at,
+                delivered_by: row.delivered_by,
+                ticket: row.ticket,
+                pickup_slot_id: row.pickup_slot_id,
             })
             .collect())
     }
 
-    pub async fn fulfil(&self, purchase: i64, delivered: bool) -> Result<Fulfilled, PortalError> {
+    pub async fn fulfil(
+        &self,
+        purchase: i64,
+        delivered: bool,
+        by: &str,
+    ) -> Result<Fulfilled, PortalError> {
         let found = self
             .db
             .query_one_raw(Statement::from_sql_and_values(
                 DbBackend::Postgres,
                 FULFIL,
-                [purchase.into(), delivered.into()],
+                [purchase.into(), delivered.into(), by.into()],
             ))
             .await
-            .map_err(sql_failed)?
-            .ok_or(PortalError::Auth(AuthError::NotFound("purchase_unknown")))?;
+            .map_err(sql_failed)?;
+
+        let Some(found) = found else {
+            let ticket = self
+                .db
+                .query_one_raw(Statement::from_sql_and_values(
+                    DbBackend::Postgres,
+                    TICKET,
+                    [purchase.into()],
+                ))
+                .await
+                .map_err(sql_failed)?;
+
+            return Err(PortalError::Auth(match ticket {
+                Some(_) => AuthError::Conflict("ticket_not_won"),
+                None => AuthError::NotFound("purchase_unknown"),
+            }));
+        };
 
         Ok(Fulfilled {
             purchase_id: purchase,
             received_item_date: found.try_get("", "received_item_date").map_err(db_down)?,
+            delivered_at: found.try_get("", "delivered_at").map_err(db_down)?,
+            delivered_by: found.try_get("", "delivered_by").map_err(db_down)?,
+        })
+    }
+
+    /// Every order `andrew_id` can collect right now.
+    pub async fn collectable(&self, andrew_id: &str) -> Result<Vec<OrderView>, PortalError> {
+        let orders = self
+            .orders(Some(andrew_id), Some(false), None, Some(LEDGER_CAP))
+            .await?;
+
+        Ok(orders
+            .into_iter()
+            .filter(|order| matches!(order.ticket.as_deref(), None | Some("won")))
+            .collect())
+    }
+
+    /// Marks a chosen set of `andrew_id`'s orders delivered together; if
+    /// any of them cannot be handed over, none are.
+    pub async fn handover(
+        &self,
+        andrew_id: &str,
+        purchases: &[i64],
+        by: &str,
+    ) -> Result<HandedOver, PortalError> {
+        let mut ids = purchases.to_vec();
+        ids.sort_unstable();
+        ids.dedup();
+
+        if ids.is_empty() {
+            return Err(PortalError::Auth(AuthError::BadRequest("handover_empty")));
+        }
+
+        let txn = self.db.begin().await.map_err(db_down)?;
+
+        let holder = users::Entity::find()
+            .filter(users::Column::AndrewId.eq(andrew_id))
+            .one(&txn)
+            .await
+            .map_err(db_down)?
+            .ok_or(PortalError::Auth(AuthError::NotFound("user_unknown")))?;
+
+        let rows = purchases::Entity::find()
+            .filter(purchases::Column::PurchaseId.is_in(ids.iter().copied()))
+            .lock_exclusive()
+            .all(&txn)
+            .await
+            .map_err(db_down)?;
+
+        handable(&rows, holder.id, ids.len())?;
+
+        let now: chrono::DateTime<chrono::FixedOffset> = chrono::Utc::now().into();
+
+        purchases::Entity::update_many()
+            .col_expr(
+                purchases::Column::ReceivedItemDate,
+                sea_query::Expr::current_date(),
+            )
+            .col_expr(purchases::Column::DeliveredAt, sea_query::Expr::value(now))
+            .col_expr(purchases::Column::DeliveredBy, sea_query::Expr::value(by))
+            .filter(purchases::Column::PurchaseId.is_in(ids.iter().copied()))
+            .exec(&txn)
+            .await
+            .map_err(db_down)?;
+
+        txn.commit().await.map_err(db_down)?;
+
+        Ok(HandedOver {
+            andrew_id: holder.andrew_id,
+            delivered_by: by.to_owned(),
+            delivered_at: now,
+            purchases: ids,
         })
     }
 
@@ -268,9 +614,59 @@ impl Desk {
             issued_at,
             scottycoins: balances.scottycoins,
             thistlestones: balances.thistlestones,
-            orders: self.orders(Some(andrew_id), None, Some(LEDGER_CAP)).await?,
+            orders: self
+                .orders(Some(andrew_id), None, None, Some(LEDGER_CAP))
+                .await?,
         })
     }
+    pub async fn auctions(&self) -> Result<Vec<AuctionDeskView>, PortalError> {
+        AuctionDeskView::find_by_statement(Statement::from_string(DbBackend::Postgres, AUCTIONS))
+            .all(&self.db)
+            .await
+            .map_err(sql_failed)
+    }
+
+    pub async fn pick_list(
+        &self,
+        slot: Option<Uuid>,
+        on: Option<Date>,
+    ) -> Result<Vec<PickSlotView>, PortalError> {
+        let rows = PickRow::find_by_statement(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            PICK_LIST,
+            [slot.into(), on.into()],
+        ))
+        .all(&self.db)
+        .await
+        .map_err(sql_failed)?;
+
+        let mut slots: Vec<PickSlotView> = Vec::new();
+        for row in rows {
+            let line = PickLineView {
+                item_id: row.item_id,
+                item: row.item,
+                options: row.options,
+                quantity: row.quantity,
+                orders: row.orders,
+                delivered: row.delivered,
+            };
+
+            match slots.last_mut() {
+                Some(last) if last.slot_id == row.slot_id => last.lines.push(line),
+                _ => slots.push(PickSlotView {
+                    slot_id: row.slot_id,
+                    location: row.location,
+                    starts_at: row.starts_at,
+                    ends_at: row.ends_at,
+                    capacity: row.capacity,
+                    lines: vec![line],
+                }),
+            }
+        }
+
+        Ok(slots)
+    }
+
     pub async fn sales(&self) -> Result<Vec<SalesItemView>, PortalError> {
         let totals = SalesTotalRow::find_by_statement(Statement::from_string(
             DbBackend::Postgres,
@@ -288,6 +684,32 @@ impl Desk {
         .await
         .map_err(sql_failed)?;
 
+        let refund_rows = RefundAuditView::find_by_statement(Statement::from_string(
+            DbBackend::Postgres,
+            SALES_REFUNDS,
+        ))
+        .all(&self.db)
+        .await
+        .map_err(sql_failed)?;
+
+        let coupon_rows = CouponSalesView::find_by_statement(Statement::from_string(
+            DbBackend::Postgres,
+            SALES_COUPONS,
+        ))
+        .all(&self.db)
+        .await
+        .map_err(sql_failed)?;
+
+        let mut coupons: HashMap<Uuid, Vec<CouponSalesView>> = HashMap::new();
+        for row in coupon_rows {
+            coupons.entry(row.item_id).or_default().push(row);
+        }
+
+        let mut refunds: HashMap<Uuid, Vec<RefundAuditView>> = HashMap::new();
+        for row in refund_rows {
+            refunds.entry(row.item_id).or_default().push(row);
+        }
+
         let mut options: HashMap<Uuid, Vec<SalesOptionView>> = HashMap::new();
 
         for row in option_rows {
@@ -303,12 +725,76 @@ impl Desk {
 
         Ok(totals
             .into_iter()
-            .map(|row| SalesItemView {
-                item_id: row.item_id,
-                item: row.item,
-                sold: row.sold,
-                options: options.remove(&row.item_id).unwrap_or_default(),
+            .map(|row| {
+                let refunds = refunds.remove(&row.item_id).unwrap_or_default();
+                SalesItemView {
+                    refunded: refunds.iter().map(|refund| refund.quantity).sum(),
+                    refunded_coins: refunds.iter().map(|refund| refund.amount).sum(),
+                    refunds,
+                    coupons: coupons.remove(&row.item_id).unwrap_or_default(),
+                    item_id: row.item_id,
+                    item: row.item,
+                    sold: row.sold,
+                    options: options.remove(&row.item_id).unwrap_or_default(),
+                }
             })
             .collect())
     }
 }
+
+#[cfg(test)]
+mod handover_tests {
+    use super::*;
+
+    fn order(id: i64, user: u128, ticket: Option<TicketState>) -> purchases::Model {
+        purchases::Model {
+            purchase_id: id,
+            user_id: Uuid::from_u128(user),
+            item_id: Uuid::from_u128(9),
+            quantity: 1,
+            unit_cost: 10,
+            received_item_date: None,
+            purchased_at: None,
+            ticket,
+            pickup_slot_id: None,
+            delivered_by: None,
+            delivered_at: None,
+        }
+    }
+
+    #[test]
+    fn a_holders_open_orders_can_be_handed_over() {
+        let rows = [order(1, 1, None), order(2, 1, Some(TicketState::Won))];
+        assert!(handable(&rows, Uuid::from_u128(1), 2).is_ok());
+    }
+
+    #[test]
+    fn missing_or_foreign_orders_are_unknown() {
+        let rows = [order(1, 1, None), order(2, 2, None)];
+        assert!(matches!(
+            handable(&rows, Uuid::from_u128(1), 2),
+            Err(AuthError::NotFound("purchase_unknown"))
+        ));
+        assert!(matches!(
+            handable(&rows[..1], Uuid::from_u128(1), 2),
+            Err(AuthError::NotFound("purchase_unknown"))
+        ));
+    }
+
+    #[test]
+    fn delivered_orders_and_unwon_tickets_stop_the_handover() {
+        let mut done = order(1, 1, None);
+        done.received_item_date = Some(chrono::Utc::now().date_naive());
+        assert!(matches!(
+            handable(&[done], Uuid::from_u128(1), 1),
+            Err(AuthError::Conflict("purchase_delivered"))
+        ));
+
+        for ticket in [TicketState::Entered, TicketState::Lost] {
+            assert!(matches!(
+                handable(&[order(1, 1, Some(ticket))], Uuid::from_u128(1), 1),
+                Err(AuthError::Conflict("ticket_not_won"))
+            ));
+        }
+    }
+}
@@ -23,6 +23,7 @@ pub fn router(staff: Staff, taps: Taps) -> OpenApiRouter {
         .routes(routes!(read))
         .routes(routes!(link, unlink))
         .routes(routes!(place))
+        .routes(routes!(kiosk))
         .with_state(Desk { staff, taps })
 }
 
@@ -189,6 +190,44 @@ async fn place(
     view(&desk.staff, &card).await
 }
 
+#[derive(Serialize, ToSchema)]
+struct KioskCode {
+    payload: String,
+    window: i64,
+    period: i32,
+    expires_at: i64,
+}
+
+#[utoipa::path(
+    get,
+    path = "/staff/challenges/{id}/qr",
+    tag = "staff",
+    params(("id" = Uuid, Path, description = "Challenge id")),
+    responses(
+        (status = OK, body = KioskCode),
+        (status = UNAUTHORIZED, body = AuthErrBody),
+        (status = FORBIDDEN, body = AuthErrBody),
+        (status = NOT_FOUND, body = AuthErrBody),
+        (status = BAD_GATEWAY, body = AuthErrBody),
+    ),
+)]
+async fn kiosk(
+    State(desk): State<Desk>,
+    CurrentUser(user): CurrentUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<KioskCode>, AuthError> {
+    allowed(&user)?;
+
+    let code = desk.taps.code(id).await?;
+
+    Ok(Json(KioskCode {
+        payload: code.payload,
+        window: code.window,
+        period: code.period,
+        expires_at: code.expires_at,
+    }))
+}
+
 async fn view(staff: &Staff, card_id: &str) -> Result<Json<CardView>, AuthError> {
     let placement = staff.placement(card_id).await?;
 
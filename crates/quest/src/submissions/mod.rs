@@ -0,0 +1,274 @@
+pub mod routes;
+
+use entity::enums::{SubmissionStatus, TapChannel};
+use entity::{challenge, challenge_photo, submission, tap_events};
+use sea_orm::prelude::Uuid;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, DatabaseConnection, DbBackend, DbErr, EntityTrait,
+    FromQueryResult, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, SqlErr, Statement,
+    TransactionTrait,
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::auth::AuthError;
+use crate::challenges::missing;
+use crate::taps::complete_in;
+
+pub const MAX_BYTES: usize = 12 * 1024 * 1024;
+
+pub const PHOTO_TYPES: &[&str] = &["image/png", "image/jpeg", "image/webp", "image/avif"];
+
+const QUEUE_CAP: u64 = 200;
+
+const QUEUE: &str = r#"
+SELECT s."id",
+       u."andrew_id",
+       s."challenge_id",
+       c."name"   AS "challenge",
+       p."prompt",
+       CASE WHEN s."asset_key" IS NOT NULL
+            THEN '/portal/submissions/' || s."id" || '/photo'
+       END AS "url",
+       s."created_at"
+FROM "submission" s
+JOIN "users" u ON u."id" = s."user_id"
+JOIN "challenge" c ON c."id" = s."challenge_id"
+LEFT JOIN "challenge_photo" p ON p."challenge_id" = s."challenge_id"
+WHERE s."status" = 'pending'
+ORDER BY s."created_at" ASC, s."id" ASC
+LIMIT $1
+"#;
+
+#[derive(Clone)]
+pub struct Submissions {
+    db: DatabaseConnection,
+}
+
+#[derive(Debug, FromQueryResult, Serialize, ToSchema)]
+pub struct Queued {
+    pub id: i64,
+    pub andrew_id: String,
+    pub challenge_id: Uuid,
+    pub challenge: String,
+    pub prompt: Option<String>,
+    /// Staff-only path the photo is served from.
+    pub url: Option<String>,
+    pub created_at: chrono::DateTime<chrono::FixedOffset>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Reviewed {
+    pub id: i64,
+    pub status: String,
+    pub first: bool,
+}
+
+impl Submissions {
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self { db }
+    }
+
+    pub async fn photo(
+        &self,
+        challenge: Uuid,
+    ) -> Result<(challenge::Model, challenge_photo::Model), AuthError> {
+        let (found, photo) = challenge::Entity::find_by_id(challenge)
+            .find_also_related(challenge_photo::Entity)
+            .one(&self.db)
+            .await
+            .map_err(db_down)?
+            .ok_or(AuthError::NotFound("challenge_unknown"))?;
+
+        let photo = photo.ok_or(AuthError::NotFound("photo_prompt_unknown"))?;
+        Ok((found, photo))
+    }
+
+    /// Refuses before anything is uploaded, so a doomed submission never
+    /// leaves an orphaned photo in the bucket.
+    pub async fn ready(&self, user: Uuid, found: &challenge::Model) -> Result<(), AuthError> {
+        let challenge = found.id;
+        let cleared = tap_events::Entity::find()
+            .filter(tap_events::Column::UserId.eq(user))
+            .filter(tap_events::Column::ChallengeId.eq(challenge))
+            .count(&self.db)
+            .await
+            .map_err(db_down)?;
+
+        if cleared > 0 {
+            return Err(AuthError::Conflict("challenge_cleared"));
+        }
+
+        let pending = submission::Entity::find()
+            .filter(submission::Column::UserId.eq(user))
+            .filter(submission::Column::ChallengeId.eq(challenge))
+            .filter(submission::Column::Status.eq(SubmissionStatus::Pending))
+            .count(&self.db)
+            .await
+            .map_err(db_down)?;
+
+        if pending > 0 {
+            return Err(AuthError::Conflict("submission_pending"));
+        }
+
+        let gated = missing(&self.db, user, std::slice::from_ref(found)).await?;
+        if gated.contains_key(&challenge) {
+            return Err(AuthError::Conflict("prerequisites_missing"));
+        }
+
+        Ok(())
+    }
+
+    pub async fn file(
+        &self,
+        user: Uuid,
+        challenge: Uuid,
+        asset_key: String,
+    ) -> Result<submission::Model, AuthError> {
+        submission::ActiveModel {
+            user_id: ActiveValue::Set(user),
+            challenge_id: ActiveValue::Set(challenge),
+            asset_key: ActiveValue::Set(Some(asset_key)),
+            status: ActiveValue::Set(SubmissionStatus::Pending),
+            ..Default::default()
+        }
+        .insert(&self.db)
+        .await
+        .map_err(|err| match err.sql_err() {
+            Some(SqlErr::UniqueConstraintViolation(_)) => AuthError::Conflict("submission_pending"),
+            _ => db_down(err),
+        })
+    }
+
+    pub async fn latest(
+        &self,
+        user: Uuid,
+        challenge: Uuid,
+    ) -> Result<Option<submission::Model>, AuthError> {
+        submission::Entity::find()
+            .filter(submission::Column::UserId.eq(user))
+            .filter(submission::Column::ChallengeId.eq(challenge))
+            .order_by_desc(submission::Column::CreatedAt)
+            .order_by_desc(submission::Column::Id)
+            .one(&self.db)
+            .await
+            .map_err(db_down)
+    }
+
+    /// The stored photo key of one submission, for the review queue.
+    pub async fn photo_key(&self, id: i64) -> Result<String, AuthError> {
+        submission::Entity::find_by_id(id)
+            .one(&self.db)
+            .await
+            .map_err(db_down)?
+            .ok_or(AuthError::NotFound("submission_unknown"))?
+            .asset_key
+            .ok_or(AuthError::NotFound("photo_unknown"))
+    }
+
+    pub async fn queue(&self, limit: Option<u64>) -> Result<Vec<Queued>, AuthError> {
+        let limit = limit.unwrap_or(50).clamp(1, QUEUE_CAP) as i64;
+
+        Queued::find_by_statement(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            QUEUE,
+            [limit.into()],
+        ))
+        .all(&self.db)
+        .await
+        .map_err(db_down)
+    }
+
+    /// Approval and the completion it grants commit together.
+    pub async fn approve(&self, id: i64, by: &str) -> Result<Reviewed, AuthError> {
+        let txn = self.db.begin().await.map_err(db_down)?;
+
+        let row = submission::Entity::find_by_id(id)
+            .lock_exclusive()
+            .one(&txn)
+            .await
+            .map_err(db_down)?
+            .ok_or(AuthError::NotFound("submission_unknown"))?;
+
+        if row.status != SubmissionStatus::Pending {
+            txn.rollback().await.ok();
+            return Err(AuthError::Conflict("submission_reviewed"));
+        }
+
+        submission::ActiveModel {
+            id: ActiveValue::Unchanged(row.id),
+            status: ActiveValue::Set(SubmissionStatus::Approved),
+            reviewed_by: ActiveValue::Set(Some(by.to_owned())),
+            reviewed_at: ActiveValue::Set(Some(chrono::Utc::now().into())),
+            ..Default::default()
+        }
+        .update(&txn)
+        .await
+        .map_err(db_down)?;
+
+        let done =
+            complete_in(&txn, row.challenge_id, row.user_id, TapChannel::Photo, None).await?;
+
+        txn.commit().await.map_err(db_down)?;
+
+        Ok(Reviewed {
+            id: row.id,
+            status: status_name(SubmissionStatus::Approved).to_owned(),
+            first: done.first,
+        })
+    }
+
+    pub async fn reject(&self, id: i64, by: &str, reason: &str) -> Result<Reviewed, AuthError> {
+        let reason = reason.trim();
+        if reason.is_empty() || reason.chars().count() > 200 {
+            return Err(AuthError::BadRequest("reject_reason_invalid"));
+        }
+
+        let txn = self.db.begin().await.map_err(db_down)?;
+
+        let row = submission::Entity::find_by_id(id)
+            .lock_exclusive()
+            .one(&txn)
+            .await
+            .map_err(db_down)?
+            .ok_or(AuthError::NotFound("submission_unknown"))?;
+
+        if row.status != SubmissionStatus::Pending {
+            txn.rollback().await.ok();
+            return Err(AuthError::Conflict("submission_reviewed"));
+        }
+
+        submission::ActiveModel {
+            id: ActiveValue::Unchanged(row.id),
+            status: ActiveValue::Set(SubmissionStatus::Rejected),
+            reason: ActiveValue::Set(Some(reason.to_owned())),
+            reviewed_by: ActiveValue::Set(Some(by.to_owned())),
+            reviewed_at: ActiveValue::Set(Some(chrono::Utc::now().into())),
+            ..Default::default()
+        }
+        .update(&txn)
+        .await
+        .map_err(db_down)?;
+
+        txn.commit().await.map_err(db_down)?;
+
+        Ok(Reviewed {
+            id: row.id,
+            status: status_name(SubmissionStatus::Rejected).to_owned(),
+            first: false,
+        })
+    }
+}
+
+pub fn status_name(status: SubmissionStatus) -> &'static str {
+    match status {
+        SubmissionStatus::Pending => "pending",
+        SubmissionStatus::Approved => "approved",
+        SubmissionStatus::Rejected => "rejected",
+    }
+}
+
+fn db_down(err: DbErr) -> AuthError {
+    eprintln!("submissions: {err}");
+    AuthError::Upstream("database_unavailable")
+}
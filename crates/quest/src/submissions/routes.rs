@@ -0,0 +1,204 @@
+use axum::body::Bytes;
+use axum::extract::{DefaultBodyLimit, Path, State};
+use axum::http::{HeaderMap, header};
+use axum::response::{IntoResponse, Response};
+use axum::{Extension, Json};
+use entity::submission;
+use sea_orm::prelude::Uuid;
+use serde::Serialize;
+use utoipa::ToSchema;
+use utoipa_axum::router::OpenApiRouter;
+use utoipa_axum::routes;
+
+use super::{MAX_BYTES, PHOTO_TYPES, Submissions, status_name};
+use crate::auth::extract::CurrentUser;
+use crate::auth::{AuthErrBody, AuthError};
+use crate::portal::assets::{AssetError, Assets};
+use crate::taps::locked;
+use crate::users::Users;
+
+pub fn router(submissions: Submissions, assets: Assets) -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(submit, status))
+        .routes(routes!(photo))
+        .layer(DefaultBodyLimit::max(MAX_BYTES + 4096))
+        .with_state((submissions, assets))
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SubmissionView {
+    id: i64,
+    challenge_id: String,
+    status: String,
+    reason: Option<String>,
+    url: Option<String>,
+    created_at: String,
+    reviewed_at: Option<String>,
+}
+
+impl SubmissionView {
+    fn new(row: submission::Model) -> Self {
+        // Photos sit in the private bucket; the owner reads theirs back
+        // through the route below rather than a CDN link.
+        let url = row
+            .asset_key
+            .is_some()
+            .then(|| format!("/challenges/{}/photo/image", row.challenge_id));
+
+        Self {
+            id: row.id,
+            challenge_id: row.challenge_id.to_string(),
+            status: status_name(row.status).to_owned(),
+            reason: row.reason,
+            url,
+            created_at: row.created_at.to_rfc3339(),
+            reviewed_at: row.reviewed_at.map(|at| at.to_rfc3339()),
+        }
+    }
+}
+
+fn photo_failed(err: AssetError) -> AuthError {
+    match err {
+        AssetError::Rejected("asset_unknown") => AuthError::NotFound("photo_unknown"),
+        AssetError::Rejected(code) => AuthError::BadRequest(code),
+        AssetError::Unconfigured => AuthError::Upstream("photo_storage_unavailable"),
+        AssetError::Upstream(detail) => {
+            eprintln!("submissions: {detail}");
+            AuthError::Upstream("photo_storage_unavailable")
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/challenges/{id}/photo",
+    tag = "challenges",
+    params(("id" = String, Path, description = "Challenge id")),
+    request_body(content = Vec<u8>, content_type = "image/jpeg"),
+    responses(
+        (status = OK, body = SubmissionView),
+        (status = BAD_REQUEST, body = AuthErrBody),
+        (status = NOT_FOUND, body = AuthErrBody),
+        (status = CONFLICT, body = AuthErrBody),
+        (status = PAYLOAD_TOO_LARGE, body = AuthErrBody),
+        (status = UNAUTHORIZED, body = AuthErrBody),
+        (status = BAD_GATEWAY, body = AuthErrBody),
+    ),
+)]
+async fn submit(
+    State((submissions, assets)): State<(Submissions, Assets)>,
+    Extension(users): Extension<Users>,
+    CurrentUser(user): CurrentUser,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    bytes: Bytes,
+) -> Result<Json<SubmissionView>, AuthError> {
+    let id = Uuid::parse_str(&id).map_err(|_| AuthError::BadRequest("challenge_id_invalid"))?;
+
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+
+    if !PHOTO_TYPES.contains(&content_type.as_str()) {
+        return Err(AuthError::BadRequest("photo_type_unsupported"));
+    }
+
+    let row = users.row(&user).await?;
+    let (challenge, _) = submissions.photo(id).await?;
+
+    if locked(&challenge) {
+        return Err(AuthError::Conflict("challenge_locked"));
+    }
+
+    submissions.ready(row.id, &challenge).await?;
+
+    let key = assets
+        .put_private(&content_type, bytes)
+        .await
+        .map_err(photo_failed)?;
+
+    let saved = submissions.file(row.id, challenge.id, key).await?;
+
+    Ok(Json(SubmissionView::new(saved)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/challenges/{id}/photo",
+    tag = "challenges",
+    params(("id" = String, Path, description = "Challenge id")),
+    responses(
+        (status = OK, body = SubmissionView),
+        (status = BAD_REQUEST, body = AuthErrBody),
+        (status = NOT_FOUND, body = AuthErrBody),
+        (status = UNAUTHORIZED, body = AuthErrBody),
+        (status = BAD_GATEWAY, body = AuthErrBody),
+    ),
+)]
+async fn status(
+    State((submissions, _)): State<(Submissions, Assets)>,
+    Extension(users): Extension<Users>,
+    CurrentUser(user): CurrentUser,
+    Path(id): Path<String>,
+) -> Result<Json<SubmissionView>, AuthError> {
+    let id = Uuid::parse_str(&id).map_err(|_| AuthError::BadRequest("challenge_id_invalid"))?;
+
+    let row = users.row(&user).await?;
+    let found = submissions
+        .latest(row.id, id)
+        .await?
+        .ok_or(AuthError::NotFound("submission_unknown"))?;
+
+    Ok(Json(SubmissionView::new(found)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/challenges/{id}/photo/image",
+    tag = "challenges",
+    params(("id" = String, Path, description = "Challenge id")),
+    responses(
+        (status = OK, description = "The player's latest photo for the challenge", content_type = "image/*"),
+        (status = BAD_REQUEST, body = AuthErrBody),
+        (status = NOT_FOUND, body = AuthErrBody),
+        (status = UNAUTHORIZED, body = AuthErrBody),
+        (status = BAD_GATEWAY, body = AuthErrBody),
+    ),
+)]
+async fn photo(
+    State((submissions, assets)): State<(Submissions, Assets)>,
+    Extension(users): Extension<Users>,
+    CurrentUser(user): CurrentUser,
+    Path(id): Path<String>,
+) -> Result<Response, AuthError> {
+    let id = Uuid::parse_str(&id).map_err(|_| AuthError::BadRequest("challenge_id_invalid"))?;
+
+    let row = users.row(&user).await?;
+    let key = submissions
+        .latest(row.id, id)
+        .await?
+        .and_then(|found| found.asset_key)
+        .ok_or(AuthError::NotFound("submission_unknown"))?;
+
+    let (mime, bytes) = assets.get_private(&key).await.map_err(photo_failed)?;
+
+    Ok(private_image(mime, bytes))
+}
+
+/// A stored photo, kept out of shared caches.
+pub fn private_image(mime: String, bytes: Bytes) -> Response {
+    (
+        [
+            (header::CONTENT_TYPE, mime),
+            (header::CACHE_CONTROL, "private, no-store".to_owned()),
+        ],
+        bytes,
+    )
+        .into_response()
+}
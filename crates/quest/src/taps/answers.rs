@@ -0,0 +1,184 @@
+use entity::enums::AnswerMatch;
+use entity::{challenge, challenge_question};
+use sea_orm::prelude::Uuid;
+use sea_orm::{
+    ConnectionTrait, DbBackend, EntityTrait, FromQueryResult, Statement, TransactionTrait,
+};
+
+use super::{Taps, advisory, db_down};
+use crate::auth::AuthError;
+
+// Wrong answers allowed per challenge inside the window before answering pauses.
+const MAX_WRONG: i64 = 5;
+
+const WINDOW_MINUTES: i32 = 10;
+
+const ANSWER_LIMIT: usize = 200;
+
+const WRONG_RECENTLY: &str = r#"
+SELECT COUNT(*)::BIGINT AS "wrong"
+FROM "answer_attempt"
+WHERE "user_id" = $1
+  AND "challenge_id" = $2
+  AND NOT "correct"
+  AND "created_at" > now() - make_interval(mins => $3)
+"#;
+
+const RECORD_ATTEMPT: &str = r#"
+INSERT INTO "answer_attempt" ("user_id", "challenge_id", "answer", "correct")
+VALUES ($1, $2, $3, $4)
+"#;
+
+#[derive(Debug, FromQueryResult)]
+struct Wrong {
+    wrong: i64,
+}
+
+impl Taps {
+    pub async fn question(
+        &self,
+        challenge: Uuid,
+    ) -> Result<(challenge::Model, challenge_question::Model), AuthError> {
+        let (found, question) = challenge::Entity::find_by_id(challenge)
+            .find_also_related(challenge_question::Entity)
+            .one(&self.db)
+            .await
+            .map_err(db_down)?
+            .ok_or(AuthError::NotFound("challenge_unknown"))?;
+
+        let question = question.ok_or(AuthError::NotFound("question_unknown"))?;
+        Ok((found, question))
+    }
+
+    /// Checks an answer and logs the attempt, refusing once a player has
+    /// burned through their wrong guesses for the window.
+    pub async fn attempt(
+        &self,
+        user: Uuid,
+        question: &challenge_question::Model,
+        answer: &str,
+    ) -> Result<bool, AuthError> {
+        let txn = self.db.begin().await.map_err(db_down)?;
+
+        // Count and insert under one lock, or parallel guesses all see the
+        // same count and slip past the limit together.
+        advisory(&txn, &format!("answer:{user}:{}", question.challenge_id)).await?;
+
+        let wrong = Wrong::find_by_statement(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            WRONG_RECENTLY,
+            [
+                user.into(),
+                question.challenge_id.into(),
+                WINDOW_MINUTES.into(),
+            ],
+        ))
+        .one(&txn)
+        .await
+        .map_err(db_down)?
+        .map_or(0, |count| count.wrong);
+
+        if wrong >= MAX_WRONG {
+            txn.rollback().await.ok();
+            return Err(AuthError::TooManyRequests("answer_rate_limited"));
+        }
+
+        let answers: Vec<String> =
+            serde_json::from_value(question.answers.clone()).unwrap_or_default();
+        let correct = accepts(question.matching, &answers, answer);
+
+        txn.execute_raw(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            RECORD_ATTEMPT,
+            [
+                user.into(),
+                question.challenge_id.into(),
+                clamp(answer).into(),
+                correct.into(),
+            ],
+        ))
+        .await
+        .map_err(db_down)?;
+
+        txn.commit().await.map_err(db_down)?;
+
+        Ok(correct)
+    }
+}
+
+pub fn accepts(matching: AnswerMatch, answers: &[String], given: &str) -> bool {
+    let given = normalise(given);
+    if given.is_empty() {
+        return false;
+    }
+
+    match matching {
+        AnswerMatch::Exact => answers
+            .first()
+            .is_some_and(|answer| normalise(answer) == given),
+        AnswerMatch::CaseInsensitive => answers
+            .first()
+            .is_some_and(|answer| normalise(answer).to_lowercase() == given.to_lowercase()),
+        AnswerMatch::AnyOf => answers
+            .iter()
+            .any(|answer| normalise(answer).to_lowercase() == given.to_lowercase()),
+    }
+}
+
+// Phone keyboards add trailing spaces and double spaces freely.
+fn normalise(raw: &str) -> String {
+    raw.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn clamp(answer: &str) -> String {
+    answer.chars().take(ANSWER_LIMIT).collect()
+}
+
+#[cfg(test)]
+mod accepts_tests {
+    use super::*;
+
+    fn answers(list: &[&str]) -> Vec<String> {
+        list.iter().map(|answer| (*answer).to_owned()).collect()
+    }
+
+    #[test]
+    fn exact_only_forgives_whitespace() {
+        let expected = answers(&["Carnegie  Mellon"]);
+        assert!(accepts(AnswerMatch::Exact, &expected, " Carnegie Mellon "));
+        assert!(!accepts(AnswerMatch::Exact, &expected, "carnegie mellon"));
+    }
+
+    #[test]
+    fn case_insensitive_ignores_case() {
+        let expected = answers(&["Scotty  Dog"]);
+        assert!(accepts(
+            AnswerMatch::CaseInsensitive,
+            &expected,
+            "sCOTTY dog "
+        ));
+        assert!(!accepts(AnswerMatch::CaseInsensitive, &expected, "scotty"));
+    }
+
+    #[test]
+    fn any_of_matches_every_listed_answer() {
+        let expected = answers(&["Scotty", "Tartan"]);
+        assert!(accepts(AnswerMatch::AnyOf, &expected, "scotty"));
+        assert!(accepts(AnswerMatch::AnyOf, &expected, "TARTAN "));
+        assert!(!accepts(AnswerMatch::AnyOf, &expected, "kiltie"));
+    }
+
+    #[test]
+    fn blank_answers_never_match() {
+        assert!(!accepts(AnswerMatch::AnyOf, &answers(&[""]), "   "));
+        assert!(!accepts(AnswerMatch::Exact, &[], "scotty"));
+    }
+
+    #[test]
+    fn stored_answers_are_clamped() {
+        assert_eq!(
+            clamp(&"é".repeat(ANSWER_LIMIT + 5)).chars().count(),
+            ANSWER_LIMIT
+        );
+    }
+}
@@ -1,14 +1,17 @@
+mod answers;
+pub mod qr;
 pub mod routes;
 
 use std::sync::Arc;
 
+use entity::enums::TapChannel;
 use entity::geography::Point;
 use entity::{challenge, challenge_card, failed_taps, tap_events};
 use quest::crypto::{VerifyError, verify_tap};
 use sea_orm::prelude::Uuid;
 use sea_orm::{
-    ActiveValue, ColumnTrait, ConnectionTrait, DatabaseConnection, DbBackend, DbErr, EntityTrait,
-    PaginatorTrait, QueryFilter, QuerySelect, Statement, TransactionTrait,
+    ActiveValue, ColumnTrait, ConnectionTrait, DatabaseConnection, DatabaseTransaction, DbBackend,
+    DbErr, EntityTrait, PaginatorTrait, QueryFilter, QuerySelect, Statement, TransactionTrait,
 };
 
 use crate::auth::AuthError;
@@ -97,7 +100,7 @@ pub fn locked(challenge: &challenge::Model) -> bool {
     challenge.open_from > chrono::Utc::now()
 }
 
-const REASONS: [&str; 11] = [
+const REASONS: [&str; 14] = [
     "tap_body_invalid",
     "tap_url_malformed",
     "tap_signature",
@@ -109,6 +112,9 @@ const REASONS: [&str; 11] = [
     "tap_replayed",
     "location_too_coarse",
     "no_location_fix",
+    "qr_unassigned",
+    "qr_expired",
+    "prerequisites_missing",
 ];
 
 const URL_LIMIT: usize = 512;
@@ -179,13 +185,11 @@ impl Taps {
     ) -> Result<Recorded, AuthError> {
         let txn = self.db.begin().await.map_err(db_down)?;
 
-        txn.execute_raw(Statement::from_sql_and_values(
-            DbBackend::Postgres,
-            "SELECT pg_advisory_xact_lock(hashtext($1))",
-            [card_id.into()],
-        ))
-        .await
-        .map_err(db_down)?;
+        // The card lock orders counters; the completion lock is the one
+        // answers, QR scans and photo approvals take, so no two channels can
+        // both pay out for the same challenge.
+        advisory(&txn, card_id).await?;
+        advisory(&txn, &completion_key(user, challenge_id)).await?;
 
         let mine = tap_events::Entity::find()
             .filter(tap_events::Column::ChallengeId.eq(challenge_id))
@@ -205,7 +209,7 @@ impl Taps {
             txn.rollback().await.ok();
             return Ok(Recorded {
                 // same counter = this exact tap delivered twice, not a re-tap
-                first: row.counter == counter,
+                first: row.counter == Some(counter),
                 place: before as i64 + 1,
             });
         }
@@ -233,12 +237,13 @@ impl Taps {
 
         let fresh = tap_events::ActiveModel {
             challenge_id: ActiveValue::Set(challenge_id),
-            card_id: ActiveValue::Set(card_id.to_owned()),
-            counter: ActiveValue::Set(counter),
+            card_id: ActiveValue::Set(Some(card_id.to_owned())),
+            counter: ActiveValue::Set(Some(counter)),
             time: ActiveValue::Set(now()),
             location: ActiveValue::Set(fix.map(|fix| fix.at)),
             accuracy: ActiveValue::Set(fix.and_then(|fix| fix.accuracy)),
             user_id: ActiveValue::Set(user),
+            channel: ActiveValue::Set(TapChannel::Nfc),
             ..Default::default()
         };
 
@@ -254,6 +259,32 @@ impl Taps {
         })
     }
 
+    /// Prerequisites of `challenge` that `user` has yet to clear.
+    pub async fn missing(
+        &self,
+        user: Uuid,
+        challenge: &challenge::Model,
+    ) -> Result<Vec<Uuid>, AuthError> {
+        let mut gated =
+            crate::challenges::missing(&self.db, user, std::slice::from_ref(challenge)).await?;
+        Ok(gated.remove(&challenge.id).unwrap_or_default())
+    }
+
+    /// Records a completion that arrived without a card. It lands in
+    /// `tap_events` like any tap so balances and standings need no changes.
+    pub async fn complete(
+        &self,
+        challenge_id: Uuid,
+        user: Uuid,
+        channel: TapChannel,
+        fix: Option<Fix>,
+    ) -> Result<Recorded, AuthError> {
+        let txn = self.db.begin().await.map_err(db_down)?;
+        let done = complete_in(&txn, challenge_id, user, channel, fix).await?;
+        txn.commit().await.map_err(db_down)?;
+        Ok(done)
+    }
+
     /// Records a rejected stage and passes the result through unchanged.
     pub async fn audited<T>(
         &self,
@@ -299,6 +330,84 @@ impl Taps {
     }
 }
 
+/// [`Taps::complete`] on a caller's transaction, for callers whose own
+/// write has to land together with the completion.
+pub async fn complete_in(
+    txn: &DatabaseTransaction,
+    challenge_id: Uuid,
+    user: Uuid,
+    channel: TapChannel,
+    fix: Option<Fix>,
+) -> Result<Recorded, AuthError> {
+    advisory(txn, &completion_key(user, challenge_id)).await?;
+
+    let mine = tap_events::Entity::find()
+        .filter(tap_events::Column::ChallengeId.eq(challenge_id))
+        .filter(tap_events::Column::UserId.eq(user))
+        .one(txn)
+        .await
+        .map_err(db_down)?;
+
+    if let Some(row) = mine {
+        let before = tap_events::Entity::find()
+            .filter(tap_events::Column::ChallengeId.eq(challenge_id))
+            .filter(tap_events::Column::Time.lt(row.time))
+            .count(txn)
+            .await
+            .map_err(db_down)?;
+
+        return Ok(Recorded {
+            first: false,
+            place: before as i64 + 1,
+        });
+    }
+
+    let before = tap_events::Entity::find()
+        .filter(tap_events::Column::ChallengeId.eq(challenge_id))
+        .count(txn)
+        .await
+        .map_err(db_down)?;
+
+    let fresh = tap_events::ActiveModel {
+        challenge_id: ActiveValue::Set(challenge_id),
+        card_id: ActiveValue::Set(None),
+        counter: ActiveValue::Set(None),
+        time: ActiveValue::Set(now()),
+        location: ActiveValue::Set(fix.map(|fix| fix.at)),
+        accuracy: ActiveValue::Set(fix.and_then(|fix| fix.accuracy)),
+        user_id: ActiveValue::Set(user),
+        channel: ActiveValue::Set(channel),
+        ..Default::default()
+    };
+
+    tap_events::Entity::insert(fresh)
+        .exec_without_returning(txn)
+        .await
+        .map_err(db_down)?;
+
+    Ok(Recorded {
+        first: true,
+        place: before as i64 + 1,
+    })
+}
+
+/// Holds a transaction-scoped advisory lock on `key` until commit.
+pub(crate) async fn advisory<C: ConnectionTrait>(conn: &C, key: &str) -> Result<(), AuthError> {
+    conn.execute_raw(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        "SELECT pg_advisory_xact_lock(hashtext($1))",
+        [key.into()],
+    ))
+    .await
+    .map_err(db_down)?;
+
+    Ok(())
+}
+
+fn completion_key(user: Uuid, challenge_id: Uuid) -> String {
+    format!("{user}:{challenge_id}")
+}
+
 fn param<'q>(query: &'q str, key: &str) -> Option<&'q str> {
     query
         .split('&')
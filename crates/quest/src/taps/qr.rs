@@ -0,0 +1,195 @@
+use entity::{challenge, challenge_qr};
+use quest::crypto::{qr_tag, verify_qr};
+use sea_orm::EntityTrait;
+use sea_orm::prelude::Uuid;
+
+use super::{Taps, db_down, now, param};
+use crate::auth::AuthError;
+
+// Same landing URL the cards carry, so a phone camera opens the app too.
+const QR_BASE: &str = "https://cmu.quest/tap";
+
+pub struct Code {
+    pub payload: String,
+    pub window: i64,
+    pub period: i32,
+    pub expires_at: i64,
+}
+
+/// The `q` parameter of a scanned URL, present only on kiosk codes.
+pub fn payload(url: &str) -> Option<&str> {
+    let (_, query) = url.split_once('?')?;
+    param(query, "q")
+}
+
+impl Taps {
+    async fn rotating(
+        &self,
+        challenge: Uuid,
+    ) -> Result<(challenge::Model, challenge_qr::Model), AuthError> {
+        let (found, qr) = challenge::Entity::find_by_id(challenge)
+            .find_also_related(challenge_qr::Entity)
+            .one(&self.db)
+            .await
+            .map_err(db_down)?
+            .ok_or(AuthError::NotFound("challenge_unknown"))?;
+
+        let qr = qr.ok_or(AuthError::NotFound("qr_unassigned"))?;
+        Ok((found, qr))
+    }
+
+    pub async fn code(&self, challenge: Uuid) -> Result<Code, AuthError> {
+        let (_, qr) = self.rotating(challenge).await?;
+        let period = i64::from(qr.period);
+        let window = now() / period;
+        let tag = qr_tag(&self.master, challenge.as_bytes(), window as u64);
+
+        Ok(Code {
+            payload: format!(
+                "{QR_BASE}?q={}.{window}.{}",
+                challenge.simple(),
+                hex::encode(tag)
+            ),
+            window,
+            period: qr.period,
+            expires_at: (window + 1) * period,
+        })
+    }
+
+    /// Checks a kiosk payload. Only the current window and the one before it
+    /// are accepted, so a photographed code stops working within two periods.
+    pub async fn scan(&self, raw: &str) -> Result<challenge::Model, AuthError> {
+        let scanned = Scanned::parse(raw)?;
+        scanned.signed(&self.master)?;
+
+        let (found, qr) = self.rotating(scanned.id).await?;
+        fresh(scanned.window, now() / i64::from(qr.period))?;
+
+        Ok(found)
+    }
+}
+
+struct Scanned {
+    id: Uuid,
+    window: i64,
+    tag: [u8; 16],
+}
+
+impl Scanned {
+    fn parse(raw: &str) -> Result<Self, AuthError> {
+        let malformed = AuthError::BadRequest("tap_url_malformed");
+
+        let mut parts = raw.split('.');
+        let (Some(id), Some(window), Some(tag), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(malformed);
+        };
+
+        Ok(Self {
+            id: Uuid::try_parse(id).map_err(|_| malformed)?,
+            window: window.parse().map_err(|_| malformed)?,
+            tag: hex::decode(tag)
+                .map_err(|_| malformed)?
+                .try_into()
+                .map_err(|_| malformed)?,
+        })
+    }
+
+    fn signed(&self, master: &[u8; 32]) -> Result<(), AuthError> {
+        if self.window < 0 || !verify_qr(master, self.id.as_bytes(), self.window as u64, &self.tag)
+        {
+            return Err(AuthError::Unauthorized("tap_signature"));
+        }
+        Ok(())
+    }
+}
+
+fn fresh(window: i64, current: i64) -> Result<(), AuthError> {
+    if window > current {
+        return Err(AuthError::Unauthorized("tap_signature"));
+    }
+    if window < current - 1 {
+        return Err(AuthError::Conflict("qr_expired"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MASTER: [u8; 32] = [7; 32];
+    const WINDOW: i64 = 59_546_666;
+
+    fn code_for(challenge: Uuid, window: i64, tag_for: Uuid) -> String {
+        let tag = qr_tag(&MASTER, tag_for.as_bytes(), window as u64);
+        format!("{}.{window}.{}", challenge.simple(), hex::encode(tag))
+    }
+
+    fn scanned(raw: &str) -> Result<i64, AuthError> {
+        let scanned = Scanned::parse(raw)?;
+        scanned.signed(&MASTER)?;
+        fresh(scanned.window, WINDOW)?;
+        Ok(scanned.window)
+    }
+
+    #[test]
+    fn current_and_previous_windows_are_accepted() {
+        let id = Uuid::from_u128(1);
+
+        assert_eq!(scanned(&code_for(id, WINDOW, id)).unwrap(), WINDOW);
+        assert_eq!(scanned(&code_for(id, WINDOW - 1, id)).unwrap(), WINDOW - 1);
+    }
+
+    #[test]
+    fn older_windows_have_expired() {
+        let id = Uuid::from_u128(1);
+
+        assert!(matches!(
+            scanned(&code_for(id, WINDOW - 2, id)),
+            Err(AuthError::Conflict("qr_expired"))
+        ));
+    }
+
+    #[test]
+    fn future_windows_are_refused() {
+        let id = Uuid::from_u128(1);
+
+        assert!(matches!(
+            scanned(&code_for(id, WINDOW + 1, id)),
+            Err(AuthError::Unauthorized("tap_signature"))
+        ));
+    }
+
+    #[test]
+    fn tags_only_verify_for_their_challenge() {
+        let id = Uuid::from_u128(1);
+        let other = Uuid::from_u128(2);
+
+        assert!(matches!(
+            scanned(&code_for(id, WINDOW, other)),
+            Err(AuthError::Unauthorized("tap_signature"))
+        ));
+        assert!(!verify_qr(
+            &[8; 32],
+            id.as_bytes(),
+            WINDOW as u64,
+            &qr_tag(&MASTER, id.as_bytes(), WINDOW as u64),
+        ));
+    }
+
+    #[test]
+    fn malformed_payloads_are_rejected() {
+        for raw in ["", "abc", "a.b.c", "a.b.c.d"] {
+            assert!(matches!(
+                Scanned::parse(raw),
+                Err(AuthError::BadRequest("tap_url_malformed"))
+            ));
+        }
+        assert_eq!(
+            payload("https://cmu.quest/tap?q=abc.1.ff&x=1"),
+            Some("abc.1.ff")
+        );
+    }
+}
@@ -1,13 +1,17 @@
-use axum::extract::State;
 use axum::extract::rejection::JsonRejection;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
 use axum::{Extension, Json};
+use entity::enums::TapChannel;
 use entity::geography::Point;
+use sea_orm::prelude::Uuid;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 use utoipa_axum::router::OpenApiRouter;
 use utoipa_axum::routes;
 
-use super::{Attempt, Fix, Proximity, Taps, locked, proximity};
+use super::{Attempt, Fix, Proximity, Taps, locked, proximity, qr};
 use crate::auth::extract::{CurrentDevice, CurrentUser};
 use crate::auth::{AuthErrBody, AuthError};
 use crate::challenges::routes::ChallengeView;
@@ -17,6 +21,7 @@ use crate::users::Users;
 pub fn router(taps: Taps, tokens: Tokens) -> OpenApiRouter {
     OpenApiRouter::new()
         .routes(routes!(register))
+        .routes(routes!(answer))
         .with_state((taps, tokens))
 }
 
@@ -30,6 +35,41 @@ struct TapBody {
     location_enabled: bool,
 }
 
+#[derive(Serialize, ToSchema)]
+struct TapErrBody {
+    error: &'static str,
+    /// Challenge ids still to clear, for `prerequisites_missing`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    missing: Vec<String>,
+}
+
+enum TapError {
+    Auth(AuthError),
+    Prerequisites(Vec<Uuid>),
+}
+
+impl From<AuthError> for TapError {
+    fn from(err: AuthError) -> Self {
+        Self::Auth(err)
+    }
+}
+
+impl IntoResponse for TapError {
+    fn into_response(self) -> Response {
+        match self {
+            Self::Auth(err) => err.into_response(),
+            Self::Prerequisites(missing) => (
+                StatusCode::CONFLICT,
+                Json(TapErrBody {
+                    error: "prerequisites_missing",
+                    missing: missing.iter().map(Uuid::to_string).collect(),
+                }),
+            )
+                .into_response(),
+        }
+    }
+}
+
 #[derive(Serialize, ToSchema)]
 struct Registered {
     challenge: ChallengeView,
@@ -50,7 +90,7 @@ struct Registered {
         (status = BAD_REQUEST, body = AuthErrBody),
         (status = UNAUTHORIZED, body = AuthErrBody),
         (status = NOT_FOUND, body = AuthErrBody),
-        (status = CONFLICT, body = AuthErrBody),
+        (status = CONFLICT, body = TapErrBody),
         (status = BAD_GATEWAY, body = AuthErrBody),
     ),
 )]
@@ -60,7 +100,7 @@ async fn register(
     CurrentUser(user): CurrentUser,
     CurrentDevice(device): CurrentDevice,
     body: Result<Json<TapBody>, JsonRejection>,
-) -> Result<Json<Registered>, AuthError> {
+) -> Result<Json<Registered>, TapError> {
     let row = users.row(&user).await?;
 
     let mut attempt = Attempt {
@@ -90,36 +130,122 @@ async fn register(
 
     attempt.fix = fix;
 
-    let read = taps.audited(&attempt, taps.read(&body.url)).await?;
+    let (challenge, read) = match qr::payload(&body.url) {
+        Some(code) => {
+            let challenge = taps.audited(&attempt, taps.scan(code).await).await?;
+            (challenge, None)
+        }
+        None => {
+            let read = taps.audited(&attempt, taps.read(&body.url)).await?;
 
-    attempt.card_id = Some(read.card_id.clone());
-    attempt.counter = Some(read.counter);
+            attempt.card_id = Some(read.card_id.clone());
+            attempt.counter = Some(read.counter);
 
-    let challenge = taps
-        .audited(&attempt, taps.challenge_for(&read.card_id).await)
-        .await?;
+            let challenge = taps
+                .audited(&attempt, taps.challenge_for(&read.card_id).await)
+                .await?;
+            (challenge, Some(read))
+        }
+    };
 
     attempt.challenge_id = Some(challenge.id);
 
     if locked(&challenge) {
         let shut = AuthError::Conflict("card_locked");
-        return Err(taps.rejected(&attempt, shut).await);
+        return Err(taps.rejected(&attempt, shut).await.into());
+    }
+
+    let missing = taps.missing(row.id, &challenge).await?;
+    if !missing.is_empty() {
+        let gated = AuthError::Conflict("prerequisites_missing");
+        taps.rejected(&attempt, gated).await;
+        return Err(TapError::Prerequisites(missing));
     }
 
     match proximity(challenge.location, attempt.fix, body.location_enabled) {
         Proximity::Accept => {}
         Proximity::Reject(reason) => {
             let out = AuthError::BadRequest(reason);
-            return Err(taps.rejected(&attempt, out).await);
+            return Err(taps.rejected(&attempt, out).await.into());
         }
     }
 
-    let done = taps
-        .audited(
-            &attempt,
+    let recorded = match &read {
+        Some(read) => {
             taps.record(challenge.id, &read.card_id, read.counter, row.id, fix)
-                .await,
-        )
+                .await
+        }
+        None => {
+            taps.complete(challenge.id, row.id, TapChannel::Qr, fix)
+                .await
+        }
+    };
+
+    let done = taps.audited(&attempt, recorded).await?;
+
+    let (purse, today) = tokio::try_join!(
+        tokens.balances(row.id, Scope::Lifetime),
+        tokens.balances(row.id, Scope::Today),
+    )?;
+
+    Ok(Json(Registered {
+        challenge: ChallengeView::new(challenge, true, false),
+        place: done.place,
+        first: done.first,
+        current_scottycoins: purse.scottycoins,
+        current_thistlestones: today.thistlestones,
+    }))
+}
+
+#[derive(Deserialize, ToSchema)]
+struct AnswerBody {
+    answer: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/challenges/{id}/answer",
+    tag = "taps",
+    params(("id" = String, Path, description = "Challenge id")),
+    request_body = AnswerBody,
+    responses(
+        (status = OK, body = Registered),
+        (status = BAD_REQUEST, body = AuthErrBody),
+        (status = UNAUTHORIZED, body = AuthErrBody),
+        (status = NOT_FOUND, body = AuthErrBody),
+        (status = CONFLICT, body = TapErrBody),
+        (status = TOO_MANY_REQUESTS, body = AuthErrBody),
+        (status = BAD_GATEWAY, body = AuthErrBody),
+    ),
+)]
+async fn answer(
+    State((taps, tokens)): State<(Taps, Tokens)>,
+    Extension(users): Extension<Users>,
+    CurrentUser(user): CurrentUser,
+    Path(id): Path<String>,
+    body: Result<Json<AnswerBody>, JsonRejection>,
+) -> Result<Json<Registered>, TapError> {
+    let id = Uuid::parse_str(&id).map_err(|_| AuthError::BadRequest("challenge_id_invalid"))?;
+    let Json(body) = body.map_err(|_| AuthError::BadRequest("answer_body_invalid"))?;
+
+    let row = users.row(&user).await?;
+    let (challenge, question) = taps.question(id).await?;
+
+    if locked(&challenge) {
+        return Err(AuthError::Conflict("challenge_locked").into());
+    }
+
+    let missing = taps.missing(row.id, &challenge).await?;
+    if !missing.is_empty() {
+        return Err(TapError::Prerequisites(missing));
+    }
+
+    if !taps.attempt(row.id, &question, &body.answer).await? {
+        return Err(AuthError::BadRequest("answer_incorrect").into());
+    }
+
+    let done = taps
+        .complete(challenge.id, row.id, TapChannel::Answer, None)
         .await?;
 
     let (purse, today) = tokio::try_join!(
@@ -46,18 +46,46 @@ earned AS (
        OR target."day" = tap."day"
 ),
 spent AS (
+    -- What checkout actually charged, not today's list price: option
+    -- surcharges, coupons and winning bids all land in unit_cost.
     SELECT
         COALESCE(
-            SUM("purchases"."quantity" * "items"."cost"),
+            SUM("purchases"."quantity" * "purchases"."unit_cost"),
             0
         )::BIGINT AS "total"
     FROM "purchases"
-    JOIN "items"
-        ON "items"."id" = "purchases"."item_id"
     CROSS JOIN target
     WHERE "purchases"."user_id" = $1
       AND target."day" IS NULL
 ),
+unlocked AS (
+    SELECT
+        COALESCE(SUM("hint_unlock"."cost"), 0)::BIGINT AS "total"
+    FROM "hint_unlock"
+    CROSS JOIN target
+    WHERE "hint_unlock"."user_id" = $1
+      AND target."day" IS NULL
+),
+withheld AS (
+    SELECT
+        COALESCE(
+            SUM("refund"."quantity" * "refund"."unit_cost" - "refund"."amount"),
+            0
+        )::BIGINT AS "total"
+    FROM "refund"
+    CROSS JOIN target
+    WHERE "refund"."user_id" = $1
+      AND target."day" IS NULL
+),
+escrowed AS (
+    SELECT
+        COALESCE(SUM("bid"."amount"), 0)::BIGINT AS "total"
+    FROM "bid"
+    CROSS JOIN target
+    WHERE "bid"."user_id" = $1
+      AND "bid"."released_at" IS NULL
+      AND target."day" IS NULL
+),
 capped AS (
     SELECT
         "day",
@@ -117,7 +145,8 @@ SELECT
         COALESCE(
             (SELECT SUM("coin_value") FROM earned),
             0
-        ) - spent."total"
+        ) - spent."total" - unlocked."total" - escrowed."total"
+            - withheld."total"
     )::BIGINT AS "scottycoins",
     CASE
         WHEN COALESCE(
@@ -130,7 +159,7 @@ SELECT
         )::BIGINT
         ELSE 0::BIGINT
     END AS "thistlestones"
-FROM target, spent
+FROM target, spent, unlocked, escrowed, withheld
 "#
     )
 });